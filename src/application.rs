@@ -1,8 +1,11 @@
 use std::error::Error;
+use std::fs;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
+use cursive::theme::Theme;
 use cursive::traits::Nameable;
 use cursive::{Cursive, CursiveRunner};
 use log::{error, info, trace};
@@ -12,12 +15,14 @@ use signal_hook::{consts::SIGHUP, consts::SIGTERM, iterator::Signals};
 
 use crate::command::Command;
 use crate::commands::CommandManager;
-use crate::config::{Config, PlaybackState};
+use crate::config::{self, Config, PlaybackState, Profile};
 use crate::events::{Event, EventManager};
+use crate::ext_traits::CursiveExt;
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::spotify::{PlayerEvent, Spotify};
 use crate::ui::create_cursive;
+use crate::ui::layout::Layout;
 use crate::theme;
 use crate::{authentication, ui, utils};
 use crate::{command, queue, spotify};
@@ -64,13 +69,28 @@ struct PlaybackRecovery {
     should_resume: bool,
 }
 
+/// How often the configuration file is checked for changes.
+const CONFIG_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
 /// The global Tokio runtime for running asynchronous tasks.
 pub static ASYNC_RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 
+/// Create the global Tokio runtime, unless it already exists, and return it.
+pub fn init_async_runtime() -> &'static tokio::runtime::Runtime {
+    ASYNC_RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    })
+}
+
 /// The representation of an ncspot application.
 pub struct Application {
     /// The music queue which controls playback order.
     queue: Arc<Queue>,
+    /// The user library, which sends the changes made while offline after reconnecting.
+    library: Arc<Library>,
     /// Internally shared
     spotify: Spotify,
     /// Internally shared
@@ -79,6 +99,9 @@ pub struct Application {
     cfg: Arc<Config>,
     /// Playback state that can be restored after an unexpected worker restart.
     pending_recovery: Option<PlaybackRecovery>,
+    /// The MPRIS server of the current session.
+    #[cfg(feature = "mpris")]
+    mpris: MprisManager,
     /// An IPC implementation using the D-Bus MPRIS protocol, used to control and inspect ncspot.
     #[cfg(unix)]
     ipc: Option<IpcSocket>,
@@ -132,29 +155,103 @@ impl Application {
         self.spotify.update_track();
     }
 
+    /// Load the last played track of the user state into the player.
+    fn load_last_track(spotify: &Spotify, queue: &Queue, configuration: &Config) {
+        let playback_state = configuration.state().playback_state.clone();
+        let queue_state = configuration.state().queuestate.clone();
+
+        if let Some(playable) = queue.get_current() {
+            spotify.load(
+                &playable,
+                playback_state == PlaybackState::Playing,
+                queue_state.track_progress.as_millis() as u32,
+            );
+            spotify.update_track();
+            match playback_state {
+                PlaybackState::Stopped => {
+                    spotify.stop();
+                }
+                PlaybackState::Paused | PlaybackState::Playing | PlaybackState::Default => {
+                    spotify.pause();
+                }
+            }
+        }
+    }
+
+    /// Create the main layout with all screens for the given session.
+    fn create_layout(
+        cursive: &Cursive,
+        event_manager: &EventManager,
+        queue: &Arc<Queue>,
+        library: &Arc<Library>,
+        configuration: &Arc<Config>,
+        theme: Theme,
+    ) -> Layout {
+        let search =
+            ui::search::SearchView::new(event_manager.clone(), queue.clone(), library.clone());
+
+        let libraryview = ui::library::LibraryView::new(queue.clone(), library.clone());
+
+        let queueview = ui::queue::QueueView::new(queue.clone(), library.clone());
+
+        #[cfg(feature = "cover")]
+        let coverview = ui::cover::CoverView::new(
+            queue.clone(),
+            library.clone(),
+            configuration,
+            cursive.cb_sink().clone(),
+        );
+
+        let status = ui::statusbar::StatusBar::new(queue.clone(), Arc::clone(library));
+
+        let mut layout = Layout::new(status, event_manager, theme, Arc::clone(configuration))
+            .screen("search", search.with_name("search"))
+            .screen("library", libraryview.with_name("library"))
+            .screen("queue", queueview);
+
+        #[cfg(feature = "cover")]
+        layout.add_screen("cover", coverview.with_name("cover"));
+
+        // initial screen is library
+        let initial_screen = configuration
+            .values()
+            .initial_screen
+            .clone()
+            .unwrap_or_else(|| config::DEFAULT_INITIAL_SCREEN.to_string());
+        if layout.has_screen(&initial_screen) {
+            layout.set_screen(initial_screen);
+        } else {
+            error!("Invalid screen name: {initial_screen}");
+            layout.set_screen("library");
+        }
+
+        layout
+    }
+
     /// Create a new ncspot application.
     ///
     /// # Arguments
     ///
     /// * `configuration_file_path` - Relative path to the configuration file inside the base path
-    pub fn new(configuration_file_path: Option<String>) -> Result<Self, Box<dyn Error>> {
+    /// * `profile` - The profile whose credentials, user state and library cache are used
+    pub fn new(
+        configuration_file_path: Option<String>,
+        profile: Profile,
+    ) -> Result<Self, Box<dyn Error>> {
         // Things here may cause the process to abort; we must do them before creating curses
         // windows otherwise the error message will not be seen by a user
 
-        ASYNC_RUNTIME
-            .set(
-                tokio::runtime::Builder::new_multi_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap(),
-            )
-            .unwrap();
+        init_async_runtime();
 
-        let configuration = Arc::new(Config::new(configuration_file_path));
-        let credentials = authentication::get_credentials(&configuration)?;
+        let configuration = Arc::new(Config::new(configuration_file_path, profile));
+        crate::cache::configure(&configuration);
+        authentication::check_permissions(configuration.profile());
+        let (credentials, online) = authentication::get_credentials(&configuration)?;
+        theme::query_terminal_background();
         let theme = configuration.build_theme();
 
-        if let Err(e) = authentication::get_rspotify_token() {
+        // The token can't be refreshed while offline, and logging in again would fail as well.
+        if online && let Err(e) = authentication::get_rspotify_token(configuration.profile()) {
             error!("Failed to get rspotify token: {e}");
         }
 
@@ -164,24 +261,72 @@ impl Application {
         let mut cursive = create_cursive().map_err(|error| error.to_string())?;
 
         cursive.set_theme(theme.clone());
-        #[cfg(target_os = "macos")]
+        #[cfg(any(target_os = "macos", all(target_os = "linux", feature = "mpris")))]
         {
-            use tokio::time::Duration;
-
             let cb_sink = cursive.cb_sink().clone();
-            let theme_cfg = configuration.values().theme.clone();
-            // Periodically check system appearance and update theme if it changes.
+            // Switch between the light and dark theme when the system appearance changes. The
+            // selected theme belongs to the active profile, so its configuration is looked up.
+            let on_change = move |appearance| {
+                if theme::set_appearance(appearance) {
+                    let _ = cb_sink.send(Box::new(|s| {
+                        if let Some(data) = s.user_data::<UserData>().cloned() {
+                            theme::apply(s, data.cmd.config().build_theme());
+                        }
+                    }));
+                }
+            };
+
+            // macOS doesn't notify about changes, so the appearance is checked periodically.
+            #[cfg(target_os = "macos")]
             ASYNC_RUNTIME.get().unwrap().spawn(async move {
-                let mut last = theme::detect_appearance();
                 loop {
                     tokio::time::sleep(Duration::from_secs(5)).await;
-                    let current = theme::detect_appearance();
-                    if current != last {
-                        last = current;
-                        let theme_cfg = theme_cfg.clone();
-                        let _ = cb_sink.send(Box::new(move |s| {
-                            s.set_theme(theme::load(&theme_cfg));
-                        }));
+                    let Ok(current) = tokio::task::spawn_blocking(theme::detect_appearance).await
+                    else {
+                        return;
+                    };
+                    on_change(current);
+                }
+            });
+
+            #[cfg(all(target_os = "linux", feature = "mpris"))]
+            ASYNC_RUNTIME.get().unwrap().spawn(async move {
+                if let Err(e) = theme::watch_color_scheme(on_change).await {
+                    log::debug!("Could not watch the color scheme of the desktop portal: {e}");
+                }
+            });
+        }
+
+        {
+            let cb_sink = cursive.cb_sink().clone();
+            let configuration = configuration.clone();
+            // Reload the configuration whenever the configuration file is saved.
+            ASYNC_RUNTIME.get().unwrap().spawn(async move {
+                let path = configuration.path();
+                let modified = move || fs::metadata(&path).and_then(|m| m.modified()).ok();
+                let mut last = modified();
+                loop {
+                    tokio::time::sleep(CONFIG_WATCH_INTERVAL).await;
+                    let current = modified();
+                    if current == last || current.is_none() {
+                        continue;
+                    }
+                    last = current;
+
+                    if !configuration
+                        .values()
+                        .auto_reload
+                        .unwrap_or(config::DEFAULT_AUTO_RELOAD)
+                    {
+                        continue;
+                    }
+                    let reload = cb_sink.send(Box::new(|s| {
+                        if let Some(data) = s.user_data::<UserData>().cloned() {
+                            data.cmd.handle(s, Command::ReloadConfig);
+                        }
+                    }));
+                    if reload.is_err() {
+                        return;
                     }
                 }
             });
@@ -220,26 +365,7 @@ impl Application {
         #[cfg(feature = "mpris")]
         spotify.set_mpris(mpris_manager.clone());
 
-        // Load the last played track into the player
-        let playback_state = configuration.state().playback_state.clone();
-        let queue_state = configuration.state().queuestate.clone();
-
-        if let Some(playable) = queue.get_current() {
-            spotify.load(
-                &playable,
-                playback_state == PlaybackState::Playing,
-                queue_state.track_progress.as_millis() as u32,
-            );
-            spotify.update_track();
-            match playback_state {
-                PlaybackState::Stopped => {
-                    spotify.stop();
-                }
-                PlaybackState::Paused | PlaybackState::Playing | PlaybackState::Default => {
-                    spotify.pause();
-                }
-            }
-        }
+        Self::load_last_track(&spotify, &queue, &configuration);
 
         #[cfg(unix)]
         let ipc = if let Ok(runtime_directory) = utils::create_runtime_directory() {
@@ -303,54 +429,116 @@ impl Application {
             });
         }
 
-        let search =
-            ui::search::SearchView::new(event_manager.clone(), queue.clone(), library.clone());
-
-        let libraryview = ui::library::LibraryView::new(queue.clone(), library.clone());
-
-        let queueview = ui::queue::QueueView::new(queue.clone(), library.clone());
-
-        #[cfg(feature = "cover")]
-        let coverview = ui::cover::CoverView::new(queue.clone(), library.clone(), &configuration);
-
-        let status = ui::statusbar::StatusBar::new(queue.clone(), Arc::clone(&library));
-
-        let mut layout =
-            ui::layout::Layout::new(status, &event_manager, theme, Arc::clone(&configuration))
-                .screen("search", search.with_name("search"))
-                .screen("library", libraryview.with_name("library"))
-                .screen("queue", queueview);
-
-        #[cfg(feature = "cover")]
-        layout.add_screen("cover", coverview.with_name("cover"));
-
-        // initial screen is library
-        let initial_screen = configuration
-            .values()
-            .initial_screen
-            .clone()
-            .unwrap_or_else(|| "library".to_string());
-        if layout.has_screen(&initial_screen) {
-            layout.set_screen(initial_screen);
-        } else {
-            error!("Invalid screen name: {initial_screen}");
-            layout.set_screen("library");
-        }
+        let layout = Self::create_layout(
+            &cursive,
+            &event_manager,
+            &queue,
+            &library,
+            &configuration,
+            theme,
+        );
 
         cursive.add_fullscreen_layer(layout.with_name("main"));
 
         Ok(Self {
             queue,
+            library,
             spotify,
             event_manager,
             cfg: configuration,
             pending_recovery: None,
+            #[cfg(feature = "mpris")]
+            mpris: mpris_manager,
             #[cfg(unix)]
             ipc,
             cursive,
         })
     }
 
+    /// Switch to the profile `name` by replacing the session, library and queue with the ones of
+    /// the profile, without restarting ncspot. The profile must have logged in before, as the
+    /// OAuth2 authorization can't be done while the user interface is running.
+    fn switch_profile(&mut self, name: &str) -> Result<String, String> {
+        let data: UserData = self
+            .cursive
+            .user_data()
+            .cloned()
+            .expect("user data should be set");
+        data.cmd.save_state();
+
+        // The old session, library and queue keep the configuration of the previous profile, so
+        // whatever they still save ends up in that profile.
+        let profile = Profile::new(Some(name.to_string()));
+        let credentials = match authentication::cached_credentials(&profile) {
+            Some(credentials) if authentication::is_logged_in(&profile) => credentials,
+            _ => {
+                return Err(format!(
+                    "Profile {name} isn't logged in, run `ncspot --profile {name} auth login` first"
+                ));
+            }
+        };
+        let cfg = Arc::new(self.cfg.for_profile(profile));
+
+        // Connect before shutting down the current session, so it can be kept if this fails.
+        let mut spotify = Spotify::new(self.event_manager.clone(), credentials, cfg.clone())
+            .map_err(|e| format!("Could not switch to profile {name}: {e}"))?;
+        info!("Switching to profile {name}");
+        self.spotify.shutdown();
+
+        let library = Arc::new(Library::new(
+            self.event_manager.clone(),
+            spotify.clone(),
+            cfg.clone(),
+        ));
+        let queue = Arc::new(Queue::new(spotify.clone(), cfg.clone(), library.clone()));
+
+        #[cfg(feature = "mpris")]
+        {
+            self.mpris = self.mpris.replace(
+                self.event_manager.clone(),
+                queue.clone(),
+                library.clone(),
+                spotify.clone(),
+            );
+            spotify.set_mpris(self.mpris.clone());
+        }
+
+        Self::load_last_track(&spotify, &queue, &cfg);
+
+        let mut cmd_manager = CommandManager::new(
+            spotify.clone(),
+            queue.clone(),
+            library.clone(),
+            cfg.clone(),
+            self.event_manager.clone(),
+        );
+        // The keybindings look up the command manager when they're used, so they don't need to be
+        // registered again.
+        cmd_manager.register_all();
+        self.cursive
+            .set_user_data(Rc::new(UserDataInner { cmd: cmd_manager }));
+
+        let theme = cfg.build_theme();
+        self.cursive.set_theme(theme.clone());
+        while self.cursive.pop_layer().is_some() {}
+        let layout = Self::create_layout(
+            &self.cursive,
+            &self.event_manager,
+            &queue,
+            &library,
+            &cfg,
+            theme,
+        );
+        self.cursive.add_fullscreen_layer(layout.with_name("main"));
+
+        self.cfg = cfg;
+        self.spotify = spotify;
+        self.queue = queue;
+        self.library = library;
+        self.pending_recovery = None;
+        Ok(format!("Switched to profile {name}"))
+    }
+
     /// Start the application and run the event loop.
     pub fn run(&mut self) -> Result<(), String> {
         #[cfg(unix)]
@@ -360,6 +548,11 @@ impl Application {
         // cursive event loop
         while self.cursive.is_running() {
             self.cursive.step();
+            // Images are written to the terminal after the frame they belong to.
+            #[cfg(feature = "cover")]
+            if let Err(e) = crate::ui::graphics::flush() {
+                error!("Failed to draw cover: {e}");
+            }
             #[cfg(unix)]
             for signal in signals.pending() {
                 if signal == SIGTERM || signal == SIGHUP {
@@ -381,6 +574,12 @@ impl Application {
                         } else if state == PlayerEvent::FinishedTrack {
                             self.pending_recovery = None;
                         }
+                        #[cfg(feature = "notify")]
+                        if matches!(state, PlayerEvent::Paused(_))
+                            && !matches!(self.spotify.get_current_status(), PlayerEvent::Paused(_))
+                        {
+                            self.queue.notify_paused();
+                        }
                         self.spotify.update_status(state.clone());
 
                         #[cfg(unix)]
@@ -407,6 +606,21 @@ impl Application {
                             self.restore_playback_recovery();
                         };
                     }
+                    Event::Reconnected => {
+                        self.library.replay_outbox();
+                        self.cursive.on_layout(|_, mut layout| {
+                            layout.set_result(Ok(Some("Connected to Spotify again".into())))
+                        });
+                    }
+                    Event::SwitchProfile(name) => {
+                        let result = self.switch_profile(&name).map(Some);
+                        self.cursive
+                            .on_layout(|_, mut layout| layout.set_result(result));
+                    }
+                    Event::CommandResult(result) => {
+                        self.cursive
+                            .on_layout(|_, mut layout| layout.set_result(result));
+                    }
                     Event::IpcInput(input) => match command::parse(&input) {
                         Ok(commands) => {
                             if let Some(data) = self.cursive.user_data::<UserData>().cloned() {
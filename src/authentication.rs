@@ -1,17 +1,38 @@
+#[cfg(feature = "secret_service")]
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
 use std::fs;
+use std::io::{self, Write};
 use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use chrono::{DateTime, Local, Utc};
 use librespot_core::authentication::Credentials as RespotCredentials;
-use librespot_core::cache::Cache;
+use librespot_core::error::ErrorKind;
 use librespot_oauth::OAuthClientBuilder;
-use log::{error, info};
+use log::{error, info, warn};
+use rspotify::clients::OAuthClient;
+use rspotify::{AuthCodePkceSpotify, Credentials as ApiCredentials, OAuth};
+use url::Url;
 
-use crate::config::{self, Config};
+use crate::config::{Config, Profile};
+#[cfg(feature = "secret_service")]
+use crate::secret_service::SecretService;
 use crate::spotify::Spotify;
 
 pub const SPOTIFY_CLIENT_ID: &str = "65b708073fc0480ea92a077233ca87bd";
 pub const NCSPOT_CLIENT_ID: &str = "d420a117a32841c2b3474932e49fb54b";
 
+/// The file in the cache directory of a profile that stores the token for the Web API.
+const RSPOTIFY_TOKEN_FILE: &str = "rspotify_token.json";
+
+/// Whether the manual authorization was requested, see [set_headless].
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
 static OAUTH_SCOPES: &[&str] = &[
     "playlist-modify",
     "playlist-modify-private",
@@ -71,29 +92,286 @@ pub fn get_client_redirect_uri() -> String {
 }
 
 /// Get credentials for use with librespot. This first tries to get cached credentials. If no cached
-/// credentials are available it will initiate the OAuth2 login process.
-pub fn get_credentials(configuration: &Config) -> Result<RespotCredentials, String> {
-    let mut credentials = {
-        let cache = Cache::new(Some(config::cache_path("librespot")), None, None, None)
-            .expect("Could not create librespot cache");
-        let cached_credentials = cache.credentials();
-        match cached_credentials {
-            Some(c) => {
-                info!("Using cached credentials");
-                c
-            }
-            None => {
-                info!("Attempting to login via OAuth2");
-                credentials_prompt(None)?
-            }
+/// credentials are available it will initiate the OAuth2 login process. Also returns whether
+/// Spotify could be reached: cached credentials are used offline when the connection fails for
+/// another reason than the credentials.
+pub fn get_credentials(configuration: &Config) -> Result<(RespotCredentials, bool), String> {
+    let cached = cached_credentials(configuration.profile());
+    let is_cached = cached.is_some();
+    let mut credentials = match cached {
+        Some(c) => {
+            info!("Using cached credentials");
+            c
+        }
+        None => {
+            info!("Attempting to login via OAuth2");
+            credentials_prompt(None)?
         }
     };
 
     while let Err(error) = Spotify::test_credentials(configuration, credentials.clone()) {
+        if is_cached && !is_authentication_error(error.kind) {
+            warn!("Could not connect to Spotify, starting offline: {error}");
+            return Ok((credentials, false));
+        }
         let error_msg = format!("{error}");
         credentials = credentials_prompt(Some(error_msg))?;
     }
-    Ok(credentials)
+    Ok((credentials, true))
+}
+
+/// Whether a failed login with the error `kind` was caused by the credentials, rather than by the
+/// connection.
+fn is_authentication_error(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::Unauthenticated | ErrorKind::PermissionDenied
+    )
+}
+
+/// Where a secret of a profile is stored.
+pub enum Storage {
+    #[cfg(feature = "secret_service")]
+    SecretService,
+    File(PathBuf),
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "secret_service")]
+            Self::SecretService => write!(f, "the Secret Service"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A secret of a profile. It's stored in the Secret Service when it's available, and
+/// otherwise in a file in the cache directory of the profile that only the user can access.
+#[derive(Clone, Copy)]
+enum Secret {
+    /// The reusable credentials of librespot, in the file format of its cache.
+    Credentials,
+    /// The token for the Web API, including its refresh token.
+    Token,
+}
+
+impl Secret {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Credentials => "credentials",
+            Self::Token => "token",
+        }
+    }
+
+    fn path(self, profile: &Profile) -> PathBuf {
+        match self {
+            Self::Credentials => profile.cache_path("librespot").join("credentials.json"),
+            Self::Token => profile.cache_path(RSPOTIFY_TOKEN_FILE),
+        }
+    }
+
+    /// The value of the secret of `profile` and where it's stored, if it is.
+    fn load(self, profile: &Profile) -> Option<(Vec<u8>, Storage)> {
+        #[cfg(feature = "secret_service")]
+        match with_secret_service(
+            async |service, attributes| service.lookup(attributes).await,
+            self,
+            profile,
+        ) {
+            Ok(Some(value)) => return Some((value, Storage::SecretService)),
+            Ok(None) => {}
+            Err(e) => info!(
+                "Could not look up the {} in the Secret Service: {e}",
+                self.name()
+            ),
+        }
+
+        let path = self.path(profile);
+        fs::read(&path)
+            .ok()
+            .map(|value| (value, Storage::File(path)))
+    }
+
+    /// Store `value` as the secret of `profile`, in a private file if the Secret Service isn't
+    /// available.
+    fn save(self, profile: &Profile, value: &[u8]) -> Result<(), String> {
+        let path = self.path(profile);
+
+        #[cfg(feature = "secret_service")]
+        {
+            let label = format!("ncspot {} (profile {profile})", self.name());
+            match with_secret_service(
+                async |service, attributes| service.store(&label, attributes, value).await,
+                self,
+                profile,
+            ) {
+                Ok(()) => {
+                    // Don't leave an older copy of the secret behind.
+                    return match fs::remove_file(&path) {
+                        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                            Err(format!("Could not remove {}: {e}", path.display()))
+                        }
+                        _ => Ok(()),
+                    };
+                }
+                Err(e) => warn!(
+                    "Could not store the {} in the Secret Service, using a file instead: {e}",
+                    self.name()
+                ),
+            }
+        }
+
+        write_private_file(&path, value)
+            .map_err(|e| format!("Could not save the {}: {e}", self.name()))
+    }
+
+    /// Delete the secret of `profile`, returning where it was stored.
+    fn delete(self, profile: &Profile) -> Result<Vec<Storage>, String> {
+        let mut removed = Vec::new();
+
+        #[cfg(feature = "secret_service")]
+        match with_secret_service(
+            async |service, attributes| service.delete(attributes).await,
+            self,
+            profile,
+        ) {
+            Ok(true) => removed.push(Storage::SecretService),
+            Ok(false) => {}
+            // The user refused to delete the secret, so it's still stored.
+            Err(e @ zbus::Error::Failure(_)) => {
+                return Err(format!(
+                    "Could not delete the {} from the Secret Service: {e}",
+                    self.name()
+                ));
+            }
+            Err(e) => warn!(
+                "Could not delete the {} from the Secret Service: {e}",
+                self.name()
+            ),
+        }
+
+        let path = self.path(profile);
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(Storage::File(path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Could not remove {}: {e}", path.display())),
+        }
+        Ok(removed)
+    }
+}
+
+/// Run `operation` on the Secret Service of the session bus with the attributes of `secret` of
+/// `profile`.
+#[cfg(feature = "secret_service")]
+fn with_secret_service<T>(
+    operation: impl AsyncFnOnce(&SecretService, &HashMap<&str, &str>) -> zbus::Result<T>,
+    secret: Secret,
+    profile: &Profile,
+) -> zbus::Result<T> {
+    let attributes = HashMap::from([
+        ("application", "ncspot"),
+        ("profile", profile.name()),
+        ("secret", secret.name()),
+    ]);
+    // The connection and the session are kept for all secrets.
+    static SERVICE: tokio::sync::OnceCell<SecretService> = tokio::sync::OnceCell::const_new();
+    crate::application::init_async_runtime().block_on(async {
+        let service = SERVICE
+            .get_or_try_init(|| async {
+                SecretService::connect(zbus::Connection::session().await?).await
+            })
+            .await?;
+        operation(service, &attributes).await
+    })
+}
+
+/// Write `contents` to the file at `path`, which only the user can access.
+fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, so restrict existing ones as well.
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)
+}
+
+/// Whether the permission bits in `mode` deny all access to the group and other users.
+#[cfg(unix)]
+fn is_private(mode: u32) -> bool {
+    mode & 0o077 == 0
+}
+
+/// Warn about files with secrets of `profile` that other users can access.
+pub fn check_permissions(profile: &Profile) {
+    #[cfg(unix)]
+    for secret in [Secret::Credentials, Secret::Token] {
+        let path = secret.path(profile);
+        if let Ok(metadata) = fs::metadata(&path)
+            && !is_private(metadata.permissions().mode())
+        {
+            let message = format!(
+                "The {} in {} can be accessed by other users, restrict its permissions with \
+                 `chmod 600 {}`",
+                secret.name(),
+                path.display(),
+                path.display()
+            );
+            eprintln!("Warning: {message}");
+            warn!("{message}");
+        }
+    }
+}
+
+/// Get the cached credentials of `profile`, if it has any.
+pub fn cached_credentials(profile: &Profile) -> Option<RespotCredentials> {
+    let (value, _) = Secret::Credentials.load(profile)?;
+    serde_json::from_slice(&value)
+        .inspect_err(|e| error!("Could not parse the stored credentials: {e}"))
+        .ok()
+}
+
+/// Where the credentials of `profile` are stored, if it has any.
+pub fn credentials_storage(profile: &Profile) -> Option<Storage> {
+    Secret::Credentials
+        .load(profile)
+        .map(|(_, storage)| storage)
+}
+
+/// Move the credentials that librespot stored in the cache directory of `profile` when logging in
+/// to the Secret Service, or make sure that only the user can access the file.
+pub fn secure_credentials(profile: &Profile) {
+    let path = Secret::Credentials.path(profile);
+    match fs::read(&path) {
+        Ok(value) => {
+            if let Err(e) = Secret::Credentials.save(profile, &value) {
+                error!("{e}");
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => error!("Could not read {}: {e}", path.display()),
+    }
+}
+
+/// Whether `profile` has logged in before, so it can be used without going through the OAuth2
+/// authorization in the web browser again.
+pub fn is_logged_in(profile: &Profile) -> bool {
+    cached_credentials(profile).is_some() && stored_rspotify_token(profile).is_some()
+}
+
+/// Remove the cached credentials and the Web API token of `profile`, returning a description of
+/// every removed secret and where it was stored.
+pub fn remove_credentials(profile: &Profile) -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+    for secret in [Secret::Credentials, Secret::Token] {
+        for storage in secret.delete(profile)? {
+            removed.push(format!("the {} from {storage}", secret.name()));
+        }
+    }
+    Ok(removed)
 }
 
 fn credentials_prompt(error_message: Option<String>) -> Result<RespotCredentials, String> {
@@ -107,6 +385,11 @@ fn credentials_prompt(error_message: Option<String>) -> Result<RespotCredentials
 pub fn create_credentials() -> Result<RespotCredentials, String> {
     println!("To login you need to perform OAuth2 authorization using your web browser\n");
 
+    if is_headless() {
+        return manual_authorization(SPOTIFY_CLIENT_ID, OAUTH_SCOPES)
+            .map(|token| RespotCredentials::with_access_token(token.access_token));
+    }
+
     let client_builder = OAuthClientBuilder::new(
         SPOTIFY_CLIENT_ID,
         &get_client_redirect_uri(),
@@ -120,52 +403,96 @@ pub fn create_credentials() -> Result<RespotCredentials, String> {
         .map_err(|e| e.to_string())
 }
 
-pub fn get_rspotify_token() -> Result<rspotify::Token, String> {
-    let path = config::cache_path("rspotify_token.json");
-    let token = if let Ok(token_json) = fs::read_to_string(&path) {
-        serde_json::from_str::<rspotify::Token>(&token_json).ok()
+/// Load the stored Web API token of `profile`, whether it's expired or not.
+pub fn stored_rspotify_token(profile: &Profile) -> Option<rspotify::Token> {
+    let (token_json, _) = Secret::Token.load(profile)?;
+    serde_json::from_slice::<rspotify::Token>(&token_json)
+        .inspect_err(|e| error!("Could not parse the stored Web API token: {e}"))
+        .ok()
+}
+
+fn save_rspotify_token(profile: &Profile, token: &rspotify::Token) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(token).map_err(|e| e.to_string())?;
+    Secret::Token.save(profile, json.as_bytes())
+}
+
+/// The command that logs in to `profile`.
+pub fn login_command(profile: &Profile) -> String {
+    if profile.is_default() {
+        "ncspot auth login".into()
     } else {
-        None
-    };
+        format!("ncspot --profile {profile} auth login")
+    }
+}
 
-    if let Some(t) = token {
-        if !t.is_expired() {
-            return Ok(t);
-        }
+/// Get the stored Web API token of `profile`, refreshed if it expired. This never asks the user to
+/// log in, so it can be used while the user interface is shown.
+pub fn refresh_rspotify_token(profile: &Profile) -> Result<rspotify::Token, String> {
+    let log_in = || format!("log in again with `{}`", login_command(profile));
+    let t = stored_rspotify_token(profile)
+        .ok_or_else(|| format!("There is no Web API token, {}", log_in()))?;
+    if !t.is_expired() {
+        return Ok(t);
+    }
 
-        // Token is expired, try to refresh if we have a refresh token
-        if let Some(ref refresh_token) = t.refresh_token {
-            info!("Access token expired, attempting to refresh..");
-            let client_builder = OAuthClientBuilder::new(
-                NCSPOT_CLIENT_ID,
-                &get_client_redirect_uri(),
-                NCSPOT_OAUTH_SCOPES.to_vec(),
-            );
-            if let Ok(oauth_client) = client_builder.build() {
-                match oauth_client.refresh_token(refresh_token) {
-                    Ok(new_token) => {
-                        let mapped = map_token(new_token);
-                        let _ = fs::write(path, serde_json::to_string_pretty(&mapped).unwrap());
-                        return Ok(mapped);
-                    }
-                    Err(e) => {
-                        error!("Failed to refresh token: {e}");
-                    }
-                }
-            }
-        }
+    let refresh_token = t.refresh_token.ok_or_else(|| {
+        format!(
+            "The Web API token expired and has no refresh token, {}",
+            log_in()
+        )
+    })?;
+    info!("The Web API token expired, refreshing it");
+    let oauth_client = OAuthClientBuilder::new(
+        NCSPOT_CLIENT_ID,
+        &get_client_redirect_uri(),
+        NCSPOT_OAUTH_SCOPES.to_vec(),
+    )
+    .build()
+    .map_err(|e| format!("Could not create the OAuth2 client to refresh the token: {e}"))?;
+    let new_token = oauth_client
+        .refresh_token(&refresh_token)
+        .map_err(|e| format!("Could not refresh the Web API token, {}: {e}", log_in()))?;
+
+    let mapped = map_token(new_token);
+    info!(
+        "Refreshed the Web API token, {}",
+        token_status(&mapped, Utc::now())
+    );
+    if let Err(e) = save_rspotify_token(profile, &mapped) {
+        error!("{e}");
+    }
+    Ok(mapped)
+}
+
+/// Get the Web API token of `profile` like [refresh_rspotify_token], but log in again if there is
+/// no valid token. Logging in is interactive, so this must only be called before the user
+/// interface is shown.
+pub fn get_rspotify_token(profile: &Profile) -> Result<rspotify::Token, String> {
+    match refresh_rspotify_token(profile) {
+        Ok(t) => return Ok(t),
+        Err(e) => warn!("Logging in to the Web API again: {e}"),
     }
 
     let t = create_rspotify_token()?;
-    let _ = fs::write(path, serde_json::to_string_pretty(&t).unwrap());
+    save_rspotify_token(profile, &t)?;
     Ok(t)
 }
 
+/// Authorize the Web API and store the token for `profile`.
+pub fn login_web_api(profile: &Profile) -> Result<(), String> {
+    let token = create_rspotify_token()?;
+    save_rspotify_token(profile, &token)
+}
+
 pub fn create_rspotify_token() -> Result<rspotify::Token, String> {
     println!(
         "To fully enable Web API features, you need to perform a second OAuth2 authorization\n"
     );
 
+    if is_headless() {
+        return manual_authorization(NCSPOT_CLIENT_ID, NCSPOT_OAUTH_SCOPES);
+    }
+
     let client_builder = OAuthClientBuilder::new(
         NCSPOT_CLIENT_ID,
         &get_client_redirect_uri(),
@@ -179,6 +506,86 @@ pub fn create_rspotify_token() -> Result<rspotify::Token, String> {
         .map_err(|e| e.to_string())
 }
 
+/// Authorize without receiving the redirect of the browser on a local port, which isn't possible
+/// when the browser runs on another machine, e.g. over SSH. The user opens the authorization URL on
+/// any device and pastes the URL the browser was redirected to, or only its code.
+fn manual_authorization(client_id: &str, scopes: &[&str]) -> Result<rspotify::Token, String> {
+    let oauth = OAuth {
+        redirect_uri: get_client_redirect_uri(),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        ..Default::default()
+    };
+    let mut client = AuthCodePkceSpotify::new(ApiCredentials::new_pkce(client_id), oauth);
+    let url = client.get_authorize_url(None).map_err(|e| e.to_string())?;
+
+    println!("Open this URL in a web browser on any device and log in:\n\n{url}\n");
+    println!(
+        "The browser is then redirected to a page that can't be loaded. Paste the address of that \
+         page, or only the value of its code parameter, and press Enter:"
+    );
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| format!("Could not read the authorization code: {e}"))?;
+    let code = authorization_code(&input)
+        .ok_or_else(|| format!("No authorization code found in \"{}\"", input.trim()))?;
+
+    client
+        .request_token(&code)
+        .map_err(|e| format!("Could not get the access token: {e}"))?;
+    let token = client.token.lock().unwrap().clone();
+    token.ok_or_else(|| "Spotify didn't return an access token".to_string())
+}
+
+/// The authorization code in `input`, which is either the URL that the browser was redirected to
+/// after the authorization, or the code itself.
+fn authorization_code(input: &str) -> Option<String> {
+    let input = input.trim();
+    match Url::parse(input) {
+        Ok(url) => url
+            .query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, code)| code.into_owned()),
+        Err(_) if !input.is_empty() && !input.contains(char::is_whitespace) => {
+            Some(input.to_string())
+        }
+        Err(_) => None,
+    }
+}
+
+/// Force the manual authorization, see [manual_authorization].
+pub fn set_headless(headless: bool) {
+    HEADLESS.store(headless, Ordering::Relaxed);
+}
+
+/// Whether the manual authorization is used because it was requested or no local web browser is
+/// available.
+fn is_headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed) || is_remote_session(|name| env::var_os(name).is_some())
+}
+
+/// Whether the environment, whose variables are checked with `is_set`, indicates an SSH session.
+fn is_remote_session(is_set: impl Fn(&str) -> bool) -> bool {
+    is_set("SSH_CONNECTION") || is_set("SSH_TTY")
+}
+
+/// A description of when `token` expires relative to `now`, and whether it can be refreshed.
+pub fn token_status(token: &rspotify::Token, now: DateTime<Utc>) -> String {
+    let Some(expires_at) = token.expires_at else {
+        return "Web API token has no expiry time".into();
+    };
+    let local = expires_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+    if expires_at > now {
+        let minutes = (expires_at - now).num_minutes();
+        format!("Web API token valid until {local} ({minutes} minutes left)")
+    } else if token.refresh_token.is_some() {
+        format!("Web API token expired at {local}, it is refreshed when it's used")
+    } else {
+        format!("Web API token expired at {local} and can't be refreshed, log in again")
+    }
+}
+
 fn map_token(token: librespot_oauth::OAuthToken) -> rspotify::Token {
     let duration = if token.expires_at > std::time::Instant::now() {
         token.expires_at.duration_since(std::time::Instant::now())
@@ -195,3 +602,68 @@ fn map_token(token: librespot_oauth::OAuthToken) -> rspotify::Token {
         refresh_token: Some(token.refresh_token),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn finds_authorization_codes() {
+        assert_eq!(
+            authorization_code("http://127.0.0.1:8989/login?code=AQB-x_1&state=abc\n").as_deref(),
+            Some("AQB-x_1")
+        );
+        assert_eq!(authorization_code("  AQB-x_1 ").as_deref(), Some("AQB-x_1"));
+        assert_eq!(
+            authorization_code("http://127.0.0.1:8989/login?error=denied"),
+            None
+        );
+        assert_eq!(authorization_code("\n"), None);
+    }
+
+    #[test]
+    fn detects_remote_sessions() {
+        assert!(is_remote_session(|name| name == "SSH_CONNECTION"));
+        assert!(is_remote_session(|name| name == "SSH_TTY"));
+        // A console without a graphical display can still open a browser, e.g. a text one.
+        assert!(!is_remote_session(|_| false));
+        assert!(!is_remote_session(
+            |name| name == "DISPLAY" || name == "WAYLAND_DISPLAY"
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn detects_open_permissions() {
+        assert!(is_private(0o100600));
+        assert!(is_private(0o100400));
+        assert!(!is_private(0o100640));
+        assert!(!is_private(0o100604));
+    }
+
+    #[test]
+    fn distinguishes_authentication_errors() {
+        assert!(is_authentication_error(ErrorKind::Unauthenticated));
+        assert!(!is_authentication_error(ErrorKind::Unavailable));
+        assert!(!is_authentication_error(ErrorKind::DeadlineExceeded));
+    }
+
+    #[test]
+    fn describes_token_expiry() {
+        let now = Utc::now();
+        let mut token = rspotify::Token {
+            expires_at: Some(now + Duration::minutes(30)),
+            ..Default::default()
+        };
+        assert!(token_status(&token, now).contains("30 minutes left"));
+
+        token.expires_at = Some(now - Duration::minutes(1));
+        token.refresh_token = Some("refresh".into());
+        assert!(token_status(&token, now).contains("it is refreshed"));
+
+        token.refresh_token = None;
+        assert!(token_status(&token, now).contains("log in again"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Management of the files that ncspot caches on disk: cover images, the library and the audio files
+//! of librespot.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use log::{debug, error};
+
+use crate::command::CacheKind;
+use crate::config::{self, Config};
+use crate::library;
+
+/// The default size limit of the cover cache in MiB.
+pub const DEFAULT_COVER_CACHE_SIZE: u32 = 100;
+
+const MEBIBYTE: u64 = 1024 * 1024;
+
+/// The size limit of the cover cache in bytes.
+static COVER_CACHE_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_COVER_CACHE_SIZE as u64 * MEBIBYTE);
+
+/// When the cached covers were last used while ncspot is running. Covers that weren't used yet
+/// fall back to their modification time, which is when they were downloaded.
+static COVER_USES: Mutex<Option<HashMap<PathBuf, SystemTime>>> = Mutex::new(None);
+
+/// Apply the cache size limits of `cfg` and evict the covers that exceed them in the background.
+pub fn configure(cfg: &Config) {
+    let size = cfg
+        .values()
+        .cover_cache_size
+        .unwrap_or(DEFAULT_COVER_CACHE_SIZE);
+    COVER_CACHE_LIMIT.store(u64::from(size) * MEBIBYTE, Ordering::Relaxed);
+    std::thread::spawn(|| evict_covers(None));
+}
+
+/// The directory in which librespot caches audio files.
+pub fn audio_cache_path() -> PathBuf {
+    config::cache_path("librespot").join("files")
+}
+
+/// The path of the cover at `url` in the cache, downloading it first if it isn't cached yet. This
+/// blocks until the download is finished.
+pub fn cover(url: &str) -> io::Result<PathBuf> {
+    if let Some(path) = cached_cover(url) {
+        return Ok(path);
+    }
+
+    let path = crate::utils::cache_path_for_url(url.to_string());
+    crate::utils::download(url.to_string(), path.clone())?;
+    // The cover is about to be used, so it mustn't be evicted right away.
+    evict_covers(Some(&path));
+    Ok(path)
+}
+
+/// The path of the cover at `url` in the cache, if it was downloaded already.
+pub fn cached_cover(url: &str) -> Option<PathBuf> {
+    let path = crate::utils::cache_path_for_url(url.to_string());
+    if !path.exists() {
+        return None;
+    }
+
+    COVER_USES
+        .lock()
+        .unwrap()
+        .get_or_insert_default()
+        .insert(path.clone(), SystemTime::now());
+    Some(path)
+}
+
+/// Download the covers at `urls` to the cache in the background.
+pub fn prefetch_covers(urls: Vec<String>) {
+    if urls.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        for url in urls {
+            if let Err(e) = cover(&url) {
+                error!("Failed to prefetch cover: {e}");
+            }
+        }
+    });
+}
+
+/// Remove the least recently used covers until the cover cache fits into its size limit. The cover
+/// at `keep` is never removed.
+fn evict_covers(keep: Option<&Path>) {
+    let Ok(entries) = fs::read_dir(config::cache_path("covers")) else {
+        return;
+    };
+    let uses = COVER_USES.lock().unwrap().clone().unwrap_or_default();
+    let covers = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_none_or(|ext| ext != "part"))
+        .filter(|entry| keep != Some(entry.path().as_path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let path = entry.path();
+            metadata.is_file().then(|| CachedFile {
+                used: uses.get(&path).copied().unwrap_or(modified).max(modified),
+                path,
+                size: metadata.len(),
+            })
+        })
+        .collect();
+
+    for path in least_recently_used(covers, COVER_CACHE_LIMIT.load(Ordering::Relaxed)) {
+        debug!("Evicting cover {}", path.display());
+        if let Err(e) = fs::remove_file(&path) {
+            error!("Could not remove cover {}: {e}", path.display());
+        }
+    }
+}
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    used: SystemTime,
+}
+
+/// The paths of the least recently used `files` that need to be removed so that the size of the
+/// remaining ones doesn't exceed `limit` bytes.
+fn least_recently_used(mut files: Vec<CachedFile>, limit: u64) -> Vec<PathBuf> {
+    files.sort_by_key(|file| file.used);
+
+    let mut total: u64 = files.iter().map(|file| file.size).sum();
+    let mut evicted = Vec::new();
+    for file in files {
+        if total <= limit {
+            break;
+        }
+        total -= file.size;
+        evicted.push(file.path);
+    }
+    evicted
+}
+
+/// The total size of the files in the directory at `path` and all of its subdirectories.
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// The size of the cache of `kind` on disk in bytes. The library cache is the one of the profile
+/// of `cfg`.
+pub fn size(kind: CacheKind, cfg: &Config) -> u64 {
+    match kind {
+        CacheKind::Covers => directory_size(&config::cache_path("covers")),
+        CacheKind::Library => library::CACHE_FILES
+            .iter()
+            .filter_map(|file| fs::metadata(cfg.profile().cache_path(file)).ok())
+            .map(|metadata| metadata.len())
+            .sum(),
+        CacheKind::Audio => directory_size(&audio_cache_path()),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / MEBIBYTE as f64)
+}
+
+/// A summary of the disk usage of all caches and their size limits.
+pub fn stats(cfg: &Config) -> String {
+    let covers = format!(
+        "covers {} of {}",
+        format_size(size(CacheKind::Covers, cfg)),
+        format_size(COVER_CACHE_LIMIT.load(Ordering::Relaxed))
+    );
+    let library = format!("library {}", format_size(size(CacheKind::Library, cfg)));
+    let audio = match (cfg.values().audio_cache, cfg.values().audio_cache_size) {
+        (Some(false), _) => "audio disabled".to_string(),
+        (_, Some(limit)) => format!(
+            "audio {} of {}",
+            format_size(size(CacheKind::Audio, cfg)),
+            format_size(u64::from(limit) * MEBIBYTE)
+        ),
+        (_, None) => format!("audio {}", format_size(size(CacheKind::Audio, cfg))),
+    };
+    format!("Cache: {covers}, {library}, {audio}")
+}
+
+/// Remove all files in the cache of `kind`, using the library cache of the profile of `cfg`. Audio
+/// files that are being played are kept.
+pub fn clear(kind: CacheKind, cfg: &Config) -> io::Result<()> {
+    let remove_file = |path: PathBuf| match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    };
+
+    match kind {
+        CacheKind::Covers => {
+            COVER_USES.lock().unwrap().take();
+            clear_directory(&config::cache_path("covers"), &HashSet::new())
+        }
+        CacheKind::Library => library::CACHE_FILES
+            .iter()
+            .try_for_each(|file| remove_file(cfg.profile().cache_path(file))),
+        CacheKind::Audio => match fs::canonicalize(audio_cache_path()) {
+            // The paths of open files are canonical, so the directory has to be as well.
+            Ok(path) => clear_directory(&path, &open_files()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// The files that ncspot has open, like the audio files that librespot plays.
+fn open_files() -> HashSet<PathBuf> {
+    #[cfg(target_os = "linux")]
+    if let Ok(entries) = fs::read_dir("/proc/self/fd") {
+        return entries
+            .flatten()
+            .filter_map(|entry| fs::read_link(entry.path()).ok())
+            .collect();
+    }
+    HashSet::new()
+}
+
+/// Remove the contents of the directory at `path` except for the files in `keep`, but keep the
+/// directory itself. Subdirectories are only removed if they end up empty.
+fn clear_directory(path: &Path, keep: &HashSet<PathBuf>) -> io::Result<()> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if keep.contains(&path) {
+            debug!("Keeping {}, which is in use", path.display());
+        } else if entry.file_type()?.is_dir() {
+            clear_directory(&path, keep)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn file(name: &str, size: u64, used: u64) -> CachedFile {
+        CachedFile {
+            path: PathBuf::from(name),
+            size,
+            used: SystemTime::UNIX_EPOCH + Duration::from_secs(used),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_files() {
+        let files = vec![file("c", 30, 3), file("a", 30, 1), file("b", 30, 2)];
+        assert_eq!(
+            least_recently_used(files, 50),
+            vec![PathBuf::from("a"), PathBuf::from("b")]
+        );
+    }
+
+    #[test]
+    fn clears_directories_except_kept_files() {
+        let root = std::env::temp_dir().join(format!("ncspot-cache-{}", std::process::id()));
+        let kept = root.join("ab").join("kept");
+        fs::create_dir_all(root.join("ab")).unwrap();
+        fs::create_dir_all(root.join("cd")).unwrap();
+        for file in [&kept, &root.join("ab/other"), &root.join("cd/other")] {
+            fs::write(file, b"audio").unwrap();
+        }
+
+        clear_directory(&root, &HashSet::from([kept.clone()])).unwrap();
+        assert!(kept.exists());
+        assert!(!root.join("ab/other").exists());
+        assert!(!root.join("cd").exists());
+
+        clear_directory(&root, &HashSet::new()).unwrap();
+        assert!(root.exists());
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 0);
+        fs::remove_dir(&root).unwrap();
+    }
+
+    #[test]
+    fn keeps_files_within_limit() {
+        let files = vec![file("a", 30, 1), file("b", 30, 2)];
+        assert!(least_recently_used(files, 60).is_empty());
+        assert!(least_recently_used(Vec::new(), 0).is_empty());
+    }
+}
@@ -1,4 +1,12 @@
-use crate::config::{user_cache_directory, user_configuration_directory};
+use chrono::Utc;
+
+use crate::application::init_async_runtime;
+use crate::authentication;
+use crate::commands::CommandManager;
+use crate::config::{
+    Config, Profile, config_path, user_cache_directory, user_configuration_directory,
+};
+use crate::spotify::Spotify;
 
 /// Print platform info like which platform directories will be used.
 pub fn info() -> Result<(), String> {
@@ -33,3 +41,97 @@ pub fn info() -> Result<(), String> {
 
     Ok(())
 }
+
+/// Print all problems in the configuration file `filename`.
+pub fn check_config(filename: &str) -> Result<(), String> {
+    let path = config_path(filename);
+    let problems = crate::validation::check_file(&path).map_err(|e| e.to_string())?;
+    for problem in &problems {
+        println!("{}:{problem}", path.display());
+    }
+
+    if problems.is_empty() {
+        println!("{} is valid", path.display());
+        Ok(())
+    } else {
+        Err(format!(
+            "Found {} problem(s) in {}",
+            problems.len(),
+            path.display()
+        ))
+    }
+}
+
+/// Print the effective configuration from the configuration file `filename`, with the defaults of
+/// all settings that it doesn't set and the keybindings merged with the default ones.
+pub fn dump_config(filename: &str) -> Result<(), String> {
+    let configuration = Config::new(Some(filename.to_string()), Profile::default());
+    let mut values = configuration.values().clone().with_defaults();
+
+    let keybindings = CommandManager::get_bindings(&configuration)
+        .into_iter()
+        .map(|(key, commands)| {
+            let commands: Vec<String> = commands.iter().map(ToString::to_string).collect();
+            (key, commands.join("; "))
+        })
+        .collect();
+    values.keybindings = Some(keybindings);
+
+    // Sort the settings by converting them to a table first.
+    let table = toml::Value::try_from(&values).map_err(|e| e.to_string())?;
+    print!(
+        "{}",
+        toml::to_string_pretty(&table).map_err(|e| e.to_string())?
+    );
+    Ok(())
+}
+
+/// Log in to `profile` and store the credentials and the Web API token. `headless` forces the
+/// login flow in which the redirect URL is pasted instead of received by a local server.
+pub fn auth_login(filename: &str, profile: Profile, headless: bool) -> Result<(), String> {
+    authentication::set_headless(headless);
+    init_async_runtime();
+    let configuration = Config::new(Some(filename.to_string()), profile);
+
+    let credentials = authentication::create_credentials()?;
+    Spotify::test_credentials(&configuration, credentials)
+        .map_err(|e| format!("Could not log in: {e}"))?;
+    authentication::login_web_api(configuration.profile())?;
+
+    println!("Logged in to profile {}", configuration.profile());
+    Ok(())
+}
+
+/// Print whether `profile` has stored credentials and when its Web API token expires.
+pub fn auth_status(profile: &Profile) -> Result<(), String> {
+    println!("Profile {profile}");
+    match authentication::credentials_storage(profile) {
+        Some(storage) => println!("Credentials stored in {storage}"),
+        None => println!("No credentials stored"),
+    }
+    match authentication::stored_rspotify_token(profile) {
+        Some(token) => println!("{}", authentication::token_status(&token, Utc::now())),
+        None => println!("No Web API token stored"),
+    }
+
+    if authentication::is_logged_in(profile) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Not logged in, run `{}` to log in",
+            authentication::login_command(profile)
+        ))
+    }
+}
+
+/// Remove the stored credentials and the Web API token of `profile`.
+pub fn auth_logout(profile: &Profile) -> Result<(), String> {
+    let removed = authentication::remove_credentials(profile)?;
+    if removed.is_empty() {
+        println!("No credentials stored");
+    }
+    for secret in removed {
+        println!("Removed {secret}");
+    }
+    Ok(())
+}
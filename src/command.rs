@@ -1,4 +1,4 @@
-use crate::queue::RepeatSetting;
+use crate::queue::{RepeatSetting, ShuffleMode};
 use crate::spotify_url::SpotifyUrl;
 use std::collections::HashMap;
 use std::fmt;
@@ -38,7 +38,7 @@ impl Default for MoveAmount {
 }
 
 /// Keys that can be used to sort songs on.
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[strum(serialize_all = "lowercase")]
 pub enum SortKey {
     Title,
@@ -46,9 +46,15 @@ pub enum SortKey {
     Artist,
     Album,
     Added,
+    #[strum(serialize = "track")]
+    TrackNumber,
+    Popularity,
+    Released,
+    #[strum(serialize = "plays")]
+    PlayCount,
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[strum(serialize_all = "lowercase")]
 pub enum SortDirection {
     Ascending,
@@ -113,6 +119,55 @@ impl fmt::Display for InsertSource {
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum CacheKind {
+    Covers,
+    Library,
+    Audio,
+}
+
+impl fmt::Display for CacheKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Self::Covers => "covers",
+            Self::Library => "library",
+            Self::Audio => "audio",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum CacheCommand {
+    Stats,
+    Clear(Option<CacheKind>),
+}
+
+impl fmt::Display for CacheCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stats => write!(f, "stats"),
+            Self::Clear(None) => write!(f, "clear"),
+            Self::Clear(Some(kind)) => write!(f, "clear {kind}"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum ProfileCommand {
+    List,
+    Switch(String),
+}
+
+impl fmt::Display for ProfileCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::List => write!(f, "list"),
+            Self::Switch(name) => write!(f, "switch {name}"),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Command {
     Quit,
@@ -137,7 +192,7 @@ pub enum Command {
     VolumeUp(u16),
     VolumeDown(u16),
     Repeat(Option<RepeatSetting>),
-    Shuffle(Option<bool>),
+    Shuffle(Option<bool>, Option<ShuffleMode>),
     #[cfg(feature = "share_clipboard")]
     Share(TargetMode),
     Back,
@@ -147,17 +202,22 @@ pub enum Command {
     Shift(ShiftMode, Option<i32>),
     Search(String),
     Jump(JumpMode),
+    Filter(Option<String>),
     Help,
     ReloadConfig,
+    Theme(Option<String>),
     Noop,
     Insert(InsertSource),
     NewPlaylist(String),
-    Sort(SortKey, SortDirection),
+    Sort(Vec<SortKey>, SortDirection),
     Logout,
     ShowRecommendations(TargetMode),
     Redraw,
     Execute(String),
     Reconnect,
+    Devices,
+    Cache(CacheCommand),
+    Profile(ProfileCommand),
 }
 
 impl fmt::Display for Command {
@@ -172,9 +232,10 @@ impl fmt::Display for Command {
                 Some(mode) => vec![mode.to_string()],
                 None => vec![],
             },
-            Self::Shuffle(on) => match on {
-                Some(b) => vec![(if *b { "on" } else { "off" }).into()],
-                None => vec![],
+            Self::Shuffle(on, mode) => match (on, mode) {
+                (_, Some(mode)) => vec![mode.to_string()],
+                (Some(b), None) => vec![(if *b { "on" } else { "off" }).into()],
+                (None, None) => vec![],
             },
             #[cfg(feature = "share_clipboard")]
             Self::Share(mode) => vec![mode.to_string()],
@@ -195,11 +256,21 @@ impl fmt::Display for Command {
                 JumpMode::Previous | JumpMode::Next => vec![],
                 JumpMode::Query(term) => vec![term.to_owned()],
             },
+            Self::Filter(expression) => expression.iter().cloned().collect(),
+            Self::Theme(name) => name.iter().cloned().collect(),
             Self::Insert(source) => vec![source.to_string()],
             Self::NewPlaylist(name) => vec![name.to_owned()],
-            Self::Sort(key, direction) => vec![key.to_string(), direction.to_string()],
+            Self::Sort(keys, direction) => vec![
+                keys.iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                direction.to_string(),
+            ],
             Self::ShowRecommendations(mode) => vec![mode.to_string()],
             Self::Execute(cmd) => vec![cmd.to_owned()],
+            Self::Cache(cmd) => vec![cmd.to_string()],
+            Self::Profile(cmd) => vec![cmd.to_string()],
             Self::Quit
             | Self::TogglePlay
             | Self::Stop
@@ -222,6 +293,7 @@ impl fmt::Display for Command {
             | Self::Noop
             | Self::Logout
             | Self::Reconnect
+            | Self::Devices
             | Self::Redraw
             | Self::ReverseOrder => vec![],
         };
@@ -255,7 +327,7 @@ impl Command {
             Self::VolumeUp(_) => "volup",
             Self::VolumeDown(_) => "voldown",
             Self::Repeat(_) => "repeat",
-            Self::Shuffle(_) => "shuffle",
+            Self::Shuffle(_, _) => "shuffle",
             #[cfg(feature = "share_clipboard")]
             Self::Share(_) => "share",
             Self::Back => "back",
@@ -267,8 +339,10 @@ impl Command {
             Self::Jump(JumpMode::Previous) => "jumpprevious",
             Self::Jump(JumpMode::Next) => "jumpnext",
             Self::Jump(JumpMode::Query(_)) => "jump",
+            Self::Filter(_) => "filter",
             Self::Help => "help",
             Self::ReloadConfig => "reload",
+            Self::Theme(_) => "theme",
             Self::Noop => "noop",
             Self::Insert(_) => "insert",
             Self::NewPlaylist(_) => "newplaylist",
@@ -278,6 +352,9 @@ impl Command {
             Self::Redraw => "redraw",
             Self::Execute(_) => "exec",
             Self::Reconnect => "reconnect",
+            Self::Devices => "devices",
+            Self::Cache(_) => "cache",
+            Self::Profile(_) => "profile",
         }
     }
 }
@@ -530,17 +607,28 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     Command::Repeat(mode)
                 }
                 "shuffle" => {
-                    let switch = match args.first().cloned() {
-                        Some("on") => Ok(Some(true)),
-                        Some("off") => Ok(Some(false)),
+                    let (switch, mode) = match args.first().cloned() {
+                        Some("on") => Ok((Some(true), None)),
+                        Some("off") => Ok((Some(false), None)),
+                        Some("random") => Ok((Some(true), Some(ShuffleMode::Random))),
+                        Some("spread") => Ok((Some(true), Some(ShuffleMode::Spread))),
+                        Some("weighted") => Ok((Some(true), Some(ShuffleMode::Weighted))),
+                        Some("albums") => Ok((Some(true), Some(ShuffleMode::Albums))),
                         Some(arg) => Err(E::BadEnumArg {
                             arg: arg.into(),
-                            accept: vec!["on".into(), "off".into()],
+                            accept: vec![
+                                "on".into(),
+                                "off".into(),
+                                "random".into(),
+                                "spread".into(),
+                                "weighted".into(),
+                                "albums".into(),
+                            ],
                             optional: true,
                         }),
-                        None => Ok(None),
+                        None => Ok((None, None)),
                     }?;
-                    Command::Shuffle(switch)
+                    Command::Shuffle(switch, mode)
                 }
                 #[cfg(feature = "share_clipboard")]
                 "share" => {
@@ -690,8 +778,10 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "jump" => Command::Jump(JumpMode::Query(args.join(" "))),
                 "jumpnext" => Command::Jump(JumpMode::Next),
                 "jumpprevious" => Command::Jump(JumpMode::Previous),
+                "filter" => Command::Filter(Some(args.join(" ")).filter(|e| !e.trim().is_empty())),
                 "help" => Command::Help,
                 "reload" => Command::ReloadConfig,
+                "theme" => Command::Theme(args.first().map(|name| name.to_string())),
                 "noop" => Command::Noop,
                 "insert" => {
                     let insert_source = match args.first().cloned() {
@@ -723,28 +813,40 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?
                 }
                 "sort" => {
-                    let &key_raw = args.first().ok_or(E::InsufficientArgs {
+                    let &keys_raw = args.first().ok_or(E::InsufficientArgs {
                         cmd: command.into(),
                         hint: Some("a sort key".into()),
                     })?;
-                    let key = match key_raw {
-                        "title" => Ok(SortKey::Title),
-                        "duration" => Ok(SortKey::Duration),
-                        "album" => Ok(SortKey::Album),
-                        "added" => Ok(SortKey::Added),
-                        "artist" => Ok(SortKey::Artist),
-                        _ => Err(E::BadEnumArg {
-                            arg: key_raw.into(),
-                            accept: vec![
-                                "title".into(),
-                                "duration".into(),
-                                "album".into(),
-                                "added".into(),
-                                "artist".into(),
-                            ],
-                            optional: false,
-                        }),
-                    }?;
+                    let keys = keys_raw
+                        .split(',')
+                        .map(|key_raw| match key_raw {
+                            "title" => Ok(SortKey::Title),
+                            "duration" => Ok(SortKey::Duration),
+                            "album" => Ok(SortKey::Album),
+                            "added" => Ok(SortKey::Added),
+                            "artist" => Ok(SortKey::Artist),
+                            "track" => Ok(SortKey::TrackNumber),
+                            "popularity" => Ok(SortKey::Popularity),
+                            "released" | "year" => Ok(SortKey::Released),
+                            "plays" => Ok(SortKey::PlayCount),
+                            _ => Err(E::BadEnumArg {
+                                arg: key_raw.into(),
+                                accept: vec![
+                                    "title".into(),
+                                    "duration".into(),
+                                    "album".into(),
+                                    "added".into(),
+                                    "artist".into(),
+                                    "track".into(),
+                                    "popularity".into(),
+                                    "released".into(),
+                                    "year".into(),
+                                    "plays".into(),
+                                ],
+                                optional: false,
+                            }),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
                     let direction = match args.get(1).copied() {
                         Some("a" | "asc" | "ascending") => Ok(SortDirection::Ascending),
                         Some("d" | "desc" | "descending") => Ok(SortDirection::Descending),
@@ -762,7 +864,7 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         }),
                         None => Ok(SortDirection::Ascending),
                     }?;
-                    Command::Sort(key, direction)
+                    Command::Sort(keys, direction)
                 }
                 "logout" => Command::Logout,
                 "similar" => {
@@ -784,6 +886,54 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "redraw" => Command::Redraw,
                 "exec" => Command::Execute(args.join(" ")),
                 "reconnect" => Command::Reconnect,
+                "devices" => Command::Devices,
+                "cache" => {
+                    let cache_command = match args.first().cloned() {
+                        Some("stats") | None => Ok(CacheCommand::Stats),
+                        Some("clear") => match args.get(1).cloned() {
+                            Some("covers") => Ok(CacheCommand::Clear(Some(CacheKind::Covers))),
+                            Some("library") => Ok(CacheCommand::Clear(Some(CacheKind::Library))),
+                            Some("audio") => Ok(CacheCommand::Clear(Some(CacheKind::Audio))),
+                            Some(arg) => Err(E::BadEnumArg {
+                                arg: arg.into(),
+                                accept: vec!["covers".into(), "library".into(), "audio".into()],
+                                optional: true,
+                            }),
+                            None => Ok(CacheCommand::Clear(None)),
+                        },
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["stats".into(), "clear".into()],
+                            optional: true,
+                        }),
+                    }?;
+                    Command::Cache(cache_command)
+                }
+                "profile" => {
+                    let profile_command = match args.first().cloned() {
+                        Some("list") | None => Ok(ProfileCommand::List),
+                        Some("switch") => match args.get(1).cloned() {
+                            Some(name) if crate::config::is_valid_profile_name(name) => {
+                                Ok(ProfileCommand::Switch(name.into()))
+                            }
+                            Some(name) => Err(E::ArgParseError {
+                                arg: name.into(),
+                                err: "profile names may only contain letters, digits, - and _"
+                                    .into(),
+                            }),
+                            None => Err(E::InsufficientArgs {
+                                cmd: "profile switch".into(),
+                                hint: Some("a profile name".into()),
+                            }),
+                        },
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["list".into(), "switch".into()],
+                            optional: true,
+                        }),
+                    }?;
+                    Command::Profile(profile_command)
+                }
                 _ => {
                     return Err(E::NoSuchCommand {
                         cmd: command.into(),
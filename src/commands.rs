@@ -4,25 +4,28 @@ use std::time::Duration;
 
 use crate::application::UserData;
 use crate::command::{
-    Command, GotoMode, JumpMode, MoveAmount, MoveMode, SeekDirection, ShiftMode, TargetMode, parse,
+    CacheCommand, CacheKind, Command, GotoMode, JumpMode, MoveAmount, MoveMode, ProfileCommand,
+    SeekDirection, ShiftMode, TargetMode, parse,
 };
-use crate::config::{Config, user_configuration_directory};
-use crate::events::EventManager;
+use crate::config::{self, Config, user_configuration_directory};
+use crate::events::{Event as AppEvent, EventManager};
 use crate::ext_traits::CursiveExt;
 use crate::library::Library;
 use crate::queue::{Queue, RepeatSetting};
-use crate::spotify::{Spotify, VOLUME_PERCENT};
+use crate::spotify::{PlayerEvent, Spotify, VOLUME_PERCENT};
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::contextmenu::{
     AddToPlaylistMenu, ContextMenu, SelectArtistActionMenu, SelectArtistMenu,
 };
+use crate::ui::devices::DevicesView;
 use crate::ui::help::HelpView;
 use crate::ui::layout::Layout;
+use crate::ui::library::LibraryView;
 use crate::ui::modal::Modal;
 use crate::ui::search_results::SearchResultsView;
 use cursive::Cursive;
 use cursive::event::{Event, Key};
-use cursive::traits::View;
+use cursive::traits::{Nameable, View};
 use cursive::views::Dialog;
 use log::{debug, error, info};
 use ncspot::CONFIGURATION_FILE_NAME;
@@ -67,7 +70,10 @@ impl CommandManager {
 
     pub fn get_bindings(config: &Config) -> HashMap<String, Vec<Command>> {
         let config = config.values();
-        let mut kb = if config.default_keybindings.unwrap_or(true) {
+        let mut kb = if config
+            .default_keybindings
+            .unwrap_or(config::DEFAULT_KEYBINDINGS_ENABLED)
+        {
             Self::default_keybindings()
         } else {
             HashMap::new()
@@ -102,6 +108,28 @@ impl CommandManager {
         self.register_aliases("repeat", vec!["loop"]);
     }
 
+    /// The configuration of the profile the commands act on.
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+
+    /// Save the queue and the playback position to the user state of the current profile.
+    pub fn save_state(&self) {
+        let queue = self.queue.queue.read().unwrap();
+        self.config.with_state_mut(move |s| {
+            debug!(
+                "saving state, {} items, current track: {:?}",
+                queue.len(),
+                self.queue.get_current_index()
+            );
+            s.queuestate.queue.clone_from(&queue);
+            s.queuestate.random_order = self.queue.get_random_order();
+            s.queuestate.current_track = self.queue.get_current_index();
+            s.queuestate.track_progress = self.spotify.get_current_progress();
+        });
+        self.config.save_state();
+    }
+
     fn handle_default_commands(
         &self,
         s: &mut Cursive,
@@ -110,19 +138,7 @@ impl CommandManager {
         match cmd {
             Command::Noop => Ok(None),
             Command::Quit => {
-                let queue = self.queue.queue.read().unwrap();
-                self.config.with_state_mut(move |s| {
-                    debug!(
-                        "saving state, {} items, current track: {:?}",
-                        queue.len(),
-                        self.queue.get_current_index()
-                    );
-                    s.queuestate.queue.clone_from(&queue);
-                    s.queuestate.random_order = self.queue.get_random_order();
-                    s.queuestate.current_track = self.queue.get_current_index();
-                    s.queuestate.track_progress = self.spotify.get_current_progress();
-                });
-                self.config.save_state();
+                self.save_state();
                 s.quit();
                 Ok(None)
             }
@@ -166,9 +182,12 @@ impl CommandManager {
                 self.queue.toggleplayback();
                 Ok(None)
             }
-            Command::Shuffle(mode) => {
-                let mode = mode.unwrap_or_else(|| !self.queue.get_shuffle());
-                self.queue.set_shuffle(mode);
+            Command::Shuffle(on, mode) => {
+                if let Some(mode) = mode {
+                    self.queue.set_shuffle_mode(*mode);
+                }
+                let on = on.unwrap_or_else(|| !self.queue.get_shuffle());
+                self.queue.set_shuffle(on);
                 Ok(None)
             }
             Command::Repeat(mode) => {
@@ -210,8 +229,13 @@ impl CommandManager {
                 s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
                 Ok(None)
             }
+            Command::Devices => {
+                let view = Box::new(DevicesView::new(self.queue.clone(), self.library.clone()));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
             Command::ReloadConfig => {
-                self.config.reload().map_err(|_| {
+                let changed = self.config.reload().map_err(|_| {
                     format!(
                         "Failed to reload configuration. Fix errors in {} and try again.",
                         user_configuration_directory()
@@ -224,13 +248,26 @@ impl CommandManager {
                 })?;
 
                 // update theme
-                let theme = self.config.build_theme();
-                s.set_theme(theme);
+                crate::theme::apply(s, self.config.build_theme());
 
                 // update bindings
                 self.unregister_keybindings(s);
                 self.bindings.replace(Self::get_bindings(&self.config));
                 self.register_keybindings(s);
+
+                match changed {
+                    Some(changed) => self.apply_settings(s, &changed).map(Some),
+                    None => Ok(Some("Configuration reloaded".into())),
+                }
+            }
+            Command::Theme(name) => {
+                // Fail early instead of falling back to the configured theme.
+                if let Some(name) = name {
+                    crate::theme::load_named(name)?;
+                }
+                self.config
+                    .with_state_mut(|state| state.theme.clone_from(name));
+                crate::theme::apply(s, self.config.build_theme());
                 Ok(None)
             }
             Command::NewPlaylist(name) => {
@@ -260,12 +297,10 @@ impl CommandManager {
                 Ok(None)
             }
             Command::Logout => {
+                // Keep the session if the credentials can't be removed, as ncspot keeps running.
+                crate::authentication::remove_credentials(self.config.profile())?;
                 self.spotify.shutdown();
 
-                let mut credentials_path = crate::config::cache_path("librespot");
-                credentials_path.push("credentials.json");
-                std::fs::remove_file(credentials_path).unwrap();
-
                 s.quit();
                 Ok(None)
             }
@@ -277,18 +312,56 @@ impl CommandManager {
                 Ok(None)
             }
             Command::Reconnect => {
-                self.spotify.shutdown();
-                Ok(None)
+                // The worker reports whether it could connect once it's done.
+                self.spotify
+                    .start_worker(None)
+                    .map_err(|e| format!("Could not reconnect: {e}"))?;
+                Ok(Some("Reconnecting…".into()))
+            }
+            Command::Cache(CacheCommand::Stats) => Ok(Some(crate::cache::stats(&self.config))),
+            Command::Cache(CacheCommand::Clear(kind)) => {
+                let kinds = match kind {
+                    Some(kind) => vec![*kind],
+                    None => vec![CacheKind::Covers, CacheKind::Library, CacheKind::Audio],
+                };
+                for kind in kinds {
+                    crate::cache::clear(kind, &self.config)
+                        .map_err(|e| format!("Could not clear the {kind} cache: {e}"))?;
+                    // Fetch the library again so that it gets cached anew.
+                    if kind == CacheKind::Library {
+                        self.library.update_library();
+                    }
+                }
+                Ok(Some(crate::cache::stats(&self.config)))
+            }
+            Command::Profile(ProfileCommand::List) => {
+                let active = self.config.profile().name();
+                let profiles: Vec<String> = config::profiles()
+                    .into_iter()
+                    .map(|name| {
+                        if name == active {
+                            format!("{name} (active)")
+                        } else {
+                            name
+                        }
+                    })
+                    .collect();
+                Ok(Some(format!("Profiles: {}", profiles.join(", "))))
+            }
+            Command::Profile(ProfileCommand::Switch(name)) => {
+                if self.config.profile().name() == name {
+                    return Err(format!("Profile {name} is already active"));
+                }
+                // The application replaces the session, library and queue, which can't be done
+                // from within a command.
+                self.events.send(AppEvent::SwitchProfile(name.clone()));
+                Ok(Some(format!("Switching to profile {name}..")))
             }
             Command::AddCurrent => {
                 if let Some(track) = self.queue.get_current()
                     && let Some(track) = track.track()
                 {
-                    let dialog = ContextMenu::add_track_dialog(
-                        self.library.clone(),
-                        self.queue.get_spotify(),
-                        track,
-                    );
+                    let dialog = ContextMenu::add_track_dialog(self.library.clone(), track);
                     s.add_layer(dialog);
                 }
                 Ok(None)
@@ -314,6 +387,7 @@ impl CommandManager {
             | Command::Move(_, _)
             | Command::Shift(_, _)
             | Command::Jump(_)
+            | Command::Filter(_)
             | Command::Insert(_)
             | Command::ShowRecommendations(_)
             | Command::Sort(_, _)
@@ -329,6 +403,102 @@ impl CommandManager {
         }
     }
 
+    /// Apply the `changed` settings of a reloaded configuration to the running application and
+    /// describe what was done. Settings that aren't handled here are read whenever they are used.
+    fn apply_settings(&self, s: &mut Cursive, changed: &[String]) -> Result<String, String> {
+        if changed.is_empty() {
+            return Ok("Configuration reloaded, nothing changed".into());
+        }
+
+        let mut restart_player = false;
+        let mut rebuild_views = false;
+        let mut next_start = Vec::new();
+        for setting in changed {
+            match setting.as_str() {
+                "ap_port" | "audio_buffer_size" | "audio_cache" | "audio_cache_size"
+                | "backend" | "backend_device" | "bitrate" | "connect" | "connect_name"
+                | "gapless" | "volnorm" | "volnorm_pregain" => restart_player = true,
+                "album_grid" | "cover_max_scale" | "cover_protocol" | "library_tabs" => {
+                    rebuild_views = true
+                }
+                "shuffle" => {
+                    if let Some(shuffle) = self.config.values().shuffle {
+                        self.queue.set_shuffle(shuffle);
+                    }
+                }
+                "shuffle_mode" => {
+                    if let Some(mode) = self.config.values().shuffle_mode {
+                        self.queue.set_shuffle_mode(mode);
+                    }
+                }
+                "repeat" => {
+                    if let Some(repeat) = self.config.values().repeat {
+                        self.queue.set_repeat(repeat);
+                    }
+                }
+                "smart_playlists" => {
+                    let library = self.library.clone();
+                    std::thread::spawn(move || library.refresh_smart_playlists());
+                }
+                "cover_cache_size" => crate::cache::configure(&self.config),
+                "initial_screen" | "playback_state" => next_start.push(setting.as_str()),
+                _ => {}
+            }
+        }
+
+        if rebuild_views {
+            let library_view = LibraryView::new(self.queue.clone(), self.library.clone());
+            #[cfg(feature = "cover")]
+            let cover_view = crate::ui::cover::CoverView::new(
+                self.queue.clone(),
+                self.library.clone(),
+                &self.config,
+                s.cb_sink().clone(),
+            );
+            s.on_layout(|_, mut layout| {
+                layout.replace_screen("library", library_view.with_name("library"));
+                #[cfg(feature = "cover")]
+                layout.replace_screen("cover", cover_view.with_name("cover"));
+            });
+        }
+
+        if restart_player {
+            self.restart_player()
+                .map_err(|e| format!("Could not apply the player settings: {e}"))?;
+        }
+
+        let mut message = format!("Configuration reloaded, changed {}", changed.join(", "));
+        if !next_start.is_empty() {
+            message.push_str(&format!(
+                "; applied on the next start: {}",
+                next_start.join(", ")
+            ));
+        }
+        Ok(message)
+    }
+
+    /// Restart the player with the current configuration and continue the current track at the
+    /// same position.
+    fn restart_player(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let status = self.spotify.get_current_status();
+        let position_ms = self
+            .spotify
+            .get_current_progress()
+            .as_millis()
+            .min(u32::MAX as u128) as u32;
+
+        self.spotify.start_worker(None)?;
+
+        if let Some(track) = self.queue.get_current()
+            && matches!(status, PlayerEvent::Playing(_) | PlayerEvent::Paused(_))
+        {
+            let playing = matches!(status, PlayerEvent::Playing(_));
+            self.spotify.load(&track, playing, position_ms);
+            self.spotify.update_track();
+        }
+        Ok(())
+    }
+
     fn handle_callbacks(&self, s: &mut Cursive, cmd: &Command) -> Result<Option<String>, String> {
         let local = if let Some(mut contextmenu) = s.find_name::<ContextMenu>("contextmenu") {
             contextmenu.on_command(s, cmd)?
@@ -363,6 +533,11 @@ impl CommandManager {
     pub fn handle(&self, s: &mut Cursive, cmd: Command) {
         let result = self.handle_callbacks(s, &cmd);
 
+        #[cfg(feature = "notify")]
+        if let Err(e) = &result {
+            self.library.notifier.error(e);
+        }
+
         s.call_on_name("main", |v: &mut Layout| {
             v.set_result(result);
         });
@@ -461,7 +636,7 @@ impl CommandManager {
         kb.insert("[".into(), vec![Command::VolumeDown(5)]);
 
         kb.insert("r".into(), vec![Command::Repeat(None)]);
-        kb.insert("z".into(), vec![Command::Shuffle(None)]);
+        kb.insert("z".into(), vec![Command::Shuffle(None, None)]);
 
         #[cfg(feature = "share_clipboard")]
         {
@@ -621,7 +796,7 @@ impl CommandManager {
         }
     }
 
-    fn parse_keybinding(kb: &str) -> Option<cursive::event::Event> {
+    pub fn parse_keybinding(kb: &str) -> Option<cursive::event::Event> {
         let mut split = kb.split('+');
         if kb != "+" && split.clone().count() == 2 {
             let modifier = split.next().unwrap();
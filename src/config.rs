@@ -1,13 +1,15 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::error::Error;
 use std::path::PathBuf;
-use std::sync::{RwLock, RwLockReadGuard};
-use std::{fs, process};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::{fmt, fs, process};
 
 use cursive::theme::Theme;
-use log::{debug, error};
+use log::{debug, error, warn};
 use ncspot::{CONFIGURATION_FILE_NAME, USER_STATE_FILE_NAME};
 use platform_dirs::AppDirs;
+use strum::IntoEnumIterator;
 
 use crate::command::{SortDirection, SortKey};
 use crate::model::playable::Playable;
@@ -15,7 +17,34 @@ use crate::queue;
 use crate::serialization::{CBOR, Serializer, TOML};
 
 pub const CACHE_VERSION: u16 = 1;
+
+// The defaults of the settings that aren't set in the configuration file, see
+// [ConfigValues::with_defaults].
 pub const DEFAULT_COMMAND_KEY: char = ':';
+pub const DEFAULT_INITIAL_SCREEN: &str = "library";
+pub const DEFAULT_KEYBINDINGS_ENABLED: bool = true;
+pub const DEFAULT_USE_NERDFONT: bool = false;
+pub const DEFAULT_FLIP_STATUS_INDICATORS: bool = false;
+pub const DEFAULT_AUDIO_CACHE: bool = true;
+pub const DEFAULT_VOLNORM: bool = false;
+pub const DEFAULT_VOLNORM_PREGAIN: f64 = 0.0;
+pub const DEFAULT_NOTIFY: bool = false;
+pub const DEFAULT_BITRATE: u32 = 320;
+pub const DEFAULT_GAPLESS: bool = true;
+pub const DEFAULT_SHUFFLE: bool = false;
+pub const DEFAULT_COVER_MAX_SCALE: f32 = 1.0;
+pub const DEFAULT_COVER_PREFETCH: usize = 0;
+pub const DEFAULT_STATUSBAR_FORMAT: &str = "%artists - %title";
+pub const DEFAULT_ALBUM_GRID: bool = false;
+pub const DEFAULT_HIDE_DISPLAY_NAMES: bool = false;
+pub const DEFAULT_CONNECT: bool = false;
+pub const DEFAULT_CONNECT_NAME: &str = "ncspot";
+pub const DEFAULT_AUTOPLAY: bool = false;
+pub const DEFAULT_AUTO_RELOAD: bool = true;
+pub const DEFAULT_NOTIFICATION_URGENCY: NotificationUrgency = NotificationUrgency::Low;
+pub const DEFAULT_NOTIFICATION_EVENTS: [NotificationEvent; 1] = [NotificationEvent::Track];
+pub const DEFAULT_NOTIFICATION_ACTIONS: bool = true;
+pub const DEFAULT_REPEAT: queue::RepeatSetting = queue::RepeatSetting::None;
 
 /// The playback state when ncspot is started.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -38,6 +67,24 @@ pub enum LibraryTab {
     Browse,
 }
 
+/// How album covers are drawn in the terminal.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverProtocol {
+    /// The kitty graphics protocol.
+    Kitty,
+    /// Sixel graphics.
+    Sixel,
+    /// The inline images of iTerm2.
+    Iterm2,
+    /// Colored Unicode half blocks, which work in any terminal with true color support.
+    HalfBlocks,
+    /// Colored Unicode braille patterns, which work in terminals without true color support.
+    Braille,
+    /// The external Überzug process.
+    Ueberzug,
+}
+
 /// The format used to represent tracks in a list.
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TrackFormat {
@@ -56,6 +103,167 @@ impl TrackFormat {
     }
 }
 
+/// A column that can be shown for tracks in list views.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Title,
+    Artists,
+    Album,
+    Duration,
+    Added,
+    TrackNumber,
+    Popularity,
+    Saved,
+}
+
+impl Column {
+    /// The text shown in the header of the column.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Title => "Title",
+            Self::Artists => "Artists",
+            Self::Album => "Album",
+            Self::Duration => "Duration",
+            Self::Added => "Added",
+            Self::TrackNumber => "#",
+            Self::Popularity => "Popularity",
+            Self::Saved => "Saved",
+        }
+    }
+
+    /// The track format placeholder that provides the content of the column.
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Artists => "artists",
+            Self::Album => "album",
+            Self::Duration => "duration",
+            Self::Added => "added_at",
+            Self::TrackNumber => "track_number",
+            Self::Popularity => "popularity",
+            Self::Saved => "saved",
+        }
+    }
+
+    /// The key used to sort on the column, if it can be sorted on.
+    pub fn sort_key(&self) -> Option<SortKey> {
+        match self {
+            Self::Title => Some(SortKey::Title),
+            Self::Artists => Some(SortKey::Artist),
+            Self::Album => Some(SortKey::Album),
+            Self::Duration => Some(SortKey::Duration),
+            Self::Added => Some(SortKey::Added),
+            Self::TrackNumber => Some(SortKey::TrackNumber),
+            Self::Popularity => Some(SortKey::Popularity),
+            Self::Saved => None,
+        }
+    }
+
+    fn default_width(&self) -> Option<usize> {
+        match self {
+            Self::Title | Self::Artists | Self::Album => None,
+            Self::Duration => Some(8),
+            Self::Added => Some(10),
+            Self::TrackNumber => Some(3),
+            Self::Popularity => Some(10),
+            Self::Saved => Some(5),
+        }
+    }
+
+    fn default_align(&self) -> ColumnAlign {
+        match self {
+            Self::Duration | Self::TrackNumber | Self::Popularity => ColumnAlign::Right,
+            _ => ColumnAlign::Left,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// The configuration of a single column in list views.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnConfig {
+    pub column: Column,
+    /// The width in cells. Columns without a width share the remaining space.
+    pub width: Option<usize>,
+    pub align: Option<ColumnAlign>,
+}
+
+impl ColumnConfig {
+    pub fn width(&self) -> Option<usize> {
+        self.width.or_else(|| self.column.default_width())
+    }
+
+    pub fn align(&self) -> ColumnAlign {
+        self.align.unwrap_or_else(|| self.column.default_align())
+    }
+}
+
+/// The library items that the rule of a smart playlist is applied to.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmartPlaylistSource {
+    /// The saved tracks.
+    #[default]
+    Tracks,
+    /// The tracks of the saved albums.
+    Albums,
+}
+
+/// A playlist that consists of the items in the library that match a rule. It is only kept
+/// locally, unless a Spotify playlist to write it to is given.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmartPlaylistConfig {
+    pub name: String,
+    /// The filter expression that the items have to match.
+    pub rule: String,
+    pub source: Option<SmartPlaylistSource>,
+    /// The maximum number of tracks.
+    pub limit: Option<usize>,
+    /// The ID of a Spotify playlist that is overwritten with the tracks of the smart playlist.
+    pub playlist: Option<String>,
+}
+
+/// The events that desktop notifications can be sent for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationEvent {
+    /// A track starts playing.
+    Track,
+    /// Playback is paused.
+    Pause,
+    /// A command failed.
+    Error,
+    /// The library finished updating.
+    Library,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationUrgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// The behavior of desktop notifications.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NotificationConfig {
+    /// The time in milliseconds after which notifications are closed, or 0 to keep them open.
+    pub timeout: Option<u32>,
+    pub urgency: Option<NotificationUrgency>,
+    pub events: Option<Vec<NotificationEvent>>,
+    /// Whether to add buttons to skip, save and pause to the notifications.
+    pub actions: Option<bool>,
+}
+
 /// The format used when sending desktop notifications about playback status.
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NotificationFormat {
@@ -72,6 +280,55 @@ impl NotificationFormat {
     }
 }
 
+/// A single line of the statusbar, split into a left aligned, a centered and a right aligned
+/// segment.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct StatusbarLine {
+    pub left: Option<String>,
+    pub center: Option<String>,
+    pub right: Option<String>,
+}
+
+/// The template used to render the statusbar.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct StatusbarTemplate {
+    pub left: Option<String>,
+    pub center: Option<String>,
+    pub right: Option<String>,
+    /// Additional lines that are shown below the first one.
+    pub lines: Option<Vec<StatusbarLine>>,
+    /// Whether to show the progress bar above the text lines.
+    pub progress: Option<bool>,
+}
+
+impl StatusbarTemplate {
+    pub fn default() -> Self {
+        Self {
+            left: Some(String::from(" %indicator %track")),
+            center: Some(String::new()),
+            right: Some(String::from(
+                "{%offline }{%updating }{%repeat }{%shuffle }{%elapsed / %duration} [%volume]",
+            )),
+            lines: None,
+            progress: Some(true),
+        }
+    }
+
+    /// All text lines of the statusbar, with unset segments of the first line taken from the
+    /// default template.
+    pub fn lines(&self) -> Vec<StatusbarLine> {
+        let default = Self::default();
+        let first = StatusbarLine {
+            left: self.left.clone().or(default.left),
+            center: self.center.clone().or(default.center),
+            right: self.right.clone().or(default.right),
+        };
+        std::iter::once(first)
+            .chain(self.lines.iter().flatten().cloned())
+            .collect()
+    }
+}
+
 /// The configuration of ncspot.
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct ConfigValues {
@@ -93,15 +350,88 @@ pub struct ConfigValues {
     pub bitrate: Option<u32>,
     pub gapless: Option<bool>,
     pub shuffle: Option<bool>,
+    pub shuffle_mode: Option<queue::ShuffleMode>,
     pub repeat: Option<queue::RepeatSetting>,
     pub cover_max_scale: Option<f32>,
+    pub cover_protocol: Option<CoverProtocol>,
+    pub cover_cache_size: Option<u32>,
+    pub cover_prefetch: Option<usize>,
     pub playback_state: Option<PlaybackState>,
     pub track_format: Option<TrackFormat>,
+    pub track_columns: Option<Vec<ColumnConfig>>,
+    pub filters: Option<HashMap<String, String>>,
+    pub smart_playlists: Option<Vec<SmartPlaylistConfig>>,
     pub notification_format: Option<NotificationFormat>,
+    pub notifications: Option<NotificationConfig>,
     pub statusbar_format: Option<String>,
+    pub statusbar: Option<StatusbarTemplate>,
     pub library_tabs: Option<Vec<LibraryTab>>,
+    pub album_grid: Option<bool>,
     pub hide_display_names: Option<bool>,
     pub ap_port: Option<u16>,
+    pub connect: Option<bool>,
+    pub connect_name: Option<String>,
+    pub autoplay: Option<bool>,
+    pub auto_reload: Option<bool>,
+}
+
+impl ConfigValues {
+    /// Set all settings that aren't set yet to their defaults.
+    pub fn with_defaults(mut self) -> Self {
+        self.command_key.get_or_insert(DEFAULT_COMMAND_KEY);
+        self.initial_screen
+            .get_or_insert_with(|| DEFAULT_INITIAL_SCREEN.into());
+        self.default_keybindings
+            .get_or_insert(DEFAULT_KEYBINDINGS_ENABLED);
+        self.use_nerdfont.get_or_insert(DEFAULT_USE_NERDFONT);
+        self.flip_status_indicators
+            .get_or_insert(DEFAULT_FLIP_STATUS_INDICATORS);
+        self.audio_cache.get_or_insert(DEFAULT_AUDIO_CACHE);
+        self.volnorm.get_or_insert(DEFAULT_VOLNORM);
+        self.volnorm_pregain.get_or_insert(DEFAULT_VOLNORM_PREGAIN);
+        self.notify.get_or_insert(DEFAULT_NOTIFY);
+        self.bitrate.get_or_insert(DEFAULT_BITRATE);
+        self.gapless.get_or_insert(DEFAULT_GAPLESS);
+        self.shuffle.get_or_insert(DEFAULT_SHUFFLE);
+        self.shuffle_mode.get_or_insert_default();
+        self.repeat.get_or_insert(DEFAULT_REPEAT);
+        self.cover_max_scale.get_or_insert(DEFAULT_COVER_MAX_SCALE);
+        self.cover_cache_size
+            .get_or_insert(crate::cache::DEFAULT_COVER_CACHE_SIZE);
+        self.cover_prefetch.get_or_insert(DEFAULT_COVER_PREFETCH);
+        #[cfg(feature = "cover")]
+        self.cover_protocol
+            .get_or_insert_with(crate::ui::graphics::detect_protocol);
+        self.playback_state.get_or_insert(PlaybackState::Default);
+        self.track_format.get_or_insert_with(TrackFormat::default);
+        self.notification_format
+            .get_or_insert_with(NotificationFormat::default);
+        let notifications = self.notifications.get_or_insert_default();
+        notifications
+            .urgency
+            .get_or_insert(DEFAULT_NOTIFICATION_URGENCY);
+        notifications
+            .events
+            .get_or_insert_with(|| DEFAULT_NOTIFICATION_EVENTS.to_vec());
+        notifications
+            .actions
+            .get_or_insert(DEFAULT_NOTIFICATION_ACTIONS);
+        self.statusbar_format
+            .get_or_insert_with(|| DEFAULT_STATUSBAR_FORMAT.into());
+        self.statusbar
+            .get_or_insert_with(StatusbarTemplate::default);
+        self.library_tabs
+            .get_or_insert_with(|| LibraryTab::iter().collect());
+        self.album_grid.get_or_insert(DEFAULT_ALBUM_GRID);
+        self.hide_display_names
+            .get_or_insert(DEFAULT_HIDE_DISPLAY_NAMES);
+        self.connect.get_or_insert(DEFAULT_CONNECT);
+        self.connect_name
+            .get_or_insert_with(|| DEFAULT_CONNECT_NAME.into());
+        self.autoplay.get_or_insert(DEFAULT_AUTOPLAY);
+        self.auto_reload.get_or_insert(DEFAULT_AUTO_RELOAD);
+        self
+    }
 }
 
 /// The ncspot theme.
@@ -123,9 +453,13 @@ pub struct ConfigTheme {
     pub statusbar_progress_bg: Option<String>,
     pub statusbar: Option<String>,
     pub statusbar_bg: Option<String>,
+    pub statusbar_left: Option<String>,
+    pub statusbar_center: Option<String>,
+    pub statusbar_right: Option<String>,
     pub cmdline: Option<String>,
     pub cmdline_bg: Option<String>,
     pub search_match: Option<String>,
+    pub autoplay: Option<String>,
 }
 
 /// A theme definition that supports either a single palette or light/dark variants.
@@ -140,11 +474,56 @@ pub struct ConfigThemeConfig {
     pub dark: Option<ConfigTheme>,
 }
 
-/// The ordering that is used when representing a playlist.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The ordering that is used when representing a playlist or another list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SortingOrder {
     pub key: SortKey,
     pub direction: SortDirection,
+    /// Keys that are used to order items for which `key` is equal.
+    #[serde(default)]
+    pub then_by: Vec<SortKey>,
+}
+
+impl SortingOrder {
+    /// Create an order from `keys`, the first of which is the primary key. Returns [None] if
+    /// `keys` is empty.
+    pub fn new(keys: &[SortKey], direction: &SortDirection) -> Option<Self> {
+        let (key, then_by) = keys.split_first()?;
+        Some(Self {
+            key: key.clone(),
+            direction: direction.clone(),
+            then_by: then_by.to_vec(),
+        })
+    }
+
+    /// All keys of the order, starting with the primary key.
+    pub fn keys(&self) -> impl Iterator<Item = &SortKey> {
+        std::iter::once(&self.key).chain(self.then_by.iter())
+    }
+
+    /// Compare two items by chaining the result of `compare` for every key and applying the
+    /// direction of the order.
+    pub fn compare(&self, compare: impl Fn(&SortKey) -> Ordering) -> Ordering {
+        let ordering = self.keys().fold(Ordering::Equal, |ordering, key| {
+            ordering.then_with(|| compare(key))
+        });
+        match self.direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        }
+    }
+
+    /// The same order in the opposite direction.
+    pub fn reversed(&self) -> Self {
+        let direction = match self.direction {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        };
+        Self {
+            direction,
+            ..self.clone()
+        }
+    }
 }
 
 /// The runtime state of the music queue.
@@ -161,9 +540,20 @@ pub struct QueueState {
 pub struct UserState {
     pub volume: u16,
     pub shuffle: bool,
+    #[serde(default)]
+    pub shuffle_mode: queue::ShuffleMode,
     pub repeat: queue::RepeatSetting,
     pub queuestate: QueueState,
     pub playlist_orders: HashMap<String, SortingOrder>,
+    /// The orders of the library lists and the queue, keyed by the name of the view.
+    #[serde(default)]
+    pub view_orders: HashMap<String, SortingOrder>,
+    /// How often every item was played, keyed by its URI.
+    #[serde(default)]
+    pub play_counts: HashMap<String, u32>,
+    /// The name of the theme selected with `:theme`, which replaces the theme of the configuration.
+    #[serde(default)]
+    pub theme: Option<String>,
     pub cache_version: u16,
     pub playback_state: PlaybackState,
 }
@@ -173,9 +563,13 @@ impl Default for UserState {
         Self {
             volume: u16::MAX,
             shuffle: false,
-            repeat: queue::RepeatSetting::None,
+            shuffle_mode: queue::ShuffleMode::Random,
+            repeat: DEFAULT_REPEAT,
             queuestate: QueueState::default(),
             playlist_orders: HashMap::new(),
+            view_orders: HashMap::new(),
+            play_counts: HashMap::new(),
+            theme: None,
             cache_version: 0,
             playback_state: PlaybackState::Default,
         }
@@ -185,59 +579,132 @@ impl Default for UserState {
 /// Configuration files are read/written relative to this directory.
 static BASE_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
 
+/// The directory inside the configuration and cache directories that contains the profiles.
+const PROFILES_DIRECTORY: &str = "profiles";
+
+/// The name under which the default profile can be selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A profile with its own credentials, user state and library caches.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile(Option<String>);
+
+impl Profile {
+    /// The profile called `name`. `None` or [DEFAULT_PROFILE] select the default profile.
+    pub fn new(name: Option<String>) -> Self {
+        Self(name.filter(|name| name != DEFAULT_PROFILE))
+    }
+
+    /// The name of the profile, which is [DEFAULT_PROFILE] for the default profile.
+    pub fn name(&self) -> &str {
+        self.0.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Whether this is the default profile.
+    pub fn is_default(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// The directory of the profile inside `directory`, which is created if it doesn't exist. The
+    /// default profile uses `directory` itself, so existing files keep working.
+    fn directory(&self, directory: PathBuf) -> PathBuf {
+        let Some(name) = &self.0 else {
+            return directory;
+        };
+        let directory = directory.join(PROFILES_DIRECTORY).join(name);
+        fs::create_dir_all(&directory).expect("can't create profile folder");
+        directory
+    }
+
+    /// Like [config_path], but for files that belong to the profile.
+    pub fn config_path(&self, file: &str) -> PathBuf {
+        self.directory(config_path("")).join(file)
+    }
+
+    /// Like [cache_path], but for files that belong to the profile.
+    pub fn cache_path(&self, file: &str) -> PathBuf {
+        self.directory(cache_path("")).join(file)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// The complete configuration (state + user configuration) of ncspot.
 pub struct Config {
     /// The configuration file path.
     filename: String,
-    /// Configuration set by the user, read only.
-    values: RwLock<ConfigValues>,
+    /// Configuration set by the user, read only. It's shared by the configurations of all profiles.
+    values: Arc<RwLock<ConfigValues>>,
+    /// The profile the runtime state belongs to.
+    profile: Profile,
     /// Runtime state which can't be edited by the user, read/write.
     state: RwLock<UserState>,
 }
 
 impl Config {
-    /// Generate the configuration from the user configuration file and the runtime state file.
-    /// `filename` can be used to look for a differently named configuration file.
-    pub fn new(filename: Option<String>) -> Self {
+    /// Generate the configuration from the user configuration file and the runtime state file of
+    /// `profile`. `filename` can be used to look for a differently named configuration file.
+    pub fn new(filename: Option<String>, profile: Profile) -> Self {
         let filename = filename.unwrap_or(CONFIGURATION_FILE_NAME.to_owned());
+        let path = config_path(&filename);
+        let problems = crate::validation::check_file(&path).unwrap_or_default();
         let values = load(&filename).unwrap_or_else(|e| {
-            eprint!(
-                "There is an error in your configuration file at {}:\n\n{e}",
-                user_configuration_directory()
-                    .map(|ref mut path| {
-                        path.push(CONFIGURATION_FILE_NAME);
-                        path.to_string_lossy().to_string()
-                    })
-                    .expect("configuration directory expected but not found")
+            eprintln!(
+                "There are errors in your configuration file at {}:\n",
+                path.display()
             );
+            if problems.is_empty() {
+                eprintln!("{e}");
+            }
+            for problem in &problems {
+                eprintln!("{}:{problem}", path.display());
+            }
             process::exit(1);
         });
 
-        let mut userstate = {
-            let path = config_path(USER_STATE_FILE_NAME);
-            CBOR.load_or_generate_default(path, || Ok(UserState::default()), true)
-                .expect("could not load user state")
-        };
-
-        if let Some(shuffle) = values.shuffle {
-            userstate.shuffle = shuffle;
+        // The other problems make the affected settings fall back to their defaults.
+        if !problems.is_empty() {
+            eprintln!(
+                "Some settings in your configuration file at {} are ignored:\n",
+                path.display()
+            );
         }
-
-        if let Some(repeat) = values.repeat {
-            userstate.repeat = repeat;
+        for problem in &problems {
+            eprintln!("{}:{problem}", path.display());
+            warn!("{}:{problem}", path.display());
         }
 
-        if let Some(playback_state) = values.playback_state.clone() {
-            userstate.playback_state = playback_state;
-        }
+        let userstate = load_state(&values, &profile);
 
         Self {
             filename,
-            values: RwLock::new(values),
+            values: Arc::new(RwLock::new(values)),
+            profile,
             state: RwLock::new(userstate),
         }
     }
 
+    /// Create the configuration of `profile`, which shares the user configuration with this one but
+    /// has its own runtime state.
+    pub fn for_profile(&self, profile: Profile) -> Self {
+        let userstate = load_state(&self.values(), &profile);
+        Self {
+            filename: self.filename.clone(),
+            values: self.values.clone(),
+            profile,
+            state: RwLock::new(userstate),
+        }
+    }
+
+    /// The profile the runtime state belongs to.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
     /// Get the user configuration values.
     pub fn values(&self) -> RwLockReadGuard<'_, ConfigValues> {
         self.values.read().unwrap()
@@ -267,26 +734,40 @@ impl Config {
     pub fn save_state(&self) {
         self.update_state_cache_version();
 
-        let path = config_path(USER_STATE_FILE_NAME);
+        let path = self.profile.config_path(USER_STATE_FILE_NAME);
         debug!("saving user state to {}", path.display());
         if let Err(e) = CBOR.write(path, &*self.state()) {
             error!("Could not save user state: {e}");
         }
     }
 
-    /// Create a [Theme] from the user supplied theme in the configuration file.
+    /// Create a [Theme] from the theme selected with `:theme`, or the user supplied theme in the
+    /// configuration file if none is selected.
     pub fn build_theme(&self) -> Theme {
-        crate::theme::load(&self.values().theme)
+        let selected = self.state().theme.clone().and_then(|name| {
+            crate::theme::load_named(&name)
+                .inspect_err(|e| error!("Could not load the selected theme: {e}"))
+                .ok()
+        });
+        crate::theme::load(&selected.or_else(|| self.values().theme.clone()))
+    }
+
+    /// The path of the configuration file.
+    pub fn path(&self) -> PathBuf {
+        config_path(&self.filename)
     }
 
-    /// Attempt to reload the configuration from the configuration file.
+    /// Attempt to reload the configuration from the configuration file, returning the names of the
+    /// settings that changed, or None if they couldn't be compared.
     ///
     /// This only updates the values stored in memory but doesn't perform any additional actions
     /// like updating active keybindings.
-    pub fn reload(&self) -> Result<(), Box<dyn Error>> {
+    pub fn reload(&self) -> Result<Option<Vec<String>>, Box<dyn Error>> {
         let cfg = load(&self.filename)?;
-        *self.values.write().unwrap() = cfg;
-        Ok(())
+        let mut values = self.values.write().unwrap();
+        let changed = changed_settings(&values, &cfg);
+        *values = cfg;
+        Ok(changed)
     }
 
     /// Update the backend device in the configuration.
@@ -296,6 +777,56 @@ impl Config {
     }
 }
 
+/// The names of the settings that have different values in `old` and `new`, or None if they can't
+/// be compared.
+fn changed_settings(old: &ConfigValues, new: &ConfigValues) -> Option<Vec<String>> {
+    let table = |values: &ConfigValues| match toml::Value::try_from(values) {
+        Ok(toml::Value::Table(table)) => Some(table),
+        Ok(_) => None,
+        Err(e) => {
+            error!("Could not compare the configuration: {e}");
+            None
+        }
+    };
+    let (old, new) = (table(old)?, table(new)?);
+
+    let mut changed: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    Some(changed)
+}
+
+/// Load the runtime state of `profile`, overridden by the settings in `values`.
+fn load_state(values: &ConfigValues, profile: &Profile) -> UserState {
+    let path = profile.config_path(USER_STATE_FILE_NAME);
+    let mut userstate = CBOR
+        .load_or_generate_default(path, || Ok(UserState::default()), true)
+        .expect("could not load user state");
+
+    if let Some(shuffle) = values.shuffle {
+        userstate.shuffle = shuffle;
+    }
+
+    if let Some(shuffle_mode) = values.shuffle_mode {
+        userstate.shuffle_mode = shuffle_mode;
+    }
+
+    if let Some(repeat) = values.repeat {
+        userstate.repeat = repeat;
+    }
+
+    if let Some(playback_state) = values.playback_state.clone() {
+        userstate.playback_state = playback_state;
+    }
+
+    userstate
+}
+
 /// Parse the configuration file with name `filename` at the configuration base path.
 fn load(filename: &str) -> Result<ConfigValues, String> {
     let path = config_path(filename);
@@ -375,3 +906,66 @@ pub fn set_configuration_base_path(base_path: Option<PathBuf>) {
         *BASE_PATH.write().unwrap() = Some(basepath);
     }
 }
+
+/// Whether `name` can be used as the name of a profile. As it becomes a directory name, only
+/// letters, digits, `-` and `_` are allowed.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The names of all profiles that have been used so far, including the default profile.
+pub fn profiles() -> Vec<String> {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    if let Some(directory) = user_configuration_directory()
+        && let Ok(entries) = fs::read_dir(directory.join(PROFILES_DIRECTORY))
+    {
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        names.sort();
+        profiles.extend(names);
+    }
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_changed_settings() {
+        let old: ConfigValues =
+            toml::from_str("bitrate = 320\nuse_nerdfont = true\n[keybindings]\n\"q\" = \"quit\"")
+                .unwrap();
+        let new: ConfigValues =
+            toml::from_str("bitrate = 160\nuse_nerdfont = true\ngapless = false").unwrap();
+
+        assert_eq!(
+            changed_settings(&old, &new).unwrap(),
+            vec!["bitrate", "gapless", "keybindings"]
+        );
+        assert_eq!(changed_settings(&new, &new), Some(Vec::new()));
+    }
+
+    #[test]
+    fn validates_profile_names() {
+        assert!(is_valid_profile_name("work"));
+        assert!(is_valid_profile_name("family-2_shared"));
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("../work"));
+        assert!(!is_valid_profile_name("my profile"));
+    }
+
+    #[test]
+    fn selects_the_default_profile() {
+        assert!(Profile::new(None).is_default());
+        assert!(Profile::new(Some(DEFAULT_PROFILE.into())).is_default());
+        assert_eq!(Profile::new(Some("work".into())).name(), "work");
+        assert_eq!(Profile::new(None).to_string(), DEFAULT_PROFILE);
+    }
+}
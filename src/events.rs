@@ -9,7 +9,13 @@ pub enum Event {
     Player(PlayerEvent),
     Queue(QueueEvent),
     SessionDied,
+    /// The worker connected to Spotify after it was offline.
+    Reconnected,
     IpcInput(String),
+    SwitchProfile(String),
+    /// The outcome of a command that finished in the background, which is shown like the result
+    /// of a command.
+    CommandResult(Result<Option<String>, String>),
     #[cfg(target_os = "macos")]
     AudioDeviceChanged(String),
 }
@@ -0,0 +1,431 @@
+//! Filter expressions that narrow down the items shown in a list.
+//!
+//! An expression consists of terms separated by whitespace, all of which have to match:
+//! - `field:value` matches items of which the field contains `value`. Instead of `:`, the
+//!   comparisons `:=`, `:<`, `:<=`, `:>` and `:>=` can be used, like in `year:<1970`.
+//! - Dates can be given relative to today in days or weeks: `added:>=30d` matches items that were
+//!   added in the last 30 days.
+//! - Any other text is searched for in the title, artists and album.
+//! - `-term` matches items that don't match `term`.
+//! - `@name` inserts the smart view `name` from the configuration.
+//! - `or` separates alternatives, like in `artist:beatles or artist:kinks`.
+//!
+//! Values that contain spaces can be quoted: `artist:"the beatles"`.
+
+use std::fmt;
+
+use chrono::{Days, NaiveDate, Utc};
+
+/// The maximum depth of smart views referring to other smart views.
+const MAX_VIEW_DEPTH: usize = 8;
+
+/// The properties of an item that can be filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Artist,
+    Album,
+    Year,
+    /// The duration in seconds.
+    Duration,
+    /// The date the item was saved, as `YYYY-MM-DD`.
+    Added,
+    Popularity,
+    Saved,
+    Explicit,
+    /// Whether the user follows one of the artists of the item.
+    Followed,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "title" => Some(Self::Title),
+            "artist" | "artists" => Some(Self::Artist),
+            "album" => Some(Self::Album),
+            "year" => Some(Self::Year),
+            "duration" | "length" => Some(Self::Duration),
+            "added" => Some(Self::Added),
+            "popularity" => Some(Self::Popularity),
+            "saved" => Some(Self::Saved),
+            "explicit" => Some(Self::Explicit),
+            "followed" => Some(Self::Followed),
+            _ => None,
+        }
+    }
+
+    /// Parse `value` as it is written in a filter for this field, which is called `name`.
+    fn parse_value(&self, name: &str, value: &str) -> Result<Value, String> {
+        let invalid = || format!("Invalid value for filter field {name}: {value}");
+        match self {
+            Self::Title | Self::Artist | Self::Album => Ok(Value::Text(value.to_lowercase())),
+            Self::Added => Ok(Value::Text(
+                relative_date(value, Utc::now().date_naive())
+                    .unwrap_or_else(|| value.to_lowercase()),
+            )),
+            Self::Year | Self::Popularity => {
+                value.parse().map(Value::Number).map_err(|_| invalid())
+            }
+            Self::Duration => parse_duration(value).map(Value::Number).ok_or_else(invalid),
+            Self::Saved | Self::Explicit | Self::Followed => match value {
+                "yes" | "y" | "true" | "1" => Ok(Value::Bool(true)),
+                "no" | "n" | "false" | "0" => Ok(Value::Bool(false)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+/// The value of a [Field] of an item.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Value {
+    Text(String),
+    Number(u64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Contains,
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Split the comparison off the start of `value`.
+    fn split(value: &str) -> (Self, &str) {
+        [
+            ("<=", Self::LessOrEqual),
+            (">=", Self::GreaterOrEqual),
+            ("<", Self::Less),
+            (">", Self::Greater),
+            ("=", Self::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| value.strip_prefix(prefix).map(|rest| (comparison, rest)))
+        .unwrap_or((Self::Contains, value))
+    }
+
+    /// Compare the value of an item to the value of the filter.
+    fn matches(&self, item: &Value, filter: &Value) -> bool {
+        let item = match item {
+            Value::Text(text) => Value::Text(text.to_lowercase()),
+            value => value.clone(),
+        };
+        match (self, &item, filter) {
+            (Self::Contains, Value::Text(item), Value::Text(filter)) => item.contains(filter),
+            (Self::Contains | Self::Equal, item, filter) => item == filter,
+            _ if std::mem::discriminant(&item) != std::mem::discriminant(filter) => false,
+            (Self::Less, item, filter) => item < filter,
+            (Self::LessOrEqual, item, filter) => item <= filter,
+            (Self::Greater, item, filter) => item > filter,
+            (Self::GreaterOrEqual, item, filter) => item >= filter,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Text that is searched for in the title, artists and album.
+    Text(String),
+    Field {
+        field: Field,
+        comparison: Comparison,
+        value: Value,
+    },
+    /// A smart view from the configuration.
+    View(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    term: Term,
+    negated: bool,
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expression: String,
+    /// Alternatives of which at least one has to match. All conditions of an alternative have to
+    /// match.
+    alternatives: Vec<Vec<Condition>>,
+}
+
+impl Filter {
+    /// Parse `expression`. `views` returns the expression of the smart view with the given name.
+    pub fn parse(expression: &str, views: &dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Self::parse_nested(expression, views, 0)
+    }
+
+    fn parse_nested(
+        expression: &str,
+        views: &dyn Fn(&str) -> Option<String>,
+        depth: usize,
+    ) -> Result<Self, String> {
+        if depth > MAX_VIEW_DEPTH {
+            return Err("Smart views are nested too deeply".to_string());
+        }
+
+        let mut alternatives = vec![Vec::new()];
+        for token in tokenize(expression) {
+            if token.eq_ignore_ascii_case("or") {
+                alternatives.push(Vec::new());
+                continue;
+            }
+
+            let (negated, token) = match token.strip_prefix(['-', '!']) {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+
+            let term = if let Some(name) = token.strip_prefix('@') {
+                let view = views(name).ok_or_else(|| format!("Unknown smart view: {name}"))?;
+                Term::View(Self::parse_nested(&view, views, depth + 1)?)
+            } else if let Some((name, value)) = token.split_once(':') {
+                let field = Field::parse(&name.to_lowercase())
+                    .ok_or_else(|| format!("Unknown filter field: {name}"))?;
+                let (comparison, value) = Comparison::split(value);
+                if value.is_empty() {
+                    return Err(format!("Missing value for filter field: {name}"));
+                }
+                Term::Field {
+                    field,
+                    comparison,
+                    value: field.parse_value(name, value)?,
+                }
+            } else {
+                Term::Text(token.to_lowercase())
+            };
+
+            if let Some(conditions) = alternatives.last_mut() {
+                conditions.push(Condition { term, negated });
+            }
+        }
+
+        alternatives.retain(|conditions| !conditions.is_empty());
+        if alternatives.is_empty() {
+            return Err("Empty filter".to_string());
+        }
+
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            alternatives,
+        })
+    }
+
+    /// Whether an item matches the filter. `value` returns the value of a field of the item, or
+    /// [None] if the item doesn't have the field.
+    pub fn matches(&self, value: &dyn Fn(Field) -> Option<Value>) -> bool {
+        self.alternatives.iter().any(|conditions| {
+            conditions
+                .iter()
+                .all(|condition| condition.negated != Self::term_matches(&condition.term, value))
+        })
+    }
+
+    fn term_matches(term: &Term, value: &dyn Fn(Field) -> Option<Value>) -> bool {
+        match term {
+            Term::Text(text) => [Field::Title, Field::Artist, Field::Album]
+                .into_iter()
+                .filter_map(value)
+                .any(|field| match field {
+                    Value::Text(field) => field.to_lowercase().contains(text),
+                    _ => false,
+                }),
+            Term::Field {
+                field,
+                comparison,
+                value: filter,
+            } => value(*field).is_some_and(|item| comparison.matches(&item, filter)),
+            Term::View(filter) => filter.matches(value),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// Split `expression` at whitespace outside of double quotes, removing the quotes.
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// The date that lies the number of days or weeks given in `value`, like `30d` or `2w`, before
+/// `today`, formatted as `YYYY-MM-DD`.
+fn relative_date(value: &str, today: NaiveDate) -> Option<String> {
+    let days = if let Some(days) = value.strip_suffix('d') {
+        days.parse::<u64>().ok()?
+    } else {
+        value.strip_suffix('w')?.parse::<u64>().ok()? * 7
+    };
+    today
+        .checked_sub_days(Days::new(days))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Parse a duration like `90`, `5m`, `1h2m3s` or `4:30` into seconds.
+fn parse_duration(value: &str) -> Option<u64> {
+    if value.contains(':') {
+        return value.split(':').try_fold(0, |total, part| {
+            Some(total * 60 + part.parse::<u64>().ok()?)
+        });
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                total += number.parse::<u64>().ok()? * unit;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+
+    if !number.is_empty() {
+        total += number.parse::<u64>().ok()?;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::album::Album;
+    use crate::model::track::Track;
+    use crate::traits::ListItem;
+
+    fn no_views(_: &str) -> Option<String> {
+        None
+    }
+
+    fn track(title: &str, artist: &str, year: &str, duration: u32) -> Track {
+        Track {
+            id: None,
+            uri: String::new(),
+            title: title.to_string(),
+            track_number: 1,
+            disc_number: 1,
+            duration,
+            artists: vec![artist.to_string()],
+            artist_ids: Vec::new(),
+            album: Some(format!("{artist} album")),
+            album_id: None,
+            album_artists: vec![artist.to_string()],
+            cover_url: None,
+            url: String::new(),
+            added_at: None,
+            list_index: 0,
+            is_local: false,
+            is_playable: None,
+            release_date: Some(format!("{year}-01-01")),
+            popularity: Some(50),
+            explicit: false,
+            autoplay: false,
+        }
+    }
+
+    fn matches(expression: &str, track: &Track) -> bool {
+        Filter::parse(expression, &no_views)
+            .unwrap()
+            .matches(&|field| track.filter_value(field))
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("5m"), Some(300));
+        assert_eq!(parse_duration("1h2m3s"), Some(3723));
+        assert_eq!(parse_duration("4:30"), Some(270));
+        assert_eq!(parse_duration("5x"), None);
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        assert_eq!(relative_date("30d", today).as_deref(), Some("2024-02-09"));
+        assert_eq!(relative_date("2w", today).as_deref(), Some("2024-02-25"));
+        assert_eq!(relative_date("2024-01-01", today), None);
+    }
+
+    #[test]
+    fn matches_fields_and_comparisons() {
+        let help = track("Help!", "The Beatles", "1965", 140_000);
+        assert!(matches("artist:beatles year:<1970", &help));
+        assert!(matches("help duration:<=2m20s", &help));
+        assert!(!matches("artist:beatles year:>=1970", &help));
+        assert!(!matches("duration:>5m", &help));
+        assert!(matches("-artist:kinks popularity:=50", &help));
+        assert!(matches("artist:\"the beatles\"", &help));
+        assert!(!matches("explicit:yes", &help));
+    }
+
+    #[test]
+    fn combines_alternatives_and_views() {
+        let help = track("Help!", "The Beatles", "1965", 140_000);
+        let views = |name: &str| (name == "sixties").then(|| "year:>=1960 year:<1970".to_string());
+        let filter = Filter::parse("@sixties or artist:kinks", &views).unwrap();
+        assert!(filter.matches(&|field| help.filter_value(field)));
+
+        let filter = Filter::parse("artist:kinks or -@sixties", &views).unwrap();
+        assert!(!filter.matches(&|field| help.filter_value(field)));
+    }
+
+    #[test]
+    fn matches_albums() {
+        let album: Album = serde_json::from_value(serde_json::json!({
+            "title": "Revolver",
+            "artists": ["The Beatles"],
+            "artist_ids": [],
+            "year": "1966",
+        }))
+        .unwrap();
+        let filter = Filter::parse("revolver year:1966", &no_views).unwrap();
+        assert!(filter.matches(&|field| album.filter_value(field)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(Filter::parse("colour:red", &no_views).is_err());
+        assert!(Filter::parse("year:old", &no_views).is_err());
+        assert!(Filter::parse("year:", &no_views).is_err());
+        assert!(Filter::parse("@missing", &no_views).is_err());
+        assert!(Filter::parse("  ", &no_views).is_err());
+
+        let recursive = |_: &str| Some("@loop".to_string());
+        assert!(Filter::parse("@loop", &recursive).is_err());
+    }
+}
@@ -36,6 +36,13 @@ pub fn program_arguments() -> clap::Command {
                 .value_parser(PathBufValueParser::new())
                 .help("custom basepath to config/cache files"),
         )
+        .arg(
+            clap::Arg::new("profile")
+                .short('p')
+                .long("profile")
+                .value_name("NAME")
+                .help("Profile with its own credentials, user state and library cache"),
+        )
         .arg(
             clap::Arg::new("config")
                 .short('c')
@@ -44,5 +51,32 @@ pub fn program_arguments() -> clap::Command {
                 .help("Filename of config file in basepath")
                 .default_value(CONFIGURATION_FILE_NAME),
         )
-        .subcommands([clap::Command::new("info").about("Print platform information like paths")])
+        .subcommands([
+            clap::Command::new("info").about("Print platform information like paths"),
+            clap::Command::new("config")
+                .about("Inspect the configuration file")
+                .subcommand_required(true)
+                .subcommands([
+                    clap::Command::new("check")
+                        .about("Report all problems in the configuration file"),
+                    clap::Command::new("dump")
+                        .about("Print the effective configuration including the defaults"),
+                ]),
+            clap::Command::new("auth")
+                .about("Manage the stored credentials of the profile")
+                .subcommand_required(true)
+                .subcommands([
+                    clap::Command::new("login")
+                        .about("Log in and store the credentials")
+                        .arg(
+                            clap::Arg::new("headless")
+                                .long("headless")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Paste the redirect URL or code instead of using a browser"),
+                        ),
+                    clap::Command::new("status")
+                        .about("Show whether credentials are stored and when the token expires"),
+                    clap::Command::new("logout").about("Remove the stored credentials"),
+                ]),
+        ])
 }
@@ -1,25 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::iter::Iterator;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread;
 
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use rspotify::model::Id;
-use serde::Serialize;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
+use crate::application::ASYNC_RUNTIME;
 use crate::config::Config;
-use crate::config::{self, CACHE_VERSION};
+use crate::config::{CACHE_VERSION, SmartPlaylistConfig, SmartPlaylistSource};
 use crate::events::EventManager;
+use crate::filter::{Field, Filter, Value};
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
+#[cfg(feature = "notify")]
+use crate::notification::Notifier;
+use crate::outbox::{Operation, Outbox};
 use crate::spotify::Spotify;
+use crate::traits::ListItem;
 
 /// Cached tracks database filename.
 const CACHE_TRACKS: &str = "tracks.db";
@@ -33,6 +40,30 @@ const CACHE_ARTISTS: &str = "artists.db";
 /// Cached playlists database filename.
 const CACHE_PLAYLISTS: &str = "playlists.db";
 
+/// Cached user info filename.
+const CACHE_USER: &str = "user.db";
+
+/// All cached library database filenames. The user info isn't included, as it's needed to use the
+/// library offline.
+pub const CACHE_FILES: [&str; 4] = [CACHE_TRACKS, CACHE_ALBUMS, CACHE_ARTISTS, CACHE_PLAYLISTS];
+
+/// Filename of the changes that are made while offline. It isn't a cache, as the changes would be
+/// lost when it's removed.
+const OUTBOX: &str = "outbox.db";
+
+/// Names under which the sort orders of the library lists are remembered in the user state.
+pub const ORDER_TRACKS: &str = "tracks";
+pub const ORDER_ALBUMS: &str = "albums";
+pub const ORDER_ARTISTS: &str = "artists";
+pub const ORDER_SHOWS: &str = "shows";
+
+/// The info about the logged in user that is cached so that it's available when starting offline.
+#[derive(Serialize, Deserialize)]
+struct CachedUser {
+    id: String,
+    display_name: Option<String>,
+}
+
 /// The user library with all their saved tracks, albums, playlists... High level interface to the
 /// Spotify API used to manage items in the user library.
 #[derive(Clone)]
@@ -43,18 +74,27 @@ pub struct Library {
     pub playlists: Arc<RwLock<Vec<Playlist>>>,
     pub shows: Arc<RwLock<Vec<Show>>>,
     pub is_done: Arc<RwLock<bool>>,
+    /// Incremented whenever the items of the library change, see [Library::generation].
+    generation: Arc<AtomicUsize>,
     pub user_id: Option<String>,
     pub display_name: Option<String>,
+    /// The directory with the cached library of the profile this library belongs to.
+    cache_directory: PathBuf,
+    /// The changes that were made while offline and still have to be sent to Spotify.
+    outbox: Arc<Outbox>,
     ev: EventManager,
     spotify: Spotify,
     pub cfg: Arc<Config>,
+    #[cfg(feature = "notify")]
+    pub notifier: Notifier,
 }
 
 impl Library {
     pub fn new(ev: EventManager, spotify: Spotify, cfg: Arc<Config>) -> Self {
-        let current_user = spotify.api.current_user().ok();
-        let user_id = current_user.as_ref().map(|u| u.id.id().to_string());
-        let display_name = current_user.as_ref().and_then(|u| u.display_name.clone());
+        let cache_directory = cfg.profile().cache_path("");
+        let user = Self::load_user(&spotify, &cache_directory.join(CACHE_USER));
+        let user_id = user.as_ref().map(|u| u.id.clone());
+        let display_name = user.and_then(|u| u.display_name);
 
         let library = Self {
             tracks: Arc::new(RwLock::new(Vec::new())),
@@ -63,8 +103,13 @@ impl Library {
             playlists: Arc::new(RwLock::new(Vec::new())),
             shows: Arc::new(RwLock::new(Vec::new())),
             is_done: Arc::new(RwLock::new(false)),
+            generation: Arc::new(AtomicUsize::new(0)),
             user_id,
             display_name,
+            outbox: Arc::new(Outbox::load(cache_directory.join(OUTBOX))),
+            cache_directory,
+            #[cfg(feature = "notify")]
+            notifier: Notifier::new(cfg.clone(), ev.clone()),
             ev,
             spotify,
             cfg,
@@ -74,6 +119,45 @@ impl Library {
         library
     }
 
+    /// Fetch the info about the logged in user and cache it in the file at `cache_path`. Use the
+    /// cached info when offline or if it can't be fetched.
+    fn load_user(spotify: &Spotify, cache_path: &Path) -> Option<CachedUser> {
+        let current_user = if spotify.is_offline() {
+            None
+        } else {
+            spotify.api.current_user().ok()
+        };
+
+        match current_user {
+            Some(current_user) => {
+                let user = CachedUser {
+                    id: current_user.id.id().to_string(),
+                    display_name: current_user.display_name,
+                };
+                let result = serde_json::to_string(&user)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| std::fs::write(cache_path, json).map_err(|e| e.to_string()));
+                if let Err(e) = result {
+                    error!("could not write user cache: {e}");
+                }
+                Some(user)
+            }
+            None => {
+                info!("using the cached user info");
+                let contents = std::fs::read_to_string(cache_path).ok()?;
+                serde_json::from_str(&contents)
+                    .inspect_err(|e| error!("can't parse user cache: {e}"))
+                    .ok()
+            }
+        }
+    }
+
+    /// The path of the cache `file` of this library. It stays the same after switching profiles, so
+    /// that threads that are still running don't write into the cache of another profile.
+    fn cache_path(&self, file: &str) -> PathBuf {
+        self.cache_directory.join(file)
+    }
+
     /// Load cached items from the file at `cache_path` into the given `store`.
     fn load_cache<T: DeserializeOwned>(&self, cache_path: &Path, store: &mut Vec<T>) {
         let saved_cache_version = self.cfg.state().cache_version;
@@ -97,6 +181,7 @@ impl Library {
                     );
                     store.clear();
                     store.extend(cache);
+                    self.changed();
 
                     // force refresh of UI (if visible)
                     self.trigger_redraw();
@@ -108,8 +193,10 @@ impl Library {
         }
     }
 
-    /// Save the items from `store` in the file at `cache_path`.
+    /// Save the items from `store` in the file at `cache_path`. The cache is saved after every
+    /// change of the library, so this also marks the library as changed.
     fn save_cache<T: Serialize>(&self, cache_path: &Path, store: &[T]) {
+        self.changed();
         let cache_file = File::create(cache_path).unwrap();
         let serialize_result = serde_json::to_writer(cache_file, store);
         if let Err(message) = serialize_result {
@@ -161,7 +248,7 @@ impl Library {
         {
             self.playlists.write().unwrap().remove(position);
             self.save_cache(
-                &config::cache_path(CACHE_PLAYLISTS),
+                &self.cache_path(CACHE_PLAYLISTS),
                 &self.playlists.read().unwrap(),
             );
         }
@@ -175,7 +262,7 @@ impl Library {
 
         self.fetch_playlists();
         self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
+            &self.cache_path(CACHE_PLAYLISTS),
             &self.playlists.read().unwrap(),
         );
     }
@@ -193,18 +280,22 @@ impl Library {
     pub fn update_library(&self) {
         *self.is_done.write().unwrap() = false;
 
+        // While offline, only the cached library is loaded.
+        let online = !self.spotify.is_offline();
         let library = self.clone();
         thread::spawn(move || {
             let t_tracks = {
                 let library = library.clone();
                 thread::spawn(move || {
                     library.load_cache(
-                        &config::cache_path(CACHE_TRACKS),
+                        &library.cache_path(CACHE_TRACKS),
                         library.tracks.write().unwrap().as_mut(),
                     );
-                    library.fetch_tracks();
+                    if online {
+                        library.fetch_tracks();
+                    }
                     library.save_cache(
-                        &config::cache_path(CACHE_TRACKS),
+                        &library.cache_path(CACHE_TRACKS),
                         &library.tracks.read().unwrap(),
                     );
                 })
@@ -214,12 +305,14 @@ impl Library {
                 let library = library.clone();
                 thread::spawn(move || {
                     library.load_cache(
-                        &config::cache_path(CACHE_ALBUMS),
+                        &library.cache_path(CACHE_ALBUMS),
                         library.albums.write().unwrap().as_mut(),
                     );
-                    library.fetch_albums();
+                    if online {
+                        library.fetch_albums();
+                    }
                     library.save_cache(
-                        &config::cache_path(CACHE_ALBUMS),
+                        &library.cache_path(CACHE_ALBUMS),
                         &library.albums.read().unwrap(),
                     );
                 })
@@ -229,10 +322,12 @@ impl Library {
                 let library = library.clone();
                 thread::spawn(move || {
                     library.load_cache(
-                        &config::cache_path(CACHE_ARTISTS),
+                        &library.cache_path(CACHE_ARTISTS),
                         library.artists.write().unwrap().as_mut(),
                     );
-                    library.fetch_artists();
+                    if online {
+                        library.fetch_artists();
+                    }
                 })
             };
 
@@ -240,12 +335,14 @@ impl Library {
                 let library = library.clone();
                 thread::spawn(move || {
                     library.load_cache(
-                        &config::cache_path(CACHE_PLAYLISTS),
+                        &library.cache_path(CACHE_PLAYLISTS),
                         library.playlists.write().unwrap().as_mut(),
                     );
-                    library.fetch_playlists();
+                    if online {
+                        library.fetch_playlists();
+                    }
                     library.save_cache(
-                        &config::cache_path(CACHE_PLAYLISTS),
+                        &library.cache_path(CACHE_PLAYLISTS),
                         &library.playlists.read().unwrap(),
                     );
                 })
@@ -254,7 +351,9 @@ impl Library {
             let t_shows = {
                 let library = library.clone();
                 thread::spawn(move || {
-                    library.fetch_shows();
+                    if online {
+                        library.fetch_shows();
+                    }
                 })
             };
 
@@ -263,7 +362,7 @@ impl Library {
 
             library.populate_artists();
             library.save_cache(
-                &config::cache_path(CACHE_ARTISTS),
+                &library.cache_path(CACHE_ARTISTS),
                 &library.artists.read().unwrap(),
             );
 
@@ -271,8 +370,14 @@ impl Library {
             t_playlists.join().unwrap();
             t_shows.join().unwrap();
 
-            let mut is_done = library.is_done.write().unwrap();
-            *is_done = true;
+            *library.is_done.write().unwrap() = true;
+
+            // The rules of smart playlists can depend on the saved state of the items, which is
+            // only known once the library is done.
+            library.refresh_smart_playlists();
+
+            #[cfg(feature = "notify")]
+            library.notifier.library_updated(&library);
 
             library.ev.trigger();
         });
@@ -302,13 +407,21 @@ impl Library {
         }
 
         *self.shows.write().unwrap() = saved_shows;
+        self.changed();
     }
 
     /// Fetch the playlists from the web API and save them to the local library. This synchronizes
     /// the local version with the remote, pruning removed playlists in the process.
     fn fetch_playlists(&self) {
         debug!("loading playlists");
-        let mut stale_lists = self.playlists.read().unwrap().clone();
+        let mut stale_lists: Vec<Playlist> = self
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|list| !list.smart)
+            .cloned()
+            .collect();
         let mut list_order = Vec::new();
 
         let lists_page = self.spotify.api.current_user_playlist();
@@ -360,10 +473,12 @@ impl Library {
                 (true, false) => std::cmp::Ordering::Less,
                 (false, true) => std::cmp::Ordering::Greater,
                 _ => {
-                    // Otherwise sort by remote order
+                    // Otherwise sort by remote order, with the smart playlists at the end
                     let a_index = list_order.iter().position(|x| x == &a.id);
                     let b_index = list_order.iter().position(|x| x == &b.id);
-                    a_index.cmp(&b_index)
+                    a_index
+                        .unwrap_or(usize::MAX)
+                        .cmp(&b_index.unwrap_or(usize::MAX))
                 }
             }
         });
@@ -372,6 +487,111 @@ impl Library {
         self.trigger_redraw();
     }
 
+    /// Put the smart playlists from the configuration together from the library and show them
+    /// after the other playlists. Smart playlists that are written to a Spotify playlist are
+    /// only written when their tracks changed.
+    pub fn refresh_smart_playlists(&self) {
+        let configs = self
+            .cfg
+            .values()
+            .smart_playlists
+            .clone()
+            .unwrap_or_default();
+        let views = self.cfg.values().filters.clone().unwrap_or_default();
+        let owner_id = self.user_id.clone().unwrap_or_default();
+
+        let mut smart_playlists = Vec::new();
+        for config in &configs {
+            let filter = match Filter::parse(&config.rule, &|name| views.get(name).cloned()) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    error!("invalid rule of smart playlist {}: {e}", config.name);
+                    continue;
+                }
+            };
+
+            let tracks = self.smart_playlist_tracks(config, &filter);
+            if let Some(id) = &config.playlist
+                && !self.spotify.is_offline()
+                && !self.playlist_has_tracks(id, &tracks)
+            {
+                self.overwrite_playlist(id, &tracks);
+            }
+            smart_playlists.push(Playlist::smart(&config.name, &owner_id, tracks));
+        }
+
+        {
+            let mut playlists = self.playlists.write().unwrap();
+            playlists.retain(|playlist| !playlist.smart);
+            playlists.extend(smart_playlists);
+        }
+
+        self.save_cache(
+            &self.cache_path(CACHE_PLAYLISTS),
+            &self.playlists.read().unwrap(),
+        );
+        self.trigger_redraw();
+    }
+
+    /// The tracks in the library that match the rule of the smart playlist `config`.
+    fn smart_playlist_tracks(&self, config: &SmartPlaylistConfig, rule: &Filter) -> Vec<Playable> {
+        // The items are copied because checking whether they are saved locks the stores again.
+        let mut tracks: Vec<Playable> = match config.source.unwrap_or_default() {
+            SmartPlaylistSource::Tracks => {
+                let tracks = self.tracks.read().unwrap().clone();
+                tracks
+                    .into_iter()
+                    .filter(|track| rule.matches(&|field| self.filter_value(track, field)))
+                    .map(Playable::Track)
+                    .collect()
+            }
+            // Only the tracks of albums that are loaded can be added.
+            SmartPlaylistSource::Albums => {
+                let albums = self.albums.read().unwrap().clone();
+                albums
+                    .into_iter()
+                    .filter(|album| rule.matches(&|field| self.filter_value(album, field)))
+                    .filter_map(|album| album.tracks)
+                    .flatten()
+                    .map(Playable::Track)
+                    .collect()
+            }
+        };
+
+        if let Some(limit) = config.limit {
+            tracks.truncate(limit);
+        }
+        tracks
+    }
+
+    /// Check whether the playlist with `id` is known and consists of exactly `tracks`.
+    fn playlist_has_tracks(&self, id: &str, tracks: &[Playable]) -> bool {
+        self.playlists
+            .read()
+            .unwrap()
+            .iter()
+            .find(|playlist| playlist.id == id)
+            .and_then(|playlist| playlist.tracks.as_ref())
+            .is_some_and(|current| {
+                current
+                    .iter()
+                    .map(Playable::uri)
+                    .eq(tracks.iter().map(Playable::uri))
+            })
+    }
+
+    /// The value of `field` of `item` for filtering, including the fields that depend on the
+    /// library.
+    pub fn filter_value(&self, item: &dyn ListItem, field: Field) -> Option<Value> {
+        match field {
+            Field::Saved => item.is_saved(self).map(Value::Bool),
+            Field::Followed => item.artists().map(|artists| {
+                Value::Bool(artists.iter().any(|artist| self.is_followed_artist(artist)))
+            }),
+            _ => item.filter_value(field),
+        }
+    }
+
     /// Fetch the artists from the web API and save them to the local library.
     fn fetch_artists(&self) {
         let mut artists: Vec<Artist> = Vec::new();
@@ -496,12 +716,14 @@ impl Library {
 
                 let store = self.tracks.read().unwrap();
 
+                // The store may be sorted differently, so the position of the items is ignored.
+                let stored: HashSet<Option<&str>> =
+                    store.iter().map(|track| track.id.as_deref()).collect();
                 if page.total as usize == store.len()
-                    && !page
+                    && page
                         .items
                         .iter()
-                        .enumerate()
-                        .any(|(i, t)| t.track.id.as_ref().map(|id| id.to_string()) != store[i].id)
+                        .all(|t| stored.contains(&t.track.id.as_ref().map(|id| id.id())))
                 {
                     return;
                 }
@@ -596,8 +818,11 @@ impl Library {
             }
         }
 
+        #[cfg(feature = "mpris")]
+        self.spotify.notify_playlist_changed(&updated.id);
+
         self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
+            &self.cache_path(CACHE_PLAYLISTS),
             &self.playlists.read().unwrap(),
         );
     }
@@ -618,12 +843,8 @@ impl Library {
             return;
         }
 
-        let save_tracks_result = self
-            .spotify
-            .api
-            .current_user_saved_tracks_add(tracks.iter().filter_map(|t| t.id.as_deref()).collect());
-
-        if save_tracks_result.is_err() {
+        let ids = tracks.iter().filter_map(|t| t.id.clone()).collect();
+        if !self.submit(Operation::SaveTracks { ids }) {
             return;
         }
 
@@ -642,12 +863,9 @@ impl Library {
 
         self.populate_artists();
 
+        self.save_cache(&self.cache_path(CACHE_TRACKS), &self.tracks.read().unwrap());
         self.save_cache(
-            &config::cache_path(CACHE_TRACKS),
-            &self.tracks.read().unwrap(),
-        );
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
+            &self.cache_path(CACHE_ARTISTS),
             &self.artists.read().unwrap(),
         );
     }
@@ -658,14 +876,8 @@ impl Library {
             return;
         }
 
-        if self
-            .spotify
-            .api
-            .current_user_saved_tracks_delete(
-                tracks.iter().filter_map(|t| t.id.as_deref()).collect(),
-            )
-            .is_err()
-        {
+        let ids = tracks.iter().filter_map(|t| t.id.clone()).collect();
+        if !self.submit(Operation::UnsaveTracks { ids }) {
             return;
         }
 
@@ -680,12 +892,9 @@ impl Library {
 
         self.populate_artists();
 
+        self.save_cache(&self.cache_path(CACHE_TRACKS), &self.tracks.read().unwrap());
         self.save_cache(
-            &config::cache_path(CACHE_TRACKS),
-            &self.tracks.read().unwrap(),
-        );
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
+            &self.cache_path(CACHE_ARTISTS),
             &self.artists.read().unwrap(),
         );
     }
@@ -706,12 +915,10 @@ impl Library {
             return;
         }
 
-        if let Some(ref album_id) = album.id
-            && self
-                .spotify
-                .api
-                .current_user_saved_albums_add(vec![album_id.as_str()])
-                .is_err()
+        if let Some(album_id) = &album.id
+            && !self.submit(Operation::SaveAlbum {
+                id: album_id.clone(),
+            })
         {
             return;
         }
@@ -726,10 +933,7 @@ impl Library {
             }
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_ALBUMS),
-            &self.albums.read().unwrap(),
-        );
+        self.save_cache(&self.cache_path(CACHE_ALBUMS), &self.albums.read().unwrap());
     }
 
     /// Remove `album` from the user's library.
@@ -738,12 +942,10 @@ impl Library {
             return;
         }
 
-        if let Some(ref album_id) = album.id
-            && self
-                .spotify
-                .api
-                .current_user_saved_albums_delete(vec![album_id.as_str()])
-                .is_err()
+        if let Some(album_id) = &album.id
+            && !self.submit(Operation::UnsaveAlbum {
+                id: album_id.clone(),
+            })
         {
             return;
         }
@@ -753,10 +955,7 @@ impl Library {
             *store = store.iter().filter(|a| a.id != album.id).cloned().collect();
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_ALBUMS),
-            &self.albums.read().unwrap(),
-        );
+        self.save_cache(&self.cache_path(CACHE_ALBUMS), &self.albums.read().unwrap());
     }
 
     /// Check whether the user follows `artist`.
@@ -775,12 +974,10 @@ impl Library {
             return;
         }
 
-        if let Some(ref artist_id) = artist.id
-            && self
-                .spotify
-                .api
-                .user_follow_artists(vec![artist_id.as_str()])
-                .is_err()
+        if let Some(artist_id) = &artist.id
+            && !self.submit(Operation::FollowArtist {
+                id: artist_id.clone(),
+            })
         {
             return;
         }
@@ -799,7 +996,7 @@ impl Library {
         self.populate_artists();
 
         self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
+            &self.cache_path(CACHE_ARTISTS),
             &self.artists.read().unwrap(),
         );
     }
@@ -810,12 +1007,10 @@ impl Library {
             return;
         }
 
-        if let Some(ref artist_id) = artist.id
-            && self
-                .spotify
-                .api
-                .user_unfollow_artists(vec![artist_id.as_str()])
-                .is_err()
+        if let Some(artist_id) = &artist.id
+            && !self.submit(Operation::UnfollowArtist {
+                id: artist_id.clone(),
+            })
         {
             return;
         }
@@ -830,7 +1025,7 @@ impl Library {
         self.populate_artists();
 
         self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
+            &self.cache_path(CACHE_ARTISTS),
             &self.artists.read().unwrap(),
         );
     }
@@ -859,13 +1054,15 @@ impl Library {
             return;
         }
 
-        let follow_playlist_result = self.spotify.api.user_playlist_follow_playlist(&playlist.id);
-
-        if follow_playlist_result.is_err() {
+        if !self.submit(Operation::FollowPlaylist {
+            id: playlist.id.clone(),
+        }) {
             return;
         }
 
-        playlist.load_tracks(&self.spotify);
+        if !self.spotify.is_offline() {
+            playlist.load_tracks(&self.spotify);
+        }
 
         {
             let mut store = self.playlists.write().unwrap();
@@ -875,7 +1072,7 @@ impl Library {
         }
 
         self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
+            &self.cache_path(CACHE_PLAYLISTS),
             &self.playlists.read().unwrap(),
         );
     }
@@ -896,13 +1093,16 @@ impl Library {
             return;
         }
 
-        if self.spotify.api.save_shows(&[show.id.as_str()]).is_ok() {
+        if self.submit(Operation::SaveShow {
+            id: show.id.clone(),
+        }) {
             {
                 let mut store = self.shows.write().unwrap();
                 if !store.iter().any(|s| s.id == show.id) {
                     store.insert(0, show.clone());
                 }
             }
+            self.changed();
         }
     }
 
@@ -912,12 +1112,105 @@ impl Library {
             return;
         }
 
-        if self.spotify.api.unsave_shows(&[show.id.as_str()]).is_ok() {
+        if self.submit(Operation::UnsaveShow {
+            id: show.id.clone(),
+        }) {
             let mut store = self.shows.write().unwrap();
             *store = store.iter().filter(|s| s.id != show.id).cloned().collect();
+            self.changed();
+        }
+    }
+
+    /// Add `tracks` to the end of the playlist with `id` on Spotify. Returns whether the local
+    /// version of the playlist should be updated.
+    pub fn add_to_playlist(&self, id: &str, tracks: &[Playable]) -> bool {
+        self.submit(Operation::AddToPlaylist {
+            id: id.to_string(),
+            tracks: tracks.to_vec(),
+        })
+    }
+
+    /// Send `operation` to Spotify, or put it in the outbox while offline. When Spotify can't be
+    /// reached, ncspot continues offline. Returns whether the local library should be changed
+    /// accordingly.
+    fn submit(&self, operation: Operation) -> bool {
+        if self.spotify.is_offline() {
+            debug!("offline, adding to the outbox: {operation:?}");
+        } else if self.send(&operation) {
+            return true;
+        } else if self.spotify.api.is_unreachable() {
+            warn!("Spotify can't be reached, continuing offline");
+            self.spotify.set_offline();
+        } else {
+            return false;
+        }
+        self.outbox.push(operation);
+        true
+    }
+
+    /// Send `operation` to Spotify, returning whether it succeeded.
+    fn send(&self, operation: &Operation) -> bool {
+        let api = &self.spotify.api;
+        match operation {
+            Operation::SaveTracks { ids } => api
+                .current_user_saved_tracks_add(ids.iter().map(String::as_str).collect())
+                .is_ok(),
+            Operation::UnsaveTracks { ids } => api
+                .current_user_saved_tracks_delete(ids.iter().map(String::as_str).collect())
+                .is_ok(),
+            Operation::SaveAlbum { id } => {
+                api.current_user_saved_albums_add(vec![id.as_str()]).is_ok()
+            }
+            Operation::UnsaveAlbum { id } => api
+                .current_user_saved_albums_delete(vec![id.as_str()])
+                .is_ok(),
+            Operation::FollowArtist { id } => api.user_follow_artists(vec![id.as_str()]).is_ok(),
+            Operation::UnfollowArtist { id } => {
+                api.user_unfollow_artists(vec![id.as_str()]).is_ok()
+            }
+            Operation::FollowPlaylist { id } => api.user_playlist_follow_playlist(id).is_ok(),
+            Operation::SaveShow { id } => api.save_shows(&[id.as_str()]).is_ok(),
+            Operation::UnsaveShow { id } => api.unsave_shows(&[id.as_str()]).is_ok(),
+            Operation::AddToPlaylist { id, tracks } => api.append_tracks(id, tracks, None).is_ok(),
         }
     }
 
+    /// Send the changes that were made while offline to Spotify and update the library afterwards,
+    /// so that it matches the library on Spotify again.
+    pub fn replay_outbox(&self) {
+        let library = self.clone();
+        thread::spawn(move || {
+            // The token for the Web API may have expired while offline.
+            if let Some(handle) = library.spotify.api.update_token() {
+                ASYNC_RUNTIME.get().unwrap().block_on(handle).ok();
+            }
+
+            if !library.outbox.is_empty() {
+                let sent = library.outbox.replay(|operation| library.send(operation));
+                info!(
+                    "sent {sent} changes from the outbox, {} left",
+                    library.outbox.len()
+                );
+            }
+            // The library on Spotify doesn't contain the changes that are left yet, so fetching it
+            // would undo them locally.
+            if library.outbox.is_empty() {
+                library.update_library();
+            }
+        });
+    }
+
+    /// A number that changes whenever the items of the library change, so that views know when to
+    /// update what they derived from them.
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Mark the items of the library as changed.
+    fn changed(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Force redraw the user interface.
     pub fn trigger_redraw(&self) {
         self.ev.trigger();
@@ -6,20 +6,23 @@ extern crate serde;
 use std::{path::PathBuf, process::exit};
 
 use application::{Application, setup_logging};
-use config::set_configuration_base_path;
+use config::{Profile, set_configuration_base_path};
 use log::error;
 use ncspot::program_arguments;
 
 mod application;
 mod authentication;
+mod cache;
 mod cli;
 mod command;
 mod commands;
 mod config;
 mod events;
 mod ext_traits;
+mod filter;
 mod library;
 mod model;
+mod outbox;
 mod panic;
 mod queue;
 mod serialization;
@@ -28,10 +31,12 @@ mod spotify;
 mod spotify_api;
 mod spotify_url;
 mod spotify_worker;
+mod template;
 mod theme;
 mod traits;
 mod ui;
 mod utils;
+mod validation;
 
 #[cfg(unix)]
 mod ipc;
@@ -39,6 +44,15 @@ mod ipc;
 #[cfg(feature = "mpris")]
 mod mpris;
 
+#[cfg(feature = "notify")]
+mod notification;
+
+#[cfg(feature = "secret_service")]
+mod secret_service;
+
+#[cfg(all(test, any(feature = "mpris", feature = "secret_service")))]
+mod private_bus;
+
 #[cfg(target_os = "macos")]
 mod macos_audio;
 
@@ -59,13 +73,41 @@ fn main() -> Result<(), String> {
     // path.
     set_configuration_base_path(matches.get_one::<PathBuf>("basepath").cloned());
 
+    // Select the profile whose credentials, user state and library cache are used.
+    let profile = matches.get_one::<String>("profile").cloned();
+    if let Some(name) = &profile
+        && !config::is_valid_profile_name(name)
+    {
+        return Err(format!(
+            "Invalid profile name \"{name}\": only letters, digits, - and _ are allowed"
+        ));
+    }
+    let profile = Profile::new(profile);
+
     match matches.subcommand() {
         Some(("info", _subcommand_matches)) => cli::info(),
+        Some(("config", subcommand_matches)) => {
+            let filename = matches.get_one::<String>("config").unwrap();
+            match subcommand_matches.subcommand() {
+                Some(("check", _)) => cli::check_config(filename),
+                Some(("dump", _)) => cli::dump_config(filename),
+                _ => unreachable!(),
+            }
+        }
+        Some(("auth", subcommand_matches)) => match subcommand_matches.subcommand() {
+            Some(("login", login_matches)) => {
+                let filename = matches.get_one::<String>("config").unwrap();
+                cli::auth_login(filename, profile, login_matches.get_flag("headless"))
+            }
+            Some(("status", _)) => cli::auth_status(&profile),
+            Some(("logout", _)) => cli::auth_logout(&profile),
+            _ => unreachable!(),
+        },
         Some((_, _)) => unreachable!(),
         None => {
             // Create the application.
             let mut application =
-                match Application::new(matches.get_one::<String>("config").cloned()) {
+                match Application::new(matches.get_one::<String>("config").cloned(), profile) {
                     Ok(application) => application,
                     Err(error) => {
                         eprintln!("{error}");
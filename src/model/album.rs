@@ -1,5 +1,7 @@
 use rand::{rng, seq::IteratorRandom};
 use rspotify::model::Id;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
@@ -7,13 +9,16 @@ use chrono::{DateTime, Utc};
 use log::debug;
 use rspotify::model::album::{FullAlbum, SavedAlbum, SimplifiedAlbum};
 
+use crate::command::SortKey;
+use crate::config::DEFAULT_USE_NERDFONT;
+use crate::filter::{Field, Value};
 use crate::library::Library;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
-use crate::model::track::Track;
+use crate::model::track::{Track, compare_artists, play_count};
 use crate::queue::Queue;
 use crate::spotify::Spotify;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::{album::AlbumView, listview::ListView};
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -154,6 +159,29 @@ impl fmt::Debug for Album {
     }
 }
 
+impl Sortable for Album {
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering {
+        let compare_title = || self.title.to_lowercase().cmp(&other.title.to_lowercase());
+        // Albums of which the tracks aren't loaded count as never played.
+        let plays = |album: &Self| -> u32 {
+            album.tracks.as_ref().map_or(0, |tracks| {
+                tracks.iter().map(|t| play_count(&t.uri, play_counts)).sum()
+            })
+        };
+
+        match *key {
+            SortKey::Title | SortKey::Album => compare_title(),
+            SortKey::Artist => compare_artists(&self.artists, &other.artists)
+                .then_with(|| self.year.cmp(&other.year))
+                .then_with(compare_title),
+            SortKey::Released => self.year.cmp(&other.year),
+            SortKey::Added => self.added_at.cmp(&other.added_at),
+            SortKey::PlayCount => plays(self).cmp(&plays(other)),
+            SortKey::Duration | SortKey::TrackNumber | SortKey::Popularity => Ordering::Equal,
+        }
+    }
+}
+
 impl ListItem for Album {
     fn is_playing(&self, queue: &Queue) -> bool {
         if let Some(tracks) = self.tracks.as_ref() {
@@ -178,7 +206,12 @@ impl ListItem for Album {
 
     fn display_right(&self, library: &Library) -> String {
         let saved = if library.is_saved_album(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
+            if library
+                .cfg
+                .values()
+                .use_nerdfont
+                .unwrap_or(DEFAULT_USE_NERDFONT)
+            {
                 "\u{f012c} "
             } else {
                 "✓ "
@@ -189,6 +222,21 @@ impl ListItem for Album {
         format!("{}{}", saved, self.year)
     }
 
+    fn filter_value(&self, field: Field) -> Option<Value> {
+        match field {
+            Field::Title | Field::Album => Some(Value::Text(self.title.clone())),
+            Field::Artist => Some(Value::Text(self.artists.join(", "))),
+            Field::Year => self.year.get(..4)?.parse().ok().map(Value::Number),
+            Field::Duration => self.tracks.as_ref().map(|tracks| {
+                Value::Number(tracks.iter().map(|t| u64::from(t.duration / 1000)).sum())
+            }),
+            Field::Added => self
+                .added_at
+                .map(|added_at| Value::Text(added_at.format("%Y-%m-%d").to_string())),
+            Field::Popularity | Field::Saved | Field::Explicit | Field::Followed => None,
+        }
+    }
+
     fn play(&mut self, queue: &Queue) {
         self.load_all_tracks(queue.get_spotify());
 
@@ -1,15 +1,19 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
 use rspotify::model::Id;
 use rspotify::model::artist::{FullArtist, SimplifiedArtist};
 
+use crate::command::SortKey;
+use crate::config::DEFAULT_USE_NERDFONT;
 use crate::library::Library;
 use crate::model::playable::Playable;
-use crate::model::track::Track;
+use crate::model::track::{Track, compare_artists, play_count};
 use crate::queue::Queue;
 use crate::spotify::Spotify;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::{artist::ArtistView, listview::ListView};
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -77,6 +81,26 @@ impl fmt::Debug for Artist {
     }
 }
 
+impl Sortable for Artist {
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering {
+        // Only the saved tracks of the artist are counted.
+        let plays = |artist: &Self| -> u32 {
+            artist.tracks.as_ref().map_or(0, |tracks| {
+                tracks.iter().map(|t| play_count(&t.uri, play_counts)).sum()
+            })
+        };
+
+        match *key {
+            SortKey::Title | SortKey::Artist => compare_artists(
+                std::slice::from_ref(&self.name),
+                std::slice::from_ref(&other.name),
+            ),
+            SortKey::PlayCount => plays(self).cmp(&plays(other)),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
 impl ListItem for Artist {
     fn is_playing(&self, queue: &Queue) -> bool {
         if let Some(tracks) = &self.tracks {
@@ -100,7 +124,12 @@ impl ListItem for Artist {
 
     fn display_right(&self, library: &Library) -> String {
         let followed = if library.is_followed_artist(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
+            if library
+                .cfg
+                .values()
+                .use_nerdfont
+                .unwrap_or(DEFAULT_USE_NERDFONT)
+            {
                 "\u{f012c} "
             } else {
                 "✓ "
@@ -1,9 +1,12 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::{
+    command::SortKey,
     library::Library,
     queue::Queue,
-    traits::{IntoBoxedViewExt, ListItem},
+    traits::{IntoBoxedViewExt, ListItem, Sortable},
     ui::listview::ListView,
 };
 
@@ -22,6 +25,20 @@ impl From<&rspotify::model::Category> for Category {
     }
 }
 
+impl Sortable for Category {
+    fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        _play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        match *key {
+            SortKey::Title => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
 impl ListItem for Category {
     fn is_playing(&self, _queue: &Queue) -> bool {
         false
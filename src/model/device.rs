@@ -0,0 +1,342 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use log::{error, info};
+use rspotify::model::PlayableItem;
+
+use crate::command::SortKey;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::spotify::PlayerEvent;
+use crate::traits::{ListItem, Sortable, ViewExt};
+
+/// How playback is transferred when a [Device] is selected.
+#[derive(Debug, PartialEq, Eq)]
+enum Transfer<'a> {
+    /// Take over playback on the running instance.
+    ToLocal,
+    /// Hand playback over to the remote device with the given id.
+    ToRemote(&'a str),
+    /// The device can't be controlled through the Web API.
+    Unavailable,
+}
+
+/// A Spotify Connect device that playback can be transferred to.
+#[derive(Clone, Debug)]
+pub struct Device {
+    pub id: Option<String>,
+    pub name: String,
+    pub device_type: String,
+    pub is_active: bool,
+    pub volume_percent: Option<u32>,
+    /// The item that is playing on this device. Only known for the active device.
+    pub playing: Option<Playable>,
+    /// Whether this is the running ncspot instance.
+    pub is_local: bool,
+}
+
+impl Device {
+    /// The entry for the running ncspot instance.
+    pub fn local(name: String, queue: &Queue) -> Self {
+        let spotify = queue.get_spotify();
+        let is_active =
+            !spotify.is_remote() && spotify.get_current_status() != PlayerEvent::Stopped;
+        Self {
+            id: None,
+            name,
+            device_type: "Computer".to_string(),
+            is_active,
+            volume_percent: Some(u32::from(spotify.volume()) * 100 / u32::from(u16::MAX)),
+            playing: queue.get_current().filter(|_| is_active),
+            is_local: true,
+        }
+    }
+
+    /// The remote devices in `devices`. In receiver mode Spotify lists the running instance as
+    /// well, which is skipped if `local_name` is given. The active device shows `playing`.
+    pub fn remote(
+        devices: &[rspotify::model::Device],
+        local_name: Option<&str>,
+        playing: Option<&Playable>,
+    ) -> Vec<Self> {
+        devices
+            .iter()
+            .filter(|device| local_name != Some(device.name.as_str()))
+            .map(|device| {
+                let mut device = Self::from(device);
+                if device.is_active {
+                    device.playing = playing.cloned();
+                }
+                device
+            })
+            .collect()
+    }
+
+    /// How playback is transferred when this device is selected.
+    fn transfer(&self) -> Transfer<'_> {
+        match self.id.as_deref() {
+            _ if self.is_local => Transfer::ToLocal,
+            Some(id) => Transfer::ToRemote(id),
+            None => Transfer::Unavailable,
+        }
+    }
+
+    /// Transfer playback to this device. This calls the Web API, so it shouldn't be done on the
+    /// user interface thread.
+    pub fn transfer_playback(&self, queue: &Queue) -> Result<(), String> {
+        match self.transfer() {
+            Transfer::ToLocal => Self::transfer_to_local(queue),
+            Transfer::ToRemote(id) => Self::transfer_to_remote(id, queue),
+            Transfer::Unavailable => Err(format!("{} can't be controlled", self.name)),
+        }
+    }
+
+    /// Take over playback from the active remote device, continuing at the same position.
+    fn transfer_to_local(queue: &Queue) -> Result<(), String> {
+        let spotify = queue.get_spotify();
+        if spotify.is_remote() {
+            // A Connect client is already playing on this instance.
+            return Ok(());
+        }
+
+        let Ok(Some(context)) = spotify.api.current_playback() else {
+            info!("no active device to transfer playback from");
+            return Ok(());
+        };
+        let Some(item) = context
+            .item
+            .as_ref()
+            .filter(|item| !matches!(item, PlayableItem::Unknown(_)))
+        else {
+            return Ok(());
+        };
+
+        let position_ms = context
+            .progress
+            .map(|progress| progress.num_milliseconds() as u32)
+            .unwrap_or(0);
+        if spotify
+            .api
+            .pause_playback(context.device.id.as_deref())
+            .is_err()
+        {
+            error!("could not pause playback on {}", context.device.name);
+        }
+        queue.resume_from(Playable::from(item), position_ms);
+        Ok(())
+    }
+
+    /// Hand the local playback over to the remote device with `device_id`.
+    fn transfer_to_remote(device_id: &str, queue: &Queue) -> Result<(), String> {
+        let spotify = queue.get_spotify();
+        let local_playback = queue
+            .get_current()
+            .filter(|_| !spotify.is_remote())
+            .filter(|_| spotify.get_current_status() != PlayerEvent::Stopped);
+
+        let transferred = match local_playback {
+            Some(playable) => {
+                let position_ms = spotify.get_current_progress().as_millis() as u32;
+                let started = spotify
+                    .api
+                    .start_playback(device_id, &playable, position_ms)
+                    .is_ok();
+                if started {
+                    spotify.pause();
+                }
+                started
+            }
+            None => spotify.api.transfer_playback(device_id, true).is_ok(),
+        };
+        if transferred {
+            Ok(())
+        } else {
+            Err(format!("Could not transfer playback to device {device_id}"))
+        }
+    }
+}
+
+impl From<&rspotify::model::Device> for Device {
+    fn from(device: &rspotify::model::Device) -> Self {
+        Self {
+            id: device.id.clone(),
+            name: device.name.clone(),
+            device_type: format!("{:?}", device._type),
+            is_active: device.is_active,
+            volume_percent: device.volume_percent,
+            playing: None,
+            is_local: false,
+        }
+    }
+}
+
+impl Sortable for Device {
+    fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        _play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        match *key {
+            SortKey::Title => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl ListItem for Device {
+    fn is_playing(&self, _queue: &Queue) -> bool {
+        self.is_active
+    }
+
+    fn display_left(&self, _library: &Library) -> String {
+        if self.is_local {
+            format!("{} (this device)", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    fn display_center(&self, library: &Library) -> String {
+        self.playing
+            .as_ref()
+            .map(|playable| Playable::format(playable, "%artists - %title", library))
+            .unwrap_or_default()
+    }
+
+    fn display_right(&self, _library: &Library) -> String {
+        match self.volume_percent {
+            Some(volume) => format!("{} {volume}%", self.device_type),
+            None => self.device_type.clone(),
+        }
+    }
+
+    fn play(&mut self, queue: &Queue) {
+        if let Err(e) = self.transfer_playback(queue) {
+            error!("{e}");
+        }
+    }
+
+    fn play_next(&mut self, _queue: &Queue) {}
+
+    fn queue(&mut self, _queue: &Queue) {}
+
+    fn toggle_saved(&mut self, _library: &Library) {}
+
+    fn save(&mut self, _library: &Library) {}
+
+    fn unsave(&mut self, _library: &Library) {}
+
+    fn open(&self, _queue: Arc<Queue>, _library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
+        None
+    }
+
+    fn share_url(&self) -> Option<String> {
+        None
+    }
+
+    fn as_listitem(&self) -> Box<dyn ListItem> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rspotify::model::DevicePayload;
+
+    use super::*;
+
+    const DEVICES: &str = r#"{
+        "devices": [
+            {
+                "id": "5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e",
+                "is_active": false,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "Living Room",
+                "supports_volume": true,
+                "type": "Speaker",
+                "volume_percent": 40
+            },
+            {
+                "id": "a6f5dfe7c2b2c8d1e8fa97e0b2ab6fb84b2fb5fb",
+                "is_active": true,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "Phone",
+                "supports_volume": false,
+                "type": "Smartphone",
+                "volume_percent": null
+            },
+            {
+                "id": "0d1841b0976bae2a3a310dd74c0f3df354899bc8",
+                "is_active": false,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "ncspot",
+                "supports_volume": true,
+                "type": "Computer",
+                "volume_percent": 100
+            },
+            {
+                "id": null,
+                "is_active": false,
+                "is_private_session": false,
+                "is_restricted": true,
+                "name": "Car",
+                "supports_volume": false,
+                "type": "Automobile",
+                "volume_percent": null
+            }
+        ]
+    }"#;
+
+    fn devices(local_name: Option<&str>) -> Vec<Device> {
+        let payload: DevicePayload = serde_json::from_str(DEVICES).unwrap();
+        Device::remote(&payload.devices, local_name, None)
+    }
+
+    #[test]
+    fn parses_devices() {
+        let devices = devices(None);
+        let names: Vec<_> = devices.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(names, ["Living Room", "Phone", "ncspot", "Car"]);
+
+        let speaker = &devices[0];
+        assert_eq!(speaker.device_type, "Speaker");
+        assert_eq!(speaker.volume_percent, Some(40));
+        assert!(!speaker.is_active && !speaker.is_local);
+
+        let phone = &devices[1];
+        assert!(phone.is_active);
+        assert_eq!(phone.volume_percent, None);
+        assert_eq!(phone.device_type, "Smartphone");
+    }
+
+    #[test]
+    fn hides_the_running_instance_in_receiver_mode() {
+        let names: Vec<_> = devices(Some("ncspot"))
+            .into_iter()
+            .map(|device| device.name)
+            .collect();
+        assert_eq!(names, ["Living Room", "Phone", "Car"]);
+    }
+
+    #[test]
+    fn selects_how_playback_is_transferred() {
+        let devices = devices(None);
+        assert_eq!(
+            devices[0].transfer(),
+            Transfer::ToRemote("5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e")
+        );
+        assert_eq!(devices[3].transfer(), Transfer::Unavailable);
+
+        let local = Device {
+            is_local: true,
+            ..devices[2].clone()
+        };
+        assert_eq!(local.transfer(), Transfer::ToLocal);
+    }
+}
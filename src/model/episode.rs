@@ -1,11 +1,15 @@
+use crate::command::SortKey;
 use crate::library::Library;
 use crate::model::playable::Playable;
+use crate::model::track::play_count;
 use crate::queue::Queue;
-use crate::traits::{ListItem, ViewExt};
+use crate::traits::{ListItem, Sortable, ViewExt};
 use crate::utils::ms_to_hms;
 use chrono::{DateTime, Utc};
 use rspotify::model::Id;
 use rspotify::model::show::{FullEpisode, SimplifiedEpisode};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
@@ -17,6 +21,9 @@ pub struct Episode {
     pub name: String,
     pub description: String,
     pub release_date: String,
+    /// The name of the show the episode belongs to, if known.
+    #[serde(default)]
+    pub show: Option<String>,
     pub cover_url: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
     pub list_index: usize,
@@ -37,6 +44,7 @@ impl From<&SimplifiedEpisode> for Episode {
             name: episode.name.clone(),
             description: episode.description.clone(),
             release_date: episode.release_date.clone(),
+            show: None,
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
             list_index: 0,
@@ -53,6 +61,7 @@ impl From<&FullEpisode> for Episode {
             name: episode.name.clone(),
             description: episode.description.clone(),
             release_date: episode.release_date.clone(),
+            show: Some(episode.show.name.clone()),
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
             list_index: 0,
@@ -66,6 +75,21 @@ impl fmt::Display for Episode {
     }
 }
 
+impl Sortable for Episode {
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering {
+        match *key {
+            SortKey::Title => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            SortKey::Duration => self.duration.cmp(&other.duration),
+            SortKey::Added => self.added_at.cmp(&other.added_at),
+            SortKey::Released => self.release_date.cmp(&other.release_date),
+            SortKey::PlayCount => {
+                play_count(&self.uri, play_counts).cmp(&play_count(&other.uri, play_counts))
+            }
+            _ => Ordering::Equal,
+        }
+    }
+}
+
 impl ListItem for Episode {
     fn is_playing(&self, queue: &Queue) -> bool {
         let current = queue.get_current();
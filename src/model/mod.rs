@@ -1,6 +1,7 @@
 pub mod album;
 pub mod artist;
 pub mod category;
+pub mod device;
 pub mod episode;
 pub mod playable;
 pub mod playlist;
@@ -1,15 +1,22 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use rspotify::model::PlayableItem;
 
+use crate::command::SortKey;
+use crate::config::{Column, DEFAULT_USE_NERDFONT};
+use crate::filter::{Field, Value};
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::episode::Episode;
 use crate::model::track::Track;
 use crate::queue::Queue;
-use crate::traits::{ListItem, ViewExt};
+use crate::template;
+use crate::traits::{ListItem, Sortable, ViewExt};
 use crate::utils::ms_to_hms;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
+use std::fmt::Write;
 use std::sync::Arc;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -21,63 +28,105 @@ pub enum Playable {
 
 impl Playable {
     pub fn format(playable: &Self, formatting: &str, library: &Library) -> String {
-        formatting
-            .replace(
-                "%artists",
-                if let Some(artists) = playable.artists() {
+        template::render(formatting, &|name, argument| {
+            playable.placeholder(name, argument, library)
+        })
+    }
+
+    /// The value of the track format placeholder `name`, or [None] if there is no such
+    /// placeholder. `argument` is the optional bracketed argument of the placeholder, like the
+    /// date format in `%added_at[%Y-%m-%d]`.
+    pub fn placeholder(
+        &self,
+        name: &str,
+        argument: Option<&str>,
+        library: &Library,
+    ) -> Option<String> {
+        let value = match (name, self) {
+            ("artists", _) => self
+                .artists()
+                .map(|artists| {
                     artists
                         .iter()
-                        .map(|artist| artist.clone().name)
+                        .map(|artist| artist.name.clone())
                         .collect::<Vec<String>>()
                         .join(", ")
+                })
+                .unwrap_or_default(),
+            ("artist", _) => self
+                .artists()
+                .and_then(|artists| artists.first().map(|artist| artist.name.clone()))
+                .unwrap_or_default(),
+            ("title", Self::Track(track)) => track.title.clone(),
+            ("title", Self::Episode(episode)) => episode.name.clone(),
+            ("album", Self::Track(track)) => track.album.clone().unwrap_or_default(),
+            ("album_artist", Self::Track(track)) => track.album_artists.join(", "),
+            ("track_number", Self::Track(track)) => track.track_number.to_string(),
+            ("disc", Self::Track(track)) => track.disc_number.to_string(),
+            ("year", Self::Track(track)) => track
+                .release_date
+                .as_deref()
+                .and_then(|date| date.split('-').next())
+                .unwrap_or_default()
+                .to_string(),
+            ("year", Self::Episode(episode)) => episode
+                .release_date
+                .split('-')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            ("popularity", Self::Track(track)) => track
+                .popularity
+                .map(|popularity| popularity.to_string())
+                .unwrap_or_default(),
+            ("explicit", Self::Track(track)) => {
+                if track.explicit {
+                    "[E]".to_string()
                 } else {
                     String::new()
                 }
-                .as_str(),
-            )
-            .replace(
-                "%artist",
-                if let Some(artists) = playable.artists() {
-                    artists
-                        .first()
-                        .map_or(String::from(""), |artist| artist.name.clone())
-                } else {
-                    String::new()
-                }
-                .as_str(),
-            )
-            .replace(
-                "%title",
-                match playable.clone() {
-                    Self::Episode(episode) => episode.name,
-                    Self::Track(track) => track.title,
-                }
-                .as_str(),
-            )
-            .replace(
-                "%album",
-                match playable.clone() {
-                    Self::Track(track) => track.album.unwrap_or_default(),
-                    _ => String::new(),
-                }
-                .as_str(),
+            }
+            ("show", Self::Episode(episode)) => episode.show.clone().unwrap_or_default(),
+            (
+                "album" | "album_artist" | "track_number" | "disc" | "popularity" | "explicit",
+                Self::Episode(_),
             )
-            .replace(
-                "%saved",
-                if library.is_saved_track(&match playable.clone() {
-                    Self::Episode(episode) => Self::Episode(episode),
-                    Self::Track(track) => Self::Track(track),
-                }) {
-                    if library.cfg.values().use_nerdfont.unwrap_or_default() {
-                        "\u{f012c}"
+            | ("show", Self::Track(_)) => String::new(),
+            ("added_at", _) => self
+                .added_at()
+                .map(|added_at| {
+                    let mut formatted = String::new();
+                    let local = added_at.with_timezone(&Local);
+                    // Invalid format strings make formatting fail instead of panicking here.
+                    write!(
+                        formatted,
+                        "{}",
+                        local.format(argument.unwrap_or("%Y-%m-%d"))
+                    )
+                    .map(|_| formatted)
+                    .unwrap_or_default()
+                })
+                .unwrap_or_default(),
+            ("saved", _) => {
+                if library.is_saved_track(self) {
+                    if library
+                        .cfg
+                        .values()
+                        .use_nerdfont
+                        .unwrap_or(DEFAULT_USE_NERDFONT)
+                    {
+                        "\u{f012c}".to_string()
                     } else {
-                        "✓"
+                        "✓".to_string()
                     }
                 } else {
-                    ""
-                },
-            )
-            .replace("%duration", playable.duration_str().as_str())
+                    String::new()
+                }
+            }
+            ("duration", _) => self.duration_str(),
+            _ => return None,
+        };
+        Some(value)
     }
 
     pub fn id(&self) -> Option<String> {
@@ -122,6 +171,13 @@ impl Playable {
         }
     }
 
+    pub fn added_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Track(track) => track.added_at,
+            Self::Episode(episode) => episode.added_at,
+        }
+    }
+
     pub fn set_added_at(&mut self, added_at: Option<DateTime<Utc>>) {
         match self {
             Self::Track(track) => track.added_at = added_at,
@@ -196,6 +252,30 @@ impl ListItem for Playable {
         self.as_listitem().display_right(library)
     }
 
+    fn column(&self, column: Column, library: &Library) -> Option<String> {
+        self.placeholder(column.placeholder(), None, library)
+    }
+
+    fn filter_value(&self, field: Field) -> Option<Value> {
+        match self {
+            Self::Track(track) => track.filter_value(field),
+            Self::Episode(episode) => match field {
+                Field::Title => Some(Value::Text(episode.name.clone())),
+                Field::Artist | Field::Album => episode.show.clone().map(Value::Text),
+                Field::Year => episode
+                    .release_date
+                    .get(..4)
+                    .and_then(|year| year.parse().ok())
+                    .map(Value::Number),
+                Field::Duration => Some(Value::Number(u64::from(episode.duration / 1000))),
+                Field::Added => episode
+                    .added_at
+                    .map(|added_at| Value::Text(added_at.format("%Y-%m-%d").to_string())),
+                Field::Popularity | Field::Saved | Field::Explicit | Field::Followed => None,
+            },
+        }
+    }
+
     fn play(&mut self, queue: &Queue) {
         self.as_listitem().play(queue)
     }
@@ -244,3 +324,15 @@ impl ListItem for Playable {
         self.as_listitem()
     }
 }
+
+impl Sortable for Playable {
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering {
+        match (self, other) {
+            (Self::Track(a), Self::Track(b)) => a.compare(b, key, play_counts),
+            (Self::Episode(a), Self::Episode(b)) => a.compare(b, key, play_counts),
+            // Keep tracks and episodes apart so the ordering stays consistent.
+            (Self::Track(_), Self::Episode(_)) => Ordering::Less,
+            (Self::Episode(_), Self::Track(_)) => Ordering::Greater,
+        }
+    }
+}
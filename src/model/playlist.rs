@@ -1,6 +1,7 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::iter::Iterator;
 use std::sync::{Arc, RwLock};
-use std::{cmp::Ordering, iter::Iterator};
 
 use rand::{rng, seq::IteratorRandom};
 
@@ -8,13 +9,15 @@ use log::{debug, warn};
 use rspotify::model::Id;
 use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
 
+use crate::command::SortKey;
+use crate::config::{DEFAULT_HIDE_DISPLAY_NAMES, DEFAULT_USE_NERDFONT};
 use crate::model::playable::Playable;
 use crate::model::track::Track;
 use crate::queue::Queue;
 use crate::spotify::Spotify;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::{listview::ListView, playlist::PlaylistView};
-use crate::{command::SortDirection, command::SortKey, library::Library};
+use crate::{config::SortingOrder, library::Library};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Playlist {
@@ -26,9 +29,27 @@ pub struct Playlist {
     pub num_tracks: usize,
     pub tracks: Option<Vec<Playable>>,
     pub collaborative: bool,
+    /// Whether this is a smart playlist that is put together locally from the library.
+    #[serde(default)]
+    pub smart: bool,
 }
 
 impl Playlist {
+    /// Create the smart playlist called `name` that consists of `tracks`.
+    pub fn smart(name: &str, owner_id: &str, tracks: Vec<Playable>) -> Self {
+        Self {
+            id: format!("smart:{name}"),
+            name: name.to_string(),
+            owner_id: owner_id.to_string(),
+            owner_name: None,
+            snapshot_id: String::new(),
+            num_tracks: tracks.len(),
+            tracks: Some(tracks),
+            collaborative: false,
+            smart: true,
+        }
+    }
+
     pub fn load_tracks(&mut self, spotify: &Spotify) {
         if self.tracks.is_some() {
             return;
@@ -56,6 +77,11 @@ impl Playlist {
     }
 
     pub fn delete_track(&mut self, index: usize, spotify: Spotify, library: &Library) -> bool {
+        if self.smart {
+            warn!("tracks can't be deleted from smart playlists");
+            return false;
+        }
+
         let playable = self.tracks.as_ref().unwrap()[index].clone();
         debug!("deleting track: {index} {playable:?}");
 
@@ -81,13 +107,10 @@ impl Playlist {
         }
     }
 
-    pub fn append_tracks(&mut self, new_tracks: &[Playable], spotify: &Spotify, library: &Library) {
+    pub fn append_tracks(&mut self, new_tracks: &[Playable], library: &Library) {
         let mut has_modified = false;
 
-        if spotify
-            .api
-            .append_tracks(&self.id, new_tracks, None)
-            .is_ok()
+        if library.add_to_playlist(&self.id, new_tracks)
             && let Some(tracks) = &mut self.tracks
         {
             tracks.append(&mut new_tracks.to_vec());
@@ -99,52 +122,9 @@ impl Playlist {
         }
     }
 
-    pub fn sort(&mut self, key: &SortKey, direction: &SortDirection) {
-        fn compare_artists(a: &[String], b: &[String]) -> Ordering {
-            let sanitize_artists_name = |x: &[String]| -> Vec<String> {
-                x.iter()
-                    .map(|x| {
-                        x.to_lowercase()
-                            .split(' ')
-                            .skip_while(|x| x == &"the")
-                            .collect()
-                    })
-                    .collect()
-            };
-
-            let a = sanitize_artists_name(a);
-            let b = sanitize_artists_name(b);
-
-            a.cmp(&b)
-        }
-
-        fn compare_album(a: &Track, b: &Track) -> Ordering {
-            a.album
-                .as_ref()
-                .map(|x| x.to_lowercase())
-                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
-                .then_with(|| a.disc_number.cmp(&b.disc_number))
-                .then_with(|| a.track_number.cmp(&b.track_number))
-        }
-
+    pub fn sort(&mut self, order: &SortingOrder, library: &Library) {
         if let Some(c) = self.tracks.as_mut() {
-            c.sort_by(|a, b| match (a.track(), b.track()) {
-                (Some(a), Some(b)) => {
-                    let (a, b) = match *direction {
-                        SortDirection::Ascending => (a, b),
-                        SortDirection::Descending => (b, a),
-                    };
-                    match *key {
-                        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
-                        SortKey::Duration => a.duration.cmp(&b.duration),
-                        SortKey::Album => compare_album(&a, &b),
-                        SortKey::Added => a.added_at.cmp(&b.added_at),
-                        SortKey::Artist => compare_artists(&a.artists, &b.artists)
-                            .then_with(|| compare_album(&a, &b)),
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
-            })
+            Playable::sort(c, order, &library.cfg.state().play_counts);
         }
     }
 }
@@ -160,6 +140,7 @@ impl From<&SimplifiedPlaylist> for Playlist {
             num_tracks: list.tracks.total as usize,
             tracks: None,
             collaborative: list.collaborative,
+            smart: false,
         }
     }
 }
@@ -175,6 +156,26 @@ impl From<&FullPlaylist> for Playlist {
             num_tracks: list.tracks.total as usize,
             tracks: None,
             collaborative: list.collaborative,
+            smart: false,
+        }
+    }
+}
+
+impl Sortable for Playlist {
+    fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        _play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        match *key {
+            SortKey::Title => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            SortKey::Artist => self
+                .owner_name
+                .as_ref()
+                .map(|name| name.to_lowercase())
+                .cmp(&other.owner_name.as_ref().map(|name| name.to_lowercase())),
+            _ => Ordering::Equal,
         }
     }
 }
@@ -197,7 +198,11 @@ impl ListItem for Playlist {
     }
 
     fn display_left(&self, library: &Library) -> String {
-        let hide_owners = library.cfg.values().hide_display_names.unwrap_or(false);
+        let hide_owners = library
+            .cfg
+            .values()
+            .hide_display_names
+            .unwrap_or(DEFAULT_HIDE_DISPLAY_NAMES);
         match (self.owner_name.as_ref(), hide_owners) {
             (Some(owner), false) => format!("{} • {}", self.name, owner),
             _ => self.name.clone(),
@@ -205,8 +210,15 @@ impl ListItem for Playlist {
     }
 
     fn display_right(&self, library: &Library) -> String {
-        let saved = if library.is_saved_playlist(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
+        let saved = if self.smart {
+            "✦ "
+        } else if library.is_saved_playlist(self) {
+            if library
+                .cfg
+                .values()
+                .use_nerdfont
+                .unwrap_or(DEFAULT_USE_NERDFONT)
+            {
                 "\u{f012c} "
             } else {
                 "✓ "
@@ -267,11 +279,15 @@ impl ListItem for Playlist {
     }
 
     fn save(&mut self, library: &Library) {
-        library.follow_playlist(self.clone());
+        if !self.smart {
+            library.follow_playlist(self.clone());
+        }
     }
 
     fn unsave(&mut self, library: &Library) {
-        library.delete_playlist(&self.id);
+        if !self.smart {
+            library.delete_playlist(&self.id);
+        }
     }
 
     fn open(&self, queue: Arc<Queue>, library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
@@ -324,6 +340,11 @@ impl ListItem for Playlist {
     }
 
     fn share_url(&self) -> Option<String> {
+        // Smart playlists only exist locally.
+        if self.smart {
+            return None;
+        }
+
         Some(format!(
             "https://open.spotify.com/user/{}/playlist/{}",
             self.owner_id, self.id
@@ -1,12 +1,16 @@
+use crate::command::SortKey;
+use crate::config::DEFAULT_USE_NERDFONT;
 use crate::library::Library;
 use crate::model::episode::Episode;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
 use crate::spotify::Spotify;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::show::ShowView;
 use rspotify::model::Id;
 use rspotify::model::show::{FullShow, SimplifiedShow};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
@@ -32,7 +36,10 @@ impl Show {
             episodes_result.next();
         }
 
-        let episodes = episodes_result.items.read().unwrap().clone();
+        let mut episodes = episodes_result.items.read().unwrap().clone();
+        for episode in &mut episodes {
+            episode.show = Some(self.name.clone());
+        }
         self.episodes = Some(episodes);
     }
 }
@@ -71,6 +78,24 @@ impl fmt::Display for Show {
     }
 }
 
+impl Sortable for Show {
+    fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        _play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        match *key {
+            SortKey::Title => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            SortKey::Artist => self
+                .publisher
+                .to_lowercase()
+                .cmp(&other.publisher.to_lowercase()),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
 impl ListItem for Show {
     fn is_playing(&self, _queue: &Queue) -> bool {
         false
@@ -82,7 +107,12 @@ impl ListItem for Show {
 
     fn display_right(&self, library: &Library) -> String {
         let saved = if library.is_saved_show(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
+            if library
+                .cfg
+                .values()
+                .use_nerdfont
+                .unwrap_or(DEFAULT_USE_NERDFONT)
+            {
                 "\u{f012c} "
             } else {
                 "✓ "
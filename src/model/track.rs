@@ -1,7 +1,11 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
+use crate::command::SortKey;
 use crate::config;
+use crate::filter::{Field, Value};
 use crate::utils::ms_to_hms;
 use chrono::{DateTime, Utc};
 use rspotify::model::Id;
@@ -13,7 +17,7 @@ use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::listview::ListView;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -35,6 +39,16 @@ pub struct Track {
     pub list_index: usize,
     pub is_local: bool,
     pub is_playable: Option<bool>,
+    /// The release date of the album, which may only consist of the year.
+    #[serde(default)]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub popularity: Option<u32>,
+    #[serde(default)]
+    pub explicit: bool,
+    /// Whether the track was added to the queue by autoplay.
+    #[serde(default)]
+    pub autoplay: bool,
 }
 
 impl Track {
@@ -73,6 +87,10 @@ impl Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: Some(album.release_date.clone()),
+            popularity: None,
+            explicit: track.explicit,
+            autoplay: false,
         }
     }
 
@@ -81,6 +99,61 @@ impl Track {
     }
 }
 
+/// Compare lists of artist names case-insensitively, ignoring a leading "the".
+pub fn compare_artists(a: &[String], b: &[String]) -> Ordering {
+    let sanitize_artists_name = |x: &[String]| -> Vec<String> {
+        x.iter()
+            .map(|x| {
+                x.to_lowercase()
+                    .split(' ')
+                    .skip_while(|x| x == &"the")
+                    .collect()
+            })
+            .collect()
+    };
+
+    let a = sanitize_artists_name(a);
+    let b = sanitize_artists_name(b);
+
+    a.cmp(&b)
+}
+
+/// The number of times the item with `uri` was played.
+pub fn play_count(uri: &str, play_counts: &HashMap<String, u32>) -> u32 {
+    play_counts.get(uri).copied().unwrap_or(0)
+}
+
+impl Sortable for Track {
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering {
+        fn compare_album(a: &Track, b: &Track) -> Ordering {
+            a.album
+                .as_ref()
+                .map(|x| x.to_lowercase())
+                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
+                .then_with(|| a.disc_number.cmp(&b.disc_number))
+                .then_with(|| a.track_number.cmp(&b.track_number))
+        }
+
+        match *key {
+            SortKey::Title => self.title.to_lowercase().cmp(&other.title.to_lowercase()),
+            SortKey::Duration => self.duration.cmp(&other.duration),
+            SortKey::Album => compare_album(self, other),
+            SortKey::Added => self.added_at.cmp(&other.added_at),
+            SortKey::Artist => compare_artists(&self.artists, &other.artists)
+                .then_with(|| compare_album(self, other)),
+            SortKey::TrackNumber => self
+                .disc_number
+                .cmp(&other.disc_number)
+                .then_with(|| self.track_number.cmp(&other.track_number)),
+            SortKey::Popularity => self.popularity.cmp(&other.popularity),
+            SortKey::Released => self.release_date.cmp(&other.release_date),
+            SortKey::PlayCount => {
+                play_count(&self.uri, play_counts).cmp(&play_count(&other.uri, play_counts))
+            }
+        }
+    }
+}
+
 impl From<&SimplifiedTrack> for Track {
     fn from(track: &SimplifiedTrack) -> Self {
         let artists = track
@@ -112,6 +185,10 @@ impl From<&SimplifiedTrack> for Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: None,
+            popularity: None,
+            explicit: track.explicit,
+            autoplay: false,
         }
     }
 }
@@ -153,6 +230,10 @@ impl From<&FullTrack> for Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: track.album.release_date.clone(),
+            popularity: Some(track.popularity),
+            explicit: track.explicit,
+            autoplay: false,
         }
     }
 }
@@ -222,7 +303,12 @@ impl ListItem for Track {
             Playable::format(&Playable::Track(self.clone()), &right, library)
         } else {
             let saved = if library.is_saved_track(&Playable::Track(self.clone())) {
-                if library.cfg.values().use_nerdfont.unwrap_or(false) {
+                if library
+                    .cfg
+                    .values()
+                    .use_nerdfont
+                    .unwrap_or(config::DEFAULT_USE_NERDFONT)
+                {
                     "\u{f012c}"
                 } else {
                     "✓"
@@ -230,7 +316,32 @@ impl ListItem for Track {
             } else {
                 ""
             };
-            format!("{} {}", saved, self.duration_str())
+            let autoplay = if self.autoplay { "∞ " } else { "" };
+            format!("{}{} {}", autoplay, saved, self.duration_str())
+        }
+    }
+
+    fn column(&self, column: config::Column, library: &Library) -> Option<String> {
+        Playable::Track(self.clone()).column(column, library)
+    }
+
+    fn filter_value(&self, field: Field) -> Option<Value> {
+        match field {
+            Field::Title => Some(Value::Text(self.title.clone())),
+            Field::Artist => Some(Value::Text(self.artists.join(", "))),
+            Field::Album => self.album.clone().map(Value::Text),
+            Field::Year => self
+                .release_date
+                .as_ref()
+                .and_then(|date| date.get(..4)?.parse().ok())
+                .map(Value::Number),
+            Field::Duration => Some(Value::Number(u64::from(self.duration / 1000))),
+            Field::Added => self
+                .added_at
+                .map(|added_at| Value::Text(added_at.format("%Y-%m-%d").to_string())),
+            Field::Popularity => self.popularity.map(|p| Value::Number(u64::from(p))),
+            Field::Explicit => Some(Value::Bool(self.explicit)),
+            Field::Saved | Field::Followed => None,
         }
     }
 
@@ -3,13 +3,13 @@
 use log::info;
 use std::collections::HashMap;
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use zbus::object_server::SignalEmitter;
-use zbus::zvariant::{ObjectPath, Value};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
 use zbus::{connection, interface};
 
 use crate::application::ASYNC_RUNTIME;
@@ -44,7 +44,7 @@ impl MprisRoot {
         false
     }
 
-    #[zbus(property)]
+    #[zbus(property, name = "HasTrackList")]
     fn has_tracklist(&self) -> bool {
         true
     }
@@ -69,11 +69,109 @@ impl MprisRoot {
     fn quit(&self) {}
 }
 
+/// The track ID of the absence of a track, see
+/// <https://specifications.freedesktop.org/mpris-spec/2.2/Track_List_Interface.html#Mapping:Track_Id>.
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// The prefix of the track IDs of the items in the queue, which are followed by their identifier
+/// in the queue, see [Queue::ids].
+const QUEUE_TRACK_PREFIX: &str = "/org/ncspot/queue/";
+
+/// The prefix of the IDs of the playlists in the library, which are followed by their encoded ID.
+const PLAYLIST_PREFIX: &str = "/org/ncspot/playlist/";
+
+/// The ID of the virtual playlist with the saved tracks.
+const SAVED_TRACKS_PLAYLIST: &str = "/org/ncspot/library/tracks";
+
+/// A playlist as MPRIS describes it: its ID, name and icon.
+type MprisPlaylist = (OwnedObjectPath, String, String);
+
+/// Metadata of the current and the next item in the queue that is fetched ahead of time, so that
+/// D-Bus calls never wait for the network.
+#[derive(Clone)]
+struct MetadataCache {
+    queue: Arc<Queue>,
+    spotify: Spotify,
+    /// The full versions of tracks that were queued without a cover, by URI.
+    tracks: Arc<RwLock<HashMap<String, Track>>>,
+}
+
+impl MetadataCache {
+    fn new(queue: Arc<Queue>, spotify: Spotify) -> Self {
+        Self {
+            queue,
+            spotify,
+            tracks: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// The full version of `playable` if it was fetched, or else `playable` itself.
+    fn full(&self, playable: Playable) -> Playable {
+        match playable {
+            Playable::Track(track) => Playable::Track(
+                self.tracks
+                    .read()
+                    .unwrap()
+                    .get(&track.uri)
+                    .cloned()
+                    .unwrap_or(track),
+            ),
+            episode => episode,
+        }
+    }
+
+    /// Fetch the metadata that is missing for the current and the next item in the queue and
+    /// download their covers. Returns whether anything new was fetched.
+    fn prefetch(&self) -> bool {
+        let indexes = [self.queue.get_current_index(), self.queue.next_index()];
+        let items: Vec<Playable> = {
+            let queue = self.queue.queue.read().unwrap();
+            indexes
+                .into_iter()
+                .flatten()
+                .filter_map(|index| queue.get(index).cloned())
+                .collect()
+        };
+
+        let mut fetched = false;
+        for playable in items {
+            let playable = match self.full(playable) {
+                // Tracks that are based on a SimplifiedTrack don't contain a cover.
+                Playable::Track(track) if track.cover_url.is_none() => {
+                    let Some(Ok(full)) = track.id.as_deref().map(|id| self.spotify.api.track(id))
+                    else {
+                        continue;
+                    };
+                    let full = Track::from(&full);
+                    self.tracks
+                        .write()
+                        .unwrap()
+                        .insert(track.uri.clone(), full.clone());
+                    fetched = true;
+                    Playable::Track(full)
+                }
+                playable => playable,
+            };
+
+            if let Some(url) = playable.cover_url()
+                && crate::cache::cached_cover(&url).is_none()
+            {
+                match crate::cache::cover(&url) {
+                    Ok(_) => fetched = true,
+                    Err(e) => log::error!("Failed to download cover: {e}"),
+                }
+            }
+        }
+        fetched
+    }
+}
+
 struct MprisPlayer {
     event: EventManager,
     queue: Arc<Queue>,
     library: Arc<Library>,
     spotify: Spotify,
+    metadata: MetadataCache,
 }
 
 #[interface(name = "org.mpris.MediaPlayer2.Player")]
@@ -123,138 +221,10 @@ impl MprisPlayer {
     }
 
     #[zbus(property)]
-    fn metadata(&self) -> HashMap<String, Value<'_>> {
-        let mut hm = HashMap::new();
-
-        let playable = self.queue.get_current();
-
-        // Fetch full track details in case this playable is based on a SimplifiedTrack
-        // This is necessary because SimplifiedTrack objects don't contain a cover_url
-        let playable_full = playable.and_then(|p| match p {
-            Playable::Track(track) => {
-                if track.cover_url.is_some() {
-                    // We already have `cover_url`, no need to fetch the full track
-                    Some(Playable::Track(track))
-                } else {
-                    self.spotify
-                        .api
-                        .track(&track.id.unwrap_or_default())
-                        .as_ref()
-                        .map(|t| Playable::Track(t.into()))
-                        .ok()
-                }
-            }
-            Playable::Episode(episode) => Some(Playable::Episode(episode)),
-        });
-        let playable = playable_full.as_ref();
-
-        hm.insert(
-            "mpris:trackid".to_string(),
-            Value::ObjectPath(ObjectPath::from_string_unchecked(format!(
-                "/org/ncspot/{}",
-                playable
-                    .filter(|t| t.id().is_some())
-                    .map(|t| t.uri().replace(':', "/"))
-                    .unwrap_or_else(|| String::from("0"))
-            ))),
-        );
-        hm.insert(
-            "mpris:length".to_string(),
-            Value::I64(playable.map(|t| t.duration() as i64 * 1_000).unwrap_or(0)),
-        );
-        hm.insert(
-            "mpris:artUrl".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| t.cover_url().unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-
-        hm.insert(
-            "xesam:album".to_string(),
-            Value::Str(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.album.unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:albumArtist".to_string(),
-            Value::Array(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.album_artists)
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:artist".to_string(),
-            Value::Array(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.artists)
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:discNumber".to_string(),
-            Value::I32(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.disc_number)
-                    .unwrap_or(0),
-            ),
-        );
-        hm.insert(
-            "xesam:title".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| match t {
-                        Playable::Track(t) => t.title.clone(),
-                        Playable::Episode(ep) => ep.name.clone(),
-                    })
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:trackNumber".to_string(),
-            Value::I32(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.track_number)
-                    .unwrap_or(0) as i32,
-            ),
-        );
-        hm.insert(
-            "xesam:url".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| t.share_url().unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:userRating".to_string(),
-            Value::F64(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| match self.library.is_saved_track(&Playable::Track(t)) {
-                        true => 1.0,
-                        false => 0.0,
-                    })
-                    .unwrap_or(0.0),
-            ),
-        );
-
-        hm
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        let id = self.queue.get_current_id();
+        let playable = self.queue.get_current().map(|p| self.metadata.full(p));
+        metadata(id, playable.as_ref(), &self.library)
     }
 
     #[zbus(property)]
@@ -377,14 +347,7 @@ impl MprisPlayer {
     }
 
     fn open_uri(&self, uri: &str) {
-        let spotify_url = if uri.contains("open.spotify.com") {
-            SpotifyUrl::from_url(uri)
-        } else if let Ok(uri_type) = uri.parse() {
-            let id = &uri[uri.rfind(':').unwrap_or(0) + 1..uri.len()];
-            Some(SpotifyUrl::new(id, uri_type))
-        } else {
-            None
-        };
+        let spotify_url = parse_uri(uri);
 
         let id = spotify_url
             .as_ref()
@@ -468,6 +431,438 @@ impl MprisPlayer {
     }
 }
 
+struct MprisTrackList {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    spotify: Spotify,
+    metadata: MetadataCache,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.TrackList")]
+impl MprisTrackList {
+    #[zbus(property(emits_changed_signal = "invalidates"))]
+    fn tracks(&self) -> Vec<OwnedObjectPath> {
+        queue_tracks(&self.queue).0
+    }
+
+    #[zbus(property)]
+    fn can_edit_tracks(&self) -> bool {
+        true
+    }
+
+    #[zbus(signal)]
+    async fn track_list_replaced(
+        context: &SignalEmitter<'_>,
+        tracks: &[OwnedObjectPath],
+        current_track: &OwnedObjectPath,
+    ) -> zbus::Result<()>;
+
+    fn get_tracks_metadata(
+        &self,
+        track_ids: Vec<ObjectPath<'_>>,
+    ) -> Vec<HashMap<String, Value<'static>>> {
+        track_ids
+            .iter()
+            .filter_map(queue_entry)
+            .filter_map(|id| {
+                let index = self.queue.index_of_id(id)?;
+                let playable = self.queue.queue.read().unwrap().get(index)?.clone();
+                let playable = self.metadata.full(playable);
+                Some(metadata(Some(id), Some(&playable), &self.library))
+            })
+            .collect()
+    }
+
+    fn add_track(&self, uri: &str, after_track: ObjectPath<'_>, set_as_current: bool) {
+        let Some(spotify_url) = parse_uri(uri) else {
+            log::warn!("MPRIS: can't add {uri} to the queue");
+            return;
+        };
+        let after = queue_entry(&after_track);
+        let queue = self.queue.clone();
+        let api = self.spotify.api.clone();
+        let uri = uri.to_string();
+
+        // Fetching the item can take a while, so it is added once it arrives instead of blocking
+        // the D-Bus connection.
+        tokio::task::spawn_blocking(move || {
+            let id = &spotify_url.id;
+            let playable = match spotify_url.uri_type {
+                UriType::Track => api.track(id).ok().map(|t| Playable::Track(Track::from(&t))),
+                UriType::Episode => api
+                    .episode(id)
+                    .ok()
+                    .map(|e| Playable::Episode(Episode::from(&e))),
+                _ => None,
+            };
+
+            let Some(playable) = playable else {
+                log::warn!("MPRIS: can't add {uri} to the queue");
+                return;
+            };
+
+            // The track is inserted at the start if `after_track` is NoTrack or isn't queued
+            // anymore.
+            let position = after
+                .and_then(|id| queue.index_of_id(id))
+                .map_or(0, |index| index + 1);
+            queue.append(playable);
+            let last = queue.len() - 1;
+            if position < last {
+                queue.shift(last, position);
+            }
+
+            if set_as_current {
+                queue.play(position.min(last), false, false);
+            }
+        });
+    }
+
+    fn remove_track(&self, track_id: ObjectPath<'_>) {
+        if let Some(index) = queue_entry(&track_id).and_then(|id| self.queue.index_of_id(id)) {
+            self.queue.remove(index);
+        }
+    }
+
+    fn go_to(&self, track_id: ObjectPath<'_>) {
+        if let Some(index) = queue_entry(&track_id).and_then(|id| self.queue.index_of_id(id)) {
+            self.queue.play(index, false, false);
+        }
+    }
+}
+
+struct MprisPlaylists {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    spotify: Spotify,
+    /// The playlist that was activated last.
+    active: RwLock<Option<MprisPlaylist>>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Playlists")]
+impl MprisPlaylists {
+    #[zbus(property)]
+    fn playlist_count(&self) -> u32 {
+        library_playlists(&self.library).len() as u32
+    }
+
+    #[zbus(property)]
+    fn orderings(&self) -> Vec<String> {
+        vec!["UserDefined".to_string(), "Alphabetical".to_string()]
+    }
+
+    #[zbus(property)]
+    fn active_playlist(&self) -> (bool, MprisPlaylist) {
+        match self.active.read().unwrap().clone() {
+            Some(playlist) => (true, playlist),
+            None => (
+                false,
+                (
+                    ObjectPath::from_static_str_unchecked("/").into(),
+                    String::new(),
+                    String::new(),
+                ),
+            ),
+        }
+    }
+
+    #[zbus(signal)]
+    async fn playlist_changed(
+        context: &SignalEmitter<'_>,
+        playlist: &MprisPlaylist,
+    ) -> zbus::Result<()>;
+
+    fn get_playlists(
+        &self,
+        index: u32,
+        max_count: u32,
+        order: &str,
+        reverse_order: bool,
+    ) -> Vec<MprisPlaylist> {
+        let mut playlists = library_playlists(&self.library);
+        if order == "Alphabetical" {
+            playlists.sort_by_key(|(_, name, _)| name.to_lowercase());
+        }
+        if reverse_order {
+            playlists.reverse();
+        }
+
+        playlists
+            .into_iter()
+            .skip(index as usize)
+            .take(max_count as usize)
+            .collect()
+    }
+
+    async fn activate_playlist(
+        &self,
+        playlist_id: ObjectPath<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) {
+        let tracks: Option<Vec<Playable>> = if playlist_id.as_str() == SAVED_TRACKS_PLAYLIST {
+            Some(
+                self.library
+                    .tracks
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|track| Playable::Track(track.clone()))
+                    .collect(),
+            )
+        } else {
+            let playlist = self
+                .library
+                .playlists
+                .read()
+                .unwrap()
+                .iter()
+                .find(|playlist| playlist_path(&playlist.id).as_str() == playlist_id.as_str())
+                .cloned();
+            match playlist {
+                // Loading the tracks can take a while, so it mustn't block the D-Bus connection.
+                Some(mut playlist) => {
+                    let spotify = self.spotify.clone();
+                    tokio::task::spawn_blocking(move || {
+                        playlist.load_tracks(&spotify);
+                        playlist.tracks
+                    })
+                    .await
+                    .ok()
+                    .flatten()
+                }
+                None => None,
+            }
+        };
+
+        let Some(tracks) = tracks.filter(|tracks| !tracks.is_empty()) else {
+            log::warn!("MPRIS: can't activate playlist {playlist_id}");
+            return;
+        };
+
+        let should_shuffle = self.queue.get_shuffle();
+        self.queue.clear();
+        let index = self.queue.append_next(&tracks);
+        self.queue.play(index, should_shuffle, should_shuffle);
+
+        *self.active.write().unwrap() = library_playlists(&self.library)
+            .into_iter()
+            .find(|(path, _, _)| path.as_str() == playlist_id.as_str());
+        if let Err(e) = self.active_playlist_changed(&emitter).await {
+            log::warn!("MPRIS: can't signal the active playlist: {e}");
+        }
+    }
+}
+
+/// The MPRIS ID of the library playlist with the Spotify `id`. Characters that aren't allowed in
+/// object paths are written as `_` followed by their hexadecimal code.
+fn playlist_path(id: &str) -> OwnedObjectPath {
+    let encoded: String = id
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => char::from(b).to_string(),
+            _ => format!("_{b:02X}"),
+        })
+        .collect();
+    ObjectPath::from_string_unchecked(format!("{PLAYLIST_PREFIX}{encoded}")).into()
+}
+
+/// The playlists of the library as MPRIS describes them, starting with the saved tracks.
+fn library_playlists(library: &Library) -> Vec<MprisPlaylist> {
+    let saved_tracks = (
+        ObjectPath::from_static_str_unchecked(SAVED_TRACKS_PLAYLIST).into(),
+        "Saved tracks".to_string(),
+        String::new(),
+    );
+    std::iter::once(saved_tracks)
+        .chain(library.playlists.read().unwrap().iter().map(|playlist| {
+            (
+                playlist_path(&playlist.id),
+                playlist.name.clone(),
+                String::new(),
+            )
+        }))
+        .collect()
+}
+
+/// The track ID of the queue item with the identifier `id`, or NoTrack.
+fn track_id(id: Option<usize>) -> ObjectPath<'static> {
+    match id {
+        Some(id) => ObjectPath::from_string_unchecked(format!("{QUEUE_TRACK_PREFIX}{id}")),
+        None => ObjectPath::from_static_str_unchecked(NO_TRACK),
+    }
+}
+
+/// The track IDs of the items in `queue` and of its current item.
+fn queue_tracks(queue: &Queue) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+    let tracks = queue
+        .ids()
+        .into_iter()
+        .map(|id| track_id(Some(id)).into())
+        .collect();
+    (tracks, track_id(queue.get_current_id()).into())
+}
+
+/// The identifier of the queue item with `track_id`, see [Queue::ids].
+fn queue_entry(track_id: &ObjectPath<'_>) -> Option<usize> {
+    track_id
+        .as_str()
+        .strip_prefix(QUEUE_TRACK_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Parse a Spotify URI or URL.
+fn parse_uri(uri: &str) -> Option<SpotifyUrl> {
+    if uri.contains("open.spotify.com") {
+        SpotifyUrl::from_url(uri)
+    } else if let Ok(uri_type) = uri.parse() {
+        let id = &uri[uri.rfind(':').unwrap_or(0) + 1..uri.len()];
+        Some(SpotifyUrl::new(id, uri_type))
+    } else {
+        None
+    }
+}
+
+/// The URL of the cover of `playable`, which is the cached file if it was downloaded already.
+fn art_url(playable: &Playable) -> String {
+    playable
+        .cover_url()
+        .map(|url| match crate::cache::cached_cover(&url) {
+            Some(path) => format!("file://{}", path.display()),
+            None => url,
+        })
+        .unwrap_or_default()
+}
+
+/// Convert a release date like `1965`, `1965-08` or `1965-08-06` into the ISO 8601 date and time
+/// that MPRIS expects.
+fn content_created(release_date: &str) -> Option<String> {
+    let mut parts = release_date.split('-');
+    let year = parts
+        .next()
+        .filter(|year| year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()))?;
+    let month = parts.next().unwrap_or("01");
+    let day = parts.next().unwrap_or("01");
+    Some(format!("{year}-{month}-{day}T00:00:00Z"))
+}
+
+/// The MPRIS metadata of `playable`, which is at `index` in the queue.
+fn metadata(
+    id: Option<usize>,
+    playable: Option<&Playable>,
+    library: &Library,
+) -> HashMap<String, Value<'static>> {
+    let mut hm = HashMap::new();
+
+    hm.insert(
+        "mpris:trackid".to_string(),
+        Value::ObjectPath(track_id(id.filter(|_| playable.is_some()))),
+    );
+    hm.insert(
+        "mpris:length".to_string(),
+        Value::I64(playable.map(|t| t.duration() as i64 * 1_000).unwrap_or(0)),
+    );
+    hm.insert(
+        "mpris:artUrl".to_string(),
+        Value::Str(playable.map(art_url).unwrap_or_default().into()),
+    );
+    hm.insert(
+        "xesam:album".to_string(),
+        Value::Str(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.album.unwrap_or_default())
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:albumArtist".to_string(),
+        Value::Array(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.album_artists)
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:artist".to_string(),
+        Value::Array(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.artists)
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:discNumber".to_string(),
+        Value::I32(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.disc_number)
+                .unwrap_or(0),
+        ),
+    );
+    hm.insert(
+        "xesam:title".to_string(),
+        Value::Str(
+            playable
+                .map(|t| match t {
+                    Playable::Track(t) => t.title.clone(),
+                    Playable::Episode(ep) => ep.name.clone(),
+                })
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:trackNumber".to_string(),
+        Value::I32(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.track_number)
+                .unwrap_or(0) as i32,
+        ),
+    );
+    hm.insert(
+        "xesam:url".to_string(),
+        Value::Str(
+            playable
+                .map(|t| t.share_url().unwrap_or_default())
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:userRating".to_string(),
+        Value::F64(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| match library.is_saved_track(&Playable::Track(t)) {
+                    true => 1.0,
+                    false => 0.0,
+                })
+                .unwrap_or(0.0),
+        ),
+    );
+    if let Some(created) = playable
+        .and_then(|p| match p {
+            Playable::Track(t) => t.release_date.clone(),
+            Playable::Episode(ep) => Some(ep.release_date.clone()),
+        })
+        .and_then(|date| content_created(&date))
+    {
+        hm.insert(
+            "xesam:contentCreated".to_string(),
+            Value::Str(created.into()),
+        );
+    }
+
+    hm
+}
+
 /// Commands to control the [MprisManager] worker thread.
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -480,6 +875,12 @@ pub enum MprisCommand {
     EmitMetadataStatus,
     /// Emit seeked position
     EmitSeekedStatus(i64),
+    /// Emit the replaced track list
+    EmitTrackListStatus,
+    /// Emit a changed playlist of the library
+    EmitPlaylistChanged(String),
+    /// Stop the server and release its bus name, then notify the sender
+    Shutdown(oneshot::Sender<()>),
 }
 
 /// An MPRIS server that internally manager a thread which can be sent commands. This is internally
@@ -495,19 +896,71 @@ impl MprisManager {
         queue: Arc<Queue>,
         library: Arc<Library>,
         spotify: Spotify,
+    ) -> Self {
+        Self::start(event, queue, library, spotify, None)
+    }
+
+    /// Stop this server and start one for another queue, library and session, e.g. after switching
+    /// profiles. The new server takes the bus name once this one has released it.
+    pub fn replace(
+        &self,
+        event: EventManager,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        spotify: Spotify,
+    ) -> Self {
+        let (tx, rx) = oneshot::channel();
+        self.send(MprisCommand::Shutdown(tx));
+        Self::start(event, queue, library, spotify, Some(rx))
+    }
+
+    /// Start the server in the background, after `released` is notified if it's given.
+    fn start(
+        event: EventManager,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        spotify: Spotify,
+        released: Option<oneshot::Receiver<()>>,
     ) -> Self {
         let root = MprisRoot {};
+        let metadata = MetadataCache::new(queue.clone(), spotify.clone());
+        let tracklist = MprisTrackList {
+            queue: queue.clone(),
+            library: library.clone(),
+            spotify: spotify.clone(),
+            metadata: metadata.clone(),
+        };
+        let playlists = MprisPlaylists {
+            queue: queue.clone(),
+            library: library.clone(),
+            spotify: spotify.clone(),
+            active: RwLock::new(None),
+        };
         let player = MprisPlayer {
             event,
             queue,
             library,
             spotify,
+            metadata,
         };
 
         let (tx, rx) = mpsc::unbounded_channel::<MprisCommand>();
 
+        let server_tx = tx.clone();
         ASYNC_RUNTIME.get().unwrap().spawn(async {
-            let result = Self::serve(UnboundedReceiverStream::new(rx), root, player).await;
+            // An error means that the previous server is gone already.
+            if let Some(released) = released {
+                let _ = released.await;
+            }
+            let result = Self::serve(
+                UnboundedReceiverStream::new(rx),
+                server_tx,
+                root,
+                player,
+                tracklist,
+                playlists,
+            )
+            .await;
             if let Err(e) = result {
                 log::error!("MPRIS error: {e}");
             }
@@ -518,13 +971,18 @@ impl MprisManager {
 
     async fn serve(
         mut rx: UnboundedReceiverStream<MprisCommand>,
+        tx: mpsc::UnboundedSender<MprisCommand>,
         root: MprisRoot,
         player: MprisPlayer,
+        tracklist: MprisTrackList,
+        playlists: MprisPlaylists,
     ) -> Result<(), Box<dyn Error + Sync + Send>> {
         let conn = connection::Builder::session()?
             .name(instance_bus_name())?
             .serve_at("/org/mpris/MediaPlayer2", root)?
             .serve_at("/org/mpris/MediaPlayer2", player)?
+            .serve_at("/org/mpris/MediaPlayer2", tracklist)?
+            .serve_at("/org/mpris/MediaPlayer2", playlists)?
             .build()
             .await?;
 
@@ -533,6 +991,14 @@ impl MprisManager {
             .interface::<_, MprisPlayer>("/org/mpris/MediaPlayer2")
             .await?;
         let player_iface = player_iface_ref.get().await;
+        let tracklist_iface_ref = object_server
+            .interface::<_, MprisTrackList>("/org/mpris/MediaPlayer2")
+            .await?;
+        let tracklist_iface = tracklist_iface_ref.get().await;
+        let playlists_iface_ref = object_server
+            .interface::<_, MprisPlaylists>("/org/mpris/MediaPlayer2")
+            .await?;
+        let playlists_iface = playlists_iface_ref.get().await;
 
         loop {
             let ctx = player_iface_ref.signal_emitter();
@@ -546,11 +1012,45 @@ impl MprisManager {
                 }
                 Some(MprisCommand::EmitMetadataStatus) => {
                     player_iface.metadata_changed(ctx).await?;
+
+                    // Fetch what is missing in the background and send the metadata again once
+                    // it is there.
+                    let metadata = player_iface.metadata.clone();
+                    let tx = tx.clone();
+                    tokio::task::spawn_blocking(move || {
+                        if metadata.prefetch() {
+                            let _ = tx.send(MprisCommand::EmitMetadataStatus);
+                        }
+                    });
                 }
                 Some(MprisCommand::EmitSeekedStatus(pos)) => {
                     info!("sending MPRIS seeked signal");
                     MprisPlayer::seeked(ctx, &pos).await?;
                 }
+                Some(MprisCommand::EmitTrackListStatus) => {
+                    let tracklist_ctx = tracklist_iface_ref.signal_emitter();
+                    let (tracks, current) = queue_tracks(&tracklist_iface.queue);
+                    MprisTrackList::track_list_replaced(tracklist_ctx, &tracks, &current).await?;
+                    tracklist_iface.tracks_invalidate(tracklist_ctx).await?;
+                }
+                Some(MprisCommand::EmitPlaylistChanged(id)) => {
+                    let playlists_ctx = playlists_iface_ref.signal_emitter();
+                    let path = playlist_path(&id);
+                    let playlist = library_playlists(&playlists_iface.library)
+                        .into_iter()
+                        .find(|(playlist_path, _, _)| *playlist_path == path);
+                    if let Some(playlist) = playlist {
+                        MprisPlaylists::playlist_changed(playlists_ctx, &playlist).await?;
+                    }
+                    playlists_iface
+                        .playlist_count_changed(playlists_ctx)
+                        .await?;
+                }
+                Some(MprisCommand::Shutdown(done)) => {
+                    conn.release_name(instance_bus_name()).await?;
+                    let _ = done.send(());
+                    return Ok(());
+                }
                 None => break,
             }
         }
@@ -573,3 +1073,127 @@ pub fn instance_bus_name() -> String {
         std::process::id()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use zbus::names::InterfaceName;
+
+    use super::*;
+    use crate::private_bus::PrivateBus;
+
+    /// Run `test` with the address of a private session bus. Fails if `dbus-daemon` isn't
+    /// installed, rather than passing without testing anything.
+    fn with_private_bus<F: Future<Output = ()>>(test: impl FnOnce(String) -> F) {
+        let bus = PrivateBus::start().expect("the D-Bus tests need dbus-daemon");
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(test(bus.address.clone()));
+    }
+
+    #[test]
+    fn converts_release_dates() {
+        assert_eq!(
+            content_created("1965-08-06").as_deref(),
+            Some("1965-08-06T00:00:00Z")
+        );
+        assert_eq!(
+            content_created("1965").as_deref(),
+            Some("1965-01-01T00:00:00Z")
+        );
+        assert_eq!(content_created("0000s"), None);
+    }
+
+    #[test]
+    fn encodes_playlist_ids() {
+        assert_eq!(
+            playlist_path("37i9dQZF1DX").as_str(),
+            "/org/ncspot/playlist/37i9dQZF1DX"
+        );
+        assert_eq!(
+            playlist_path("smart:old_hits").as_str(),
+            "/org/ncspot/playlist/smart_3Aold_5Fhits"
+        );
+    }
+
+    #[test]
+    fn maps_track_ids_to_queue_entries() {
+        assert_eq!(queue_entry(&track_id(Some(3))), Some(3));
+        assert_eq!(queue_entry(&track_id(None)), None);
+        let playlist = ObjectPath::from_static_str_unchecked("/org/ncspot/playlist/3");
+        assert_eq!(queue_entry(&playlist), None);
+    }
+
+    #[test]
+    fn exports_spec_property_names() {
+        with_private_bus(|address| async move {
+            let server = connection::Builder::address(address.as_str())
+                .unwrap()
+                .serve_at("/org/mpris/MediaPlayer2", MprisRoot {})
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let client = connection::Builder::address(address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let properties = zbus::fdo::PropertiesProxy::builder(&client)
+                .destination(server.unique_name().unwrap().as_str())
+                .unwrap()
+                .path("/org/mpris/MediaPlayer2")
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+
+            let has_track_list = properties
+                .get(
+                    InterfaceName::from_static_str_unchecked("org.mpris.MediaPlayer2"),
+                    "HasTrackList",
+                )
+                .await
+                .unwrap();
+            assert!(bool::try_from(has_track_list).unwrap());
+        });
+    }
+
+    #[test]
+    fn emits_track_list_replaced() {
+        with_private_bus(|address| async move {
+            let server = connection::Builder::address(address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let client = connection::Builder::address(address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let rule = zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface("org.mpris.MediaPlayer2.TrackList")
+                .unwrap()
+                .member("TrackListReplaced")
+                .unwrap()
+                .build();
+            let mut signals = zbus::MessageStream::for_match_rule(rule, &client, None)
+                .await
+                .unwrap();
+
+            let emitter = SignalEmitter::new(&server, "/org/mpris/MediaPlayer2").unwrap();
+            let tracks: Vec<OwnedObjectPath> =
+                vec![track_id(Some(1)).into(), track_id(Some(4)).into()];
+            MprisTrackList::track_list_replaced(&emitter, &tracks, &tracks[1])
+                .await
+                .unwrap();
+
+            let signal = signals.next().await.unwrap().unwrap();
+            let (received, current): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+                signal.body().deserialize().unwrap();
+            assert_eq!(received, tracks);
+            assert_eq!(current, tracks[1]);
+        });
+    }
+}
@@ -0,0 +1,341 @@
+//! Desktop notifications about playback, errors and the library.
+
+use std::sync::Arc;
+use std::sync::mpsc;
+
+use log::error;
+#[cfg(all(unix, not(target_os = "macos")))]
+use log::info;
+use notify_rust::{Notification, Timeout};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::config::DEFAULT_NOTIFICATION_URGENCY;
+use crate::config::{
+    Config, DEFAULT_NOTIFICATION_ACTIONS, DEFAULT_NOTIFICATION_EVENTS, DEFAULT_NOTIFY,
+    NotificationEvent, NotificationUrgency,
+};
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::events::Event;
+use crate::events::EventManager;
+use crate::library::Library;
+use crate::model::playable::Playable;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::queue::QueueEvent;
+
+/// An action that can be invoked from a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    Next,
+    Save,
+    TogglePlayback,
+}
+
+impl NotificationAction {
+    /// The identifier of the action in the notification.
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Next => "next",
+            Self::Save => "save",
+            Self::TogglePlayback => "playpause",
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn from_id(id: &str) -> Option<Self> {
+        [Self::Next, Self::Save, Self::TogglePlayback]
+            .into_iter()
+            .find(|action| action.id() == id)
+    }
+
+    /// The label of the button of the action. `paused` is whether playback is paused.
+    fn label(&self, paused: bool) -> &'static str {
+        match self {
+            Self::Next => "Skip",
+            Self::Save => "Like",
+            Self::TogglePlayback if paused => "Play",
+            Self::TogglePlayback => "Pause",
+        }
+    }
+}
+
+/// A notification that waits to be shown by the worker thread of a [Notifier].
+struct Request {
+    /// A short title for the notification.
+    summary_txt: String,
+    /// The actual content of the notification.
+    body_txt: String,
+    /// A URL to an image to show in the notification. It is downloaded to the cover cache if it
+    /// isn't there yet.
+    cover_url: Option<String>,
+    actions: Vec<NotificationAction>,
+    /// Whether playback is paused, which changes the label of the playback action.
+    paused: bool,
+}
+
+/// The notification that was shown last, which the next one replaces.
+#[cfg(all(unix, not(target_os = "macos")))]
+struct Shown {
+    id: u32,
+    /// The thread that waits for the actions of the notification until it's closed.
+    actions: Option<std::thread::JoinHandle<()>>,
+}
+
+/// Sends desktop notifications. Every notification replaces the previous one instead of being
+/// stacked on top of it. They are shown one after another by a worker thread, which stops when the
+/// last clone of the notifier is dropped.
+#[derive(Clone)]
+pub struct Notifier {
+    cfg: Arc<Config>,
+    tx: mpsc::Sender<Request>,
+}
+
+impl Notifier {
+    pub fn new(cfg: Arc<Config>, events: EventManager) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let worker_cfg = cfg.clone();
+        std::thread::spawn(move || show_notifications(rx, &worker_cfg, &events));
+        Self { cfg, tx }
+    }
+
+    /// Whether notifications should be sent for `event`.
+    fn enabled(&self, event: NotificationEvent) -> bool {
+        let values = self.cfg.values();
+        values.notify.unwrap_or(DEFAULT_NOTIFY)
+            && values
+                .notifications
+                .as_ref()
+                .and_then(|n| n.events.as_deref())
+                .unwrap_or(&DEFAULT_NOTIFICATION_EVENTS)
+                .contains(&event)
+    }
+
+    /// Notify that `playable` started playing, or was paused if `paused` is true.
+    pub fn playback(&self, playable: &Playable, paused: bool, library: &Library) {
+        let event = if paused {
+            NotificationEvent::Pause
+        } else {
+            NotificationEvent::Track
+        };
+        if !self.enabled(event) {
+            return;
+        }
+
+        // use same parser as track_format, Playable::format
+        let format = self
+            .cfg
+            .values()
+            .notification_format
+            .clone()
+            .unwrap_or_default();
+        let default_title = crate::config::NotificationFormat::default().title.unwrap();
+        let title = format.title.unwrap_or_else(|| default_title.clone());
+
+        let default_body = crate::config::NotificationFormat::default().body.unwrap();
+        let body = format.body.unwrap_or_else(|| default_body.clone());
+
+        let summary_txt = Playable::format(playable, &title, library);
+        let mut body_txt = Playable::format(playable, &body, library);
+        if paused {
+            body_txt = format!("Paused: {body_txt}");
+        }
+
+        let mut actions = vec![NotificationAction::Next];
+        if matches!(playable, Playable::Track(_)) && !library.is_saved_track(playable) {
+            actions.push(NotificationAction::Save);
+        }
+        actions.push(NotificationAction::TogglePlayback);
+
+        self.send(summary_txt, body_txt, playable.cover_url(), actions, paused);
+    }
+
+    /// Notify about an error.
+    pub fn error(&self, message: &str) {
+        if self.enabled(NotificationEvent::Error) {
+            self.send("Error".into(), message.into(), None, Vec::new(), false);
+        }
+    }
+
+    /// Notify that the library finished updating.
+    pub fn library_updated(&self, library: &Library) {
+        if self.enabled(NotificationEvent::Library) {
+            let body = format!(
+                "{} tracks, {} albums, {} playlists",
+                library.tracks.read().unwrap().len(),
+                library.albums.read().unwrap().len(),
+                library.playlists.read().unwrap().len()
+            );
+            self.send("Library updated".into(), body, None, Vec::new(), false);
+        }
+    }
+
+    /// Queue a notification for the worker thread.
+    fn send(
+        &self,
+        summary_txt: String,
+        body_txt: String,
+        cover_url: Option<String>,
+        actions: Vec<NotificationAction>,
+        paused: bool,
+    ) {
+        let request = Request {
+            summary_txt,
+            body_txt,
+            cover_url,
+            actions,
+            paused,
+        };
+        if self.tx.send(request).is_err() {
+            error!("Notification thread stopped");
+        }
+    }
+}
+
+/// Show the notifications from `rx` one after another, each in place of the previous one.
+fn show_notifications(rx: mpsc::Receiver<Request>, cfg: &Config, events: &EventManager) {
+    let mut shown = None;
+    for request in rx {
+        let options = cfg.values().notifications.clone().unwrap_or_default();
+
+        let mut n = Notification::new();
+        n.appname("ncspot")
+            .summary(&request.summary_txt)
+            .body(&request.body_txt)
+            .timeout(timeout(options.timeout));
+
+        // album cover image
+        if let Some(u) = request.cover_url {
+            match crate::cache::cover(&u) {
+                Ok(path) => {
+                    n.icon(path.to_str().unwrap());
+                }
+                Err(e) => error!("Failed to download cover: {e}"),
+            }
+        }
+
+        let actions = if options.actions.unwrap_or(DEFAULT_NOTIFICATION_ACTIONS) {
+            request.actions
+        } else {
+            Vec::new()
+        };
+        for action in &actions {
+            n.action(action.id(), action.label(request.paused));
+        }
+
+        shown = show(n, shown, options.urgency, !actions.is_empty(), events);
+    }
+}
+
+/// How long notifications stay open if they should be closed after `timeout` milliseconds.
+fn timeout(timeout: Option<u32>) -> Timeout {
+    match timeout {
+        Some(0) => Timeout::Never,
+        Some(ms) => Timeout::Milliseconds(ms),
+        None => Timeout::Default,
+    }
+}
+
+/// Show `n` in place of the `previous` notification, keeping its id, and wait for its actions in
+/// another thread. Returns the shown notification.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(
+    mut n: Notification,
+    previous: Option<Shown>,
+    urgency: Option<NotificationUrgency>,
+    has_actions: bool,
+    events: &EventManager,
+) -> Option<Shown> {
+    // XDG desktop entry hints
+    n.urgency(match urgency.unwrap_or(DEFAULT_NOTIFICATION_URGENCY) {
+        NotificationUrgency::Low => notify_rust::Urgency::Low,
+        NotificationUrgency::Normal => notify_rust::Urgency::Normal,
+        NotificationUrgency::Critical => notify_rust::Urgency::Critical,
+    })
+    .hint(notify_rust::Hint::Transient(true))
+    .hint(notify_rust::Hint::DesktopEntry("ncspot".into()));
+
+    if let Some(previous) = &previous {
+        n.id(previous.id);
+    }
+
+    match n.show() {
+        Ok(handle) => {
+            let id = handle.id();
+            info!("Created notification: {id}");
+            // The thread that waits for the actions of the replaced notification keeps waiting for
+            // the ones of the new notification, as they share the id. It ends when the notification
+            // is closed, after which the server may assign a new id.
+            let mut actions = previous
+                .filter(|previous| previous.id == id)
+                .and_then(|previous| previous.actions)
+                .filter(|thread| !thread.is_finished());
+            if has_actions && actions.is_none() {
+                let events = events.clone();
+                actions = Some(std::thread::spawn(move || {
+                    notify_rust::handle_action(id, |action: &str| invoke(action, &events));
+                }));
+            }
+            Some(Shown { id, actions })
+        }
+        Err(e) => {
+            error!("Failed to send notification: {e}");
+            None
+        }
+    }
+}
+
+/// Show `n`. Urgency, replacing and actions are only supported by XDG notifications.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show(
+    n: Notification,
+    _previous: Option<()>,
+    _urgency: Option<NotificationUrgency>,
+    _has_actions: bool,
+    _events: &EventManager,
+) -> Option<()> {
+    if let Err(e) = n.show() {
+        error!("Failed to send notification: {e}");
+    }
+    None
+}
+
+/// Handle the notification action with `id`. It is `__closed` if the notification was closed
+/// without an action.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn invoke(id: &str, events: &EventManager) {
+    if let Some(action) = NotificationAction::from_id(id) {
+        info!("Notification action: {action:?}");
+        events.send(Event::Queue(QueueEvent::NotificationAction(action)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_playback_action_by_state() {
+        assert_eq!(NotificationAction::TogglePlayback.label(true), "Play");
+        assert_eq!(NotificationAction::TogglePlayback.label(false), "Pause");
+        assert_eq!(NotificationAction::Next.label(true), "Skip");
+    }
+
+    #[test]
+    fn converts_timeouts() {
+        assert_eq!(timeout(Some(0)), Timeout::Never);
+        assert_eq!(timeout(Some(5000)), Timeout::Milliseconds(5000));
+        assert_eq!(timeout(None), Timeout::Default);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn parses_action_ids() {
+        for action in [
+            NotificationAction::Next,
+            NotificationAction::Save,
+            NotificationAction::TogglePlayback,
+        ] {
+            assert_eq!(NotificationAction::from_id(action.id()), Some(action));
+        }
+        assert_eq!(NotificationAction::from_id("__closed"), None);
+    }
+}
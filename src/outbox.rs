@@ -0,0 +1,143 @@
+//! Changes of the library that are made while ncspot is offline. They are applied to the local
+//! library right away and sent to Spotify once it can be reached again.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+
+use crate::model::playable::Playable;
+
+/// A change of the user library on Spotify.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum Operation {
+    SaveTracks { ids: Vec<String> },
+    UnsaveTracks { ids: Vec<String> },
+    SaveAlbum { id: String },
+    UnsaveAlbum { id: String },
+    FollowArtist { id: String },
+    UnfollowArtist { id: String },
+    FollowPlaylist { id: String },
+    SaveShow { id: String },
+    UnsaveShow { id: String },
+    AddToPlaylist { id: String, tracks: Vec<Playable> },
+}
+
+/// The operations that haven't been sent to Spotify yet, in the order in which they were made. They
+/// are stored in a file so that they aren't lost when ncspot is closed while offline.
+pub struct Outbox {
+    path: PathBuf,
+    operations: Mutex<Vec<Operation>>,
+}
+
+impl Outbox {
+    /// Load the outbox stored in the file at `path`, which is empty if the file doesn't exist.
+    pub fn load(path: PathBuf) -> Self {
+        let operations = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                error!("can't parse outbox {}: {e}", path.display());
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        debug!("{} operations in the outbox", operations.len());
+
+        Self {
+            path,
+            operations: Mutex::new(operations),
+        }
+    }
+
+    /// The number of operations that haven't been sent yet.
+    pub fn len(&self) -> usize {
+        self.operations.lock().unwrap().len()
+    }
+
+    /// Whether all operations have been sent.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add `operation` to the end of the outbox.
+    pub fn push(&self, operation: Operation) {
+        let mut operations = self.operations.lock().unwrap();
+        operations.push(operation);
+        self.save(&operations);
+    }
+
+    /// Send the operations in order with `send`, which returns whether sending succeeded. Stops at
+    /// the first operation that fails, so that later operations aren't applied before it. Returns
+    /// the number of operations that were sent.
+    pub fn replay(&self, mut send: impl FnMut(&Operation) -> bool) -> usize {
+        let mut operations = self.operations.lock().unwrap();
+        let sent = operations
+            .iter()
+            .position(|operation| !send(operation))
+            .unwrap_or(operations.len());
+        if sent > 0 {
+            operations.drain(..sent);
+            self.save(&operations);
+        }
+        sent
+    }
+
+    /// Write `operations` to the file of the outbox, or remove it if there are none.
+    fn save(&self, operations: &[Operation]) {
+        let result = if operations.is_empty() {
+            fs::remove_file(&self.path).or_else(|e| match e.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })
+        } else {
+            serde_json::to_string(operations)
+                .map_err(io::Error::other)
+                .and_then(|json| fs::write(&self.path, json))
+        };
+        if let Err(e) = result {
+            error!("could not write outbox {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation_ids(outbox: &Outbox) -> Vec<String> {
+        outbox
+            .operations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|operation| match operation {
+                Operation::SaveAlbum { id } | Operation::FollowArtist { id } => id.clone(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn persists_and_replays_operations_in_order() {
+        let path = std::env::temp_dir().join(format!("ncspot-outbox-{}.db", std::process::id()));
+        let outbox = Outbox::load(path.clone());
+        outbox.push(Operation::SaveAlbum { id: "a".into() });
+        outbox.push(Operation::FollowArtist { id: "b".into() });
+        outbox.push(Operation::SaveAlbum { id: "c".into() });
+
+        let outbox = Outbox::load(path.clone());
+        assert_eq!(operation_ids(&outbox), ["a", "b", "c"]);
+
+        // Sending stops at the first failure, so that the order is kept.
+        let sent = outbox.replay(|operation| matches!(operation, Operation::SaveAlbum { .. }));
+        assert_eq!(sent, 1);
+        assert_eq!(operation_ids(&Outbox::load(path.clone())), ["b", "c"]);
+
+        assert_eq!(outbox.replay(|_| true), 2);
+        assert!(outbox.is_empty());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,37 @@
+//! A private D-Bus session bus for tests, so that they don't depend on the bus of the desktop.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+/// A private session bus, which is stopped when it's dropped.
+pub struct PrivateBus {
+    daemon: Child,
+    /// The address that connections to the bus use.
+    pub address: String,
+}
+
+impl PrivateBus {
+    /// Start a private bus, if `dbus-daemon` is installed.
+    pub fn start() -> Option<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(Self {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
@@ -1,19 +1,30 @@
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{self, AtomicUsize};
 use std::sync::{Arc, RwLock};
 
-use log::{debug, info};
-#[cfg(feature = "notify")]
-use notify_rust::Notification;
+use log::{debug, info, warn};
 
 use rand::prelude::*;
 use strum_macros::Display;
 
-use crate::config::Config;
+use crate::application::ASYNC_RUNTIME;
+use crate::config::{Config, DEFAULT_AUTOPLAY, DEFAULT_COVER_PREFETCH, SortingOrder};
+use crate::events::Event;
 use crate::library::Library;
+use crate::model::episode::Episode;
 use crate::model::playable::Playable;
+use crate::model::track::Track;
+#[cfg(feature = "notify")]
+use crate::notification::NotificationAction;
 use crate::spotify::PlayerEvent;
-use crate::spotify::Spotify;
-use crate::traits::ListItem;
+use crate::spotify::{Spotify, UriType};
+use crate::traits::{ListItem, Sortable};
+
+/// The amount of recently played items that are remembered for autoplay.
+const AUTOPLAY_HISTORY: usize = 50;
+/// The amount of tracks appended to the queue by autoplay at once.
+const AUTOPLAY_BATCH: usize = 10;
 
 /// Repeat behavior for the [Queue].
 #[derive(Display, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -26,12 +37,39 @@ pub enum RepeatSetting {
     RepeatTrack,
 }
 
+/// The algorithm used to determine the playback order when shuffle is enabled.
+#[derive(Display, Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ShuffleMode {
+    /// A uniformly random permutation.
+    #[default]
+    Random,
+    /// Spread out items by the same artist as evenly as possible.
+    Spread,
+    /// Favor saved items and items that weren't played recently.
+    Weighted,
+    /// Shuffle the order of albums, but keep the tracks of every album in order.
+    Albums,
+}
+
 /// Events that are specific to the [Queue].
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub enum QueueEvent {
     /// Request the player to 'preload' a track, basically making sure that
     /// transitions between tracks can be uninterrupted.
     PreloadTrackRequest,
+    /// A remote Spotify Connect client started playback of the item with the given URI, which
+    /// should be reflected in the queue.
+    RemoteTrack(String),
+    /// The item played by a remote Spotify Connect client was fetched because it wasn't in the
+    /// queue yet.
+    RemotePlayable(Playable),
+    /// Recommendations to append to the queue were fetched for autoplay.
+    Autoplay(Vec<Track>),
+    /// An action was invoked from a desktop notification.
+    #[cfg(feature = "notify")]
+    NotificationAction(NotificationAction),
 }
 
 /// The queue determines the playback order of [Playable] items, and is also used to control
@@ -40,9 +78,20 @@ pub struct Queue {
     /// The internal data, which doesn't change with shuffle or repeat. This is
     /// the raw data only.
     pub queue: Arc<RwLock<Vec<Playable>>>,
+    /// An identifier for every item in `self.queue`, which stays the same while the item is in the
+    /// queue, even when other items are added, removed or moved.
+    ids: RwLock<Vec<usize>>,
+    /// The identifier of the next item that is added to the queue.
+    next_id: AtomicUsize,
     /// The playback order of the queue, as indices into `self.queue`.
     random_order: RwLock<Option<Vec<usize>>>,
     current_track: RwLock<Option<usize>>,
+    /// Recently played items, most recent last. Used to seed autoplay and avoid repeats.
+    history: RwLock<VecDeque<Playable>>,
+    /// Whether autoplay recommendations are being fetched.
+    autoplay_pending: RwLock<bool>,
+    /// Incremented whenever the items of the queue change, see [Queue::generation].
+    generation: AtomicUsize,
     spotify: Spotify,
     cfg: Arc<Config>,
     library: Arc<Library>,
@@ -51,11 +100,17 @@ pub struct Queue {
 impl Queue {
     pub fn new(spotify: Spotify, cfg: Arc<Config>, library: Arc<Library>) -> Self {
         let queue_state = cfg.state().queuestate.clone();
+        let len = queue_state.queue.len();
 
         Self {
             queue: Arc::new(RwLock::new(queue_state.queue)),
+            ids: RwLock::new((0..len).collect()),
+            next_id: AtomicUsize::new(len),
             spotify: spotify.clone(),
             current_track: RwLock::new(queue_state.current_track),
+            history: RwLock::new(VecDeque::new()),
+            autoplay_pending: RwLock::new(false),
+            generation: AtomicUsize::new(0),
             random_order: RwLock::new(queue_state.random_order),
             cfg,
             library,
@@ -123,6 +178,12 @@ impl Queue {
         *self.current_track.read().unwrap()
     }
 
+    /// The identifier of the currently playing item, see [Queue::ids].
+    pub fn get_current_id(&self) -> Option<usize> {
+        let index = self.get_current_index()?;
+        self.ids.read().unwrap().get(index).copied()
+    }
+
     /// Insert `track` as the item that should logically follow the currently
     /// playing item, taking into account shuffle status.
     pub fn insert_after_current(&self, track: Playable) {
@@ -141,9 +202,13 @@ impl Queue {
             }
             let mut q = self.queue.write().unwrap();
             q.insert(index + 1, track);
+            self.ids.write().unwrap().insert(index + 1, self.new_id());
         } else {
             self.append(track);
+            return;
         }
+
+        self.changed();
     }
 
     /// Add `track` to the end of the queue.
@@ -154,8 +219,10 @@ impl Queue {
             order.push(index);
         }
 
-        let mut q = self.queue.write().unwrap();
-        q.push(track);
+        self.queue.write().unwrap().push(track);
+        self.ids.write().unwrap().push(self.new_id());
+
+        self.changed();
     }
 
     /// Append `tracks` after the currently playing item, taking into account
@@ -175,11 +242,17 @@ impl Queue {
             None => q.len(),
         };
 
+        let mut ids = self.ids.write().unwrap();
         let mut i = first;
         for track in tracks {
             q.insert(i, track.clone());
+            ids.insert(i, self.new_id());
             i += 1;
         }
+        drop(ids);
+        drop(q);
+
+        self.changed();
 
         first
     }
@@ -194,8 +267,11 @@ impl Queue {
                 return;
             }
             q.remove(index);
+            self.ids.write().unwrap().remove(index);
         }
 
+        self.changed();
+
         // if the queue is empty stop playback
         let len = self.queue.read().unwrap().len();
         if len == 0 {
@@ -241,11 +317,14 @@ impl Queue {
 
         let mut q = self.queue.write().unwrap();
         q.clear();
+        self.ids.write().unwrap().clear();
 
         let mut random_order = self.random_order.write().unwrap();
         if let Some(o) = random_order.as_mut() {
             o.clear()
         }
+
+        self.changed();
     }
 
     /// The amount of items in `self.queue`.
@@ -258,6 +337,10 @@ impl Queue {
         let mut queue = self.queue.write().unwrap();
         let item = queue.remove(from);
         queue.insert(to, item);
+        let mut ids = self.ids.write().unwrap();
+        let id = ids.remove(from);
+        ids.insert(to, id);
+        drop(ids);
 
         // if the currently playing track is affected by the shift, update its
         // index
@@ -271,6 +354,38 @@ impl Queue {
                 current.replace(to - 1);
             }
         }
+
+        self.changed();
+    }
+
+    /// Sort the queue by `order`. The currently playing item keeps playing and is followed by
+    /// the items after it in the new order.
+    pub fn sort(&self, order: &SortingOrder) {
+        {
+            let mut queue = self.queue.write().unwrap();
+            let state = self.cfg.state();
+            let mut indices: Vec<usize> = (0..queue.len()).collect();
+            indices.sort_by(|a, b| {
+                order.compare(|key| queue[*a].compare(&queue[*b], key, &state.play_counts))
+            });
+
+            let mut current = self.current_track.write().unwrap();
+            if let Some(index) = *current {
+                *current = indices.iter().position(|i| *i == index);
+            }
+
+            let sorted: Vec<Playable> = indices.iter().map(|i| queue[*i].clone()).collect();
+            *queue = sorted;
+            let mut ids = self.ids.write().unwrap();
+            let sorted_ids: Vec<usize> = indices.iter().map(|i| ids[*i]).collect();
+            *ids = sorted_ids;
+        }
+
+        self.changed();
+
+        if self.get_shuffle() {
+            self.generate_random_order();
+        }
     }
 
     /// Play the item at `index` in `self.queue`.
@@ -291,29 +406,13 @@ impl Queue {
             let mut current = self.current_track.write().unwrap();
             current.replace(index);
             self.spotify.update_track();
+            self.remember(track);
+            self.cfg.with_state_mut(|state| {
+                *state.play_counts.entry(track.uri()).or_default() += 1;
+            });
 
             #[cfg(feature = "notify")]
-            if self.cfg.values().notify.unwrap_or(false) {
-                std::thread::spawn({
-                    // use same parser as track_format, Playable::format
-                    let format = self
-                        .cfg
-                        .values()
-                        .notification_format
-                        .clone()
-                        .unwrap_or_default();
-                    let default_title = crate::config::NotificationFormat::default().title.unwrap();
-                    let title = format.title.unwrap_or_else(|| default_title.clone());
-
-                    let default_body = crate::config::NotificationFormat::default().body.unwrap();
-                    let body = format.body.unwrap_or_else(|| default_body.clone());
-
-                    let summary_txt = Playable::format(track, &title, &self.library);
-                    let body_txt = Playable::format(track, &body, &self.library);
-                    let cover_url = track.cover_url();
-                    move || send_notification(&summary_txt, &body_txt, cover_url)
-                });
-            }
+            self.library.notifier.playback(track, false, &self.library);
 
             // Send a Seeked signal at start of new track
             #[cfg(feature = "mpris")]
@@ -323,6 +422,49 @@ impl Queue {
         if reshuffle && self.get_shuffle() {
             self.generate_random_order()
         }
+
+        self.prefetch_covers();
+    }
+
+    /// Download the covers of the items that play after the current one in the background, so
+    /// that they can be shown without delay.
+    fn prefetch_covers(&self) {
+        let count = self
+            .cfg
+            .values()
+            .cover_prefetch
+            .unwrap_or(DEFAULT_COVER_PREFETCH);
+        let Some(current) = self.get_current_index() else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+
+        let queue = self.queue.read().unwrap();
+        let order = match self.random_order.read().unwrap().as_ref() {
+            Some(order) => order.clone(),
+            None => (0..queue.len()).collect(),
+        };
+        let Some(position) = order.iter().position(|&index| index == current) else {
+            return;
+        };
+        let urls = order[position + 1..]
+            .iter()
+            .take(count)
+            .filter_map(|&index| queue.get(index)?.cover_url())
+            .collect();
+        crate::cache::prefetch_covers(urls);
+    }
+
+    /// Send a desktop notification that the current item was paused.
+    #[cfg(feature = "notify")]
+    pub fn notify_paused(&self) {
+        if let Some(playable) = self.get_current() {
+            self.library
+                .notifier
+                .playback(&playable, true, &self.library);
+        }
     }
 
     /// Toggle the playback. If playback is currently stopped, this will either
@@ -364,6 +506,15 @@ impl Queue {
     /// used, and the next track will actually be played. This should be used
     /// when going to the next entry in the queue is the wanted behavior.
     pub fn next(&self, manual: bool) {
+        if self.spotify.is_remote() {
+            self.spotify.next();
+            return;
+        }
+
+        if self.next_index().is_none() && self.get_repeat() == RepeatSetting::None {
+            self.autoplay();
+        }
+
         let q = self.queue.read().unwrap();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
@@ -396,6 +547,11 @@ impl Queue {
 
     /// Play the previous item in the queue.
     pub fn previous(&self) {
+        if self.spotify.is_remote() {
+            self.spotify.previous();
+            return;
+        }
+
         let q = self.queue.read().unwrap();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
@@ -450,13 +606,54 @@ impl Queue {
         }
 
         let mut rng = rand::rng();
-        random.shuffle(&mut rng);
+        let random = match self.get_shuffle_mode() {
+            ShuffleMode::Random => {
+                random.shuffle(&mut rng);
+                random
+            }
+            ShuffleMode::Spread => spread_shuffle(&q, random, &mut rng),
+            ShuffleMode::Weighted => {
+                let recent: HashSet<String> = self
+                    .history
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|playable| playable.uri())
+                    .collect();
+                let weights: Vec<f64> = q
+                    .iter()
+                    .map(|playable| {
+                        let mut weight = 1.0;
+                        if self.library.is_saved_track(playable) {
+                            weight *= 2.0;
+                        }
+                        if recent.contains(&playable.uri()) {
+                            weight *= 0.25;
+                        }
+                        weight
+                    })
+                    .collect();
+                weighted_shuffle(random, &weights, &mut rng)
+            }
+            ShuffleMode::Albums => album_shuffle(&q, order.first().copied(), random, &mut rng),
+        };
         order.extend(random);
 
         let mut random_order = self.random_order.write().unwrap();
         *random_order = Some(order);
     }
 
+    /// Get the algorithm that is used to shuffle.
+    pub fn get_shuffle_mode(&self) -> ShuffleMode {
+        self.cfg.state().shuffle_mode
+    }
+
+    /// Set the algorithm that is used to shuffle. Takes effect the next time the order is
+    /// generated.
+    pub fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        self.cfg.with_state_mut(|s| s.shuffle_mode = mode);
+    }
+
     /// Set the current shuffle behavior.
     pub fn set_shuffle(&self, new: bool) {
         self.cfg.with_state_mut(|s| s.shuffle = new);
@@ -472,13 +669,216 @@ impl Queue {
     pub fn handle_event(&self, event: QueueEvent) {
         match event {
             QueueEvent::PreloadTrackRequest => {
+                if self.next_index().is_none() && self.get_repeat() == RepeatSetting::None {
+                    self.autoplay();
+                }
                 if let Some(next_index) = self.next_index() {
                     let track = self.queue.read().unwrap()[next_index].clone();
                     debug!("Preloading track {track} as requested by librespot");
                     self.spotify.preload(&track);
                 }
             }
+            QueueEvent::RemoteTrack(uri) => self.mirror_remote_track(&uri),
+            QueueEvent::RemotePlayable(playable) => self.mirror_remote_playable(playable),
+            QueueEvent::Autoplay(tracks) => self.append_autoplay(tracks),
+            #[cfg(feature = "notify")]
+            QueueEvent::NotificationAction(action) => match action {
+                NotificationAction::Next => self.next(true),
+                NotificationAction::TogglePlayback => self.toggleplayback(),
+                NotificationAction::Save => {
+                    if let Some(Playable::Track(track)) = self.get_current() {
+                        self.library.save_tracks(&[&track]);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Add `playable` to the playback history.
+    fn remember(&self, playable: &Playable) {
+        let mut history = self.history.write().unwrap();
+        if history.len() == AUTOPLAY_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(playable.clone());
+    }
+
+    /// Fetch recommendations based on the recently played tracks and their artists in the
+    /// background, if autoplay is enabled. They are appended to the queue once they arrive.
+    fn autoplay(&self) {
+        if !self.cfg.values().autoplay.unwrap_or(DEFAULT_AUTOPLAY) {
+            return;
+        }
+
+        let mut pending = self.autoplay_pending.write().unwrap();
+        if *pending {
+            return;
+        }
+
+        let (track_ids, artist_ids) = autoplay_seeds(&self.history.read().unwrap());
+        if track_ids.is_empty() && artist_ids.is_empty() {
+            debug!("autoplay: no recently played tracks to base recommendations on");
+            return;
+        }
+        *pending = true;
+
+        let api = self.spotify.api.clone();
+        let spotify = self.spotify.clone();
+        ASYNC_RUNTIME.get().unwrap().spawn_blocking(move || {
+            let seeds = |ids: &[String]| {
+                (!ids.is_empty()).then(|| ids.iter().map(String::as_str).collect::<Vec<&str>>())
+            };
+            let tracks = match api.recommendations(seeds(&artist_ids), None, seeds(&track_ids)) {
+                Ok(recommendations) => recommendations.tracks.iter().map(Track::from).collect(),
+                Err(_) => {
+                    warn!("autoplay: could not fetch recommendations");
+                    Vec::new()
+                }
+            };
+            spotify.send_event(Event::Queue(QueueEvent::Autoplay(tracks)));
+        });
+    }
+
+    /// Append the autoplay `recommendations` that weren't played recently and aren't queued
+    /// already. Playback continues with them if it stopped at the end of the queue in the meantime.
+    fn append_autoplay(&self, recommendations: Vec<Track>) {
+        *self.autoplay_pending.write().unwrap() = false;
+
+        let known: HashSet<String> = self
+            .history
+            .read()
+            .unwrap()
+            .iter()
+            .chain(self.queue.read().unwrap().iter())
+            .map(|playable| playable.uri())
+            .collect();
+        let tracks = autoplay_tracks(recommendations, &known);
+        info!("autoplay: appending {} tracks", tracks.len());
+
+        let resume = self.next_index().is_none()
+            && self.get_current_index().is_some()
+            && self.spotify.get_current_status() == PlayerEvent::Stopped;
+        let first = self.len();
+        for track in tracks {
+            self.append(Playable::Track(track));
+        }
+        if resume && first < self.len() {
+            self.play(first, false, false);
+        }
+    }
+
+    /// Make the item with `uri`, which is already being played by a remote Spotify Connect
+    /// client, the current item of the queue. Items that aren't in the queue yet are fetched in the
+    /// background and added once they arrive.
+    fn mirror_remote_track(&self, uri: &str) {
+        if self
+            .get_current()
+            .is_some_and(|current| current.uri() == uri)
+        {
+            return;
+        }
+
+        if let Some(index) = self.position_of(uri) {
+            self.set_remote_current(index);
+            return;
         }
+
+        let api = self.spotify.api.clone();
+        let spotify = self.spotify.clone();
+        let uri = uri.to_string();
+        ASYNC_RUNTIME.get().unwrap().spawn_blocking(move || {
+            let id = uri.rsplit(':').next().unwrap_or_default();
+            let playable = match uri.parse() {
+                Ok(UriType::Track) => api
+                    .track(id)
+                    .ok()
+                    .map(|track| Playable::Track(Track::from(&track))),
+                Ok(UriType::Episode) => api
+                    .episode(id)
+                    .ok()
+                    .map(|episode| Playable::Episode(Episode::from(&episode))),
+                _ => None,
+            };
+            match playable {
+                Some(playable) => {
+                    spotify.send_event(Event::Queue(QueueEvent::RemotePlayable(playable)))
+                }
+                None => warn!("could not mirror remote playback of {uri}"),
+            }
+        });
+    }
+
+    /// Add `playable`, which was fetched for a remote Spotify Connect client, to the queue and make
+    /// it the current item.
+    fn mirror_remote_playable(&self, playable: Playable) {
+        // The item might have been added while it was being fetched.
+        let index = self
+            .position_of(&playable.uri())
+            .unwrap_or_else(|| self.insert_next(playable));
+        self.set_remote_current(index);
+    }
+
+    /// Make the item at `index` current without loading it, as it is already being played.
+    fn set_remote_current(&self, index: usize) {
+        self.current_track.write().unwrap().replace(index);
+        self.spotify.update_track();
+    }
+
+    /// Continue playback of `playable` at `position_ms`, for example after it was transferred
+    /// from another Spotify Connect device.
+    pub fn resume_from(&self, playable: Playable, position_ms: u32) {
+        let index = self
+            .position_of(&playable.uri())
+            .unwrap_or_else(|| self.insert_next(playable.clone()));
+
+        self.spotify.load(&playable, true, position_ms);
+        self.current_track.write().unwrap().replace(index);
+        self.spotify.update_track();
+    }
+
+    /// The index of the item with `uri` in `self.queue`.
+    fn position_of(&self, uri: &str) -> Option<usize> {
+        self.queue
+            .read()
+            .unwrap()
+            .iter()
+            .position(|playable| playable.uri() == uri)
+    }
+
+    /// Insert `playable` after the currently playing item and return its index.
+    fn insert_next(&self, playable: Playable) -> usize {
+        self.insert_after_current(playable);
+        self.get_current_index()
+            .map(|index| index + 1)
+            .unwrap_or_else(|| self.len() - 1)
+    }
+
+    /// The identifiers of the items in `self.queue`, in the same order.
+    pub fn ids(&self) -> Vec<usize> {
+        self.ids.read().unwrap().clone()
+    }
+
+    /// The index in `self.queue` of the item with the identifier `id`, if it is still queued.
+    pub fn index_of_id(&self, id: usize) -> Option<usize> {
+        self.ids.read().unwrap().iter().position(|item| *item == id)
+    }
+
+    /// A new identifier for an item that is added to `self.queue`.
+    fn new_id(&self) -> usize {
+        self.next_id.fetch_add(1, atomic::Ordering::Relaxed)
+    }
+
+    /// A number that changes whenever the items of the queue change, so that views know when to
+    /// update what they derived from them.
+    pub fn generation(&self) -> usize {
+        self.generation.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Mark the items of the queue as changed.
+    fn changed(&self) {
+        self.generation.fetch_add(1, atomic::Ordering::Relaxed);
+        #[cfg(feature = "mpris")]
+        self.spotify.notify_tracklist_changed();
     }
 
     /// Get the spotify session.
@@ -487,41 +887,347 @@ impl Queue {
     }
 }
 
-/// Send a notification using the desktops default notification method.
+/// The ids of the most recently played tracks in `history` and of their artists, which seed the
+/// autoplay recommendations. Spotify accepts at most 5 seeds in total.
+fn autoplay_seeds(history: &VecDeque<Playable>) -> (Vec<String>, Vec<String>) {
+    const MAX_TRACK_SEEDS: usize = 3;
+    const MAX_ARTIST_SEEDS: usize = 2;
+
+    let recent: Vec<Track> = history
+        .iter()
+        .rev()
+        .filter_map(|playable| playable.track())
+        .take(MAX_TRACK_SEEDS)
+        .collect();
+
+    let track_ids: Vec<String> = recent.iter().filter_map(|t| t.id.clone()).collect();
+    let mut artist_ids: Vec<String> = Vec::new();
+    for id in recent.iter().flat_map(|t| t.artist_ids.iter()) {
+        if artist_ids.len() < MAX_ARTIST_SEEDS && !artist_ids.contains(id) {
+            artist_ids.push(id.clone());
+        }
+    }
+    (track_ids, artist_ids)
+}
+
+/// The first [AUTOPLAY_BATCH] `recommendations` whose URI isn't `known`, marked as added by
+/// autoplay.
+fn autoplay_tracks(recommendations: Vec<Track>, known: &HashSet<String>) -> Vec<Track> {
+    recommendations
+        .into_iter()
+        .filter(|track| !known.contains(&track.uri))
+        .take(AUTOPLAY_BATCH)
+        .map(|track| Track {
+            autoplay: true,
+            ..track
+        })
+        .collect()
+}
+
+/// The key that is used to spread out items in [ShuffleMode::Spread].
+fn spread_key(playable: &Playable) -> String {
+    playable
+        .track()
+        .and_then(|track| track.artists.first().cloned().or(track.album))
+        .unwrap_or_else(|| playable.uri())
+}
+
+/// Shuffle `indices` into `items` so that items with the same artist are evenly distributed.
 ///
-/// `summary_txt`: A short title for the notification.
-/// `body_txt`: The actual content of the notification.
-/// `cover_url`: A URL to an image to show in the notification.
-/// `notification_id`: Unique id for a notification, that can be used to operate
-/// on a previous notification (for example to close it).
-#[cfg(feature = "notify")]
-pub fn send_notification(summary_txt: &str, body_txt: &str, cover_url: Option<String>) {
-    let mut n = Notification::new();
-    n.appname("ncspot").summary(summary_txt).body(body_txt);
-
-    // album cover image
-    if let Some(u) = cover_url {
-        let path = crate::utils::cache_path_for_url(u.to_string());
-        if !path.exists()
-            && let Err(e) = crate::utils::download(u, path.clone())
-        {
-            log::error!("Failed to download cover: {e}");
+/// Every group of items by the same artist is shuffled and then laid out across the whole order
+/// with equal spacing, starting at a random offset.
+fn spread_shuffle<R: Rng>(items: &[Playable], indices: Vec<usize>, rng: &mut R) -> Vec<usize> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for index in indices {
+        groups
+            .entry(spread_key(&items[index]))
+            .or_default()
+            .push(index);
+    }
+
+    let mut positioned: Vec<(f64, usize)> = Vec::new();
+    for mut group in groups.into_values() {
+        group.shuffle(rng);
+        let spacing = 1.0 / group.len() as f64;
+        let offset = rng.random_range(0.0..spacing);
+        positioned.extend(
+            group
+                .into_iter()
+                .enumerate()
+                .map(|(i, index)| (offset + i as f64 * spacing, index)),
+        );
+    }
+
+    positioned.sort_by(|a, b| a.0.total_cmp(&b.0));
+    positioned.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Shuffle `indices` so that items with a higher weight in `weights` tend to come first.
+///
+/// Uses weighted random sampling without replacement: every index gets the key `u^(1/w)` for a
+/// uniformly random `u`, and the indices are ordered by descending key.
+fn weighted_shuffle<R: Rng>(indices: Vec<usize>, weights: &[f64], rng: &mut R) -> Vec<usize> {
+    let mut keyed: Vec<(f64, usize)> = indices
+        .into_iter()
+        .map(|index| {
+            let weight = weights[index].max(f64::EPSILON);
+            (rng.random::<f64>().powf(1.0 / weight), index)
+        })
+        .collect();
+
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed.into_iter().map(|(_, index)| index).collect()
+}
+
+/// The key that is used to group the tracks of an album in [ShuffleMode::Albums].
+fn album_key(playable: &Playable) -> String {
+    playable
+        .track()
+        .and_then(|track| track.album_id.or(track.album))
+        .unwrap_or_else(|| playable.uri())
+}
+
+/// The position of `playable` within its album.
+fn album_position(playable: &Playable) -> (i32, u32) {
+    playable
+        .track()
+        .map(|track| (track.disc_number, track.track_number))
+        .unwrap_or_default()
+}
+
+/// Shuffle the albums of the items at `indices`, keeping the tracks of every album in disc and
+/// track order. The remaining tracks of the album of `current` are played first.
+fn album_shuffle<R: Rng>(
+    items: &[Playable],
+    current: Option<usize>,
+    indices: Vec<usize>,
+    rng: &mut R,
+) -> Vec<usize> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for index in indices {
+        let key = album_key(&items[index]);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(index),
+            None => groups.push((key, vec![index])),
+        }
+    }
+    for (_, group) in groups.iter_mut() {
+        group.sort_by_key(|index| album_position(&items[*index]));
+    }
+
+    // Continue the album of the current track before moving on to the next album.
+    let mut order = Vec::new();
+    if let Some(current) = current {
+        let key = album_key(&items[current]);
+        let position = album_position(&items[current]);
+        if let Some((_, group)) = groups.iter_mut().find(|(k, _)| *k == key) {
+            let following: Vec<usize> = group
+                .iter()
+                .copied()
+                .filter(|index| album_position(&items[*index]) > position)
+                .collect();
+            group.retain(|index| !following.contains(index));
+            order.extend(following);
+        }
+        groups.retain(|(_, group)| !group.is_empty());
+    }
+
+    groups.shuffle(rng);
+    order.extend(groups.into_iter().flat_map(|(_, group)| group));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{SortDirection, SortKey};
+
+    fn track(artist: &str, album: &str, number: u32) -> Playable {
+        Playable::Track(Track {
+            id: None,
+            uri: format!("spotify:track:{artist}{album}{number}"),
+            title: format!("{artist} {number}"),
+            track_number: number,
+            disc_number: 1,
+            duration: 0,
+            artists: vec![artist.to_string()],
+            artist_ids: Vec::new(),
+            album: Some(album.to_string()),
+            album_id: Some(album.to_string()),
+            album_artists: vec![artist.to_string()],
+            cover_url: None,
+            url: String::new(),
+            added_at: None,
+            list_index: 0,
+            is_local: false,
+            is_playable: None,
+            release_date: None,
+            popularity: None,
+            explicit: false,
+            autoplay: false,
+        })
+    }
+
+    fn seed(id: &str, artist_ids: &[&str]) -> Playable {
+        let mut playable = track(id, "a", 1);
+        if let Playable::Track(track) = &mut playable {
+            track.id = Some(id.to_string());
+            track.artist_ids = artist_ids.iter().map(|id| id.to_string()).collect();
         }
-        n.icon(path.to_str().unwrap());
+        playable
+    }
+
+    #[test]
+    fn seeds_autoplay_with_recent_tracks_and_artists() {
+        let history = VecDeque::from([
+            seed("t1", &["a1"]),
+            seed("t2", &["a2"]),
+            seed("t3", &["a3", "a2"]),
+            seed("t4", &["a2"]),
+        ]);
+        let (track_ids, artist_ids) = autoplay_seeds(&history);
+        assert_eq!(track_ids, ["t4", "t3", "t2"]);
+        assert_eq!(artist_ids, ["a2", "a3"]);
+
+        assert_eq!(autoplay_seeds(&VecDeque::new()), (Vec::new(), Vec::new()));
     }
 
-    // XDG desktop entry hints
-    #[cfg(all(unix, not(target_os = "macos")))]
-    n.urgency(notify_rust::Urgency::Low)
-        .hint(notify_rust::Hint::Transient(true))
-        .hint(notify_rust::Hint::DesktopEntry("ncspot".into()));
+    #[test]
+    fn autoplay_skips_known_tracks() {
+        let recommendations: Vec<Track> = (1..=AUTOPLAY_BATCH as u32 + 2)
+            .filter_map(|number| track("r", "x", number).track())
+            .collect();
+        let known = HashSet::from(["spotify:track:rx1".to_string()]);
+
+        let tracks = autoplay_tracks(recommendations, &known);
+        assert_eq!(tracks.len(), AUTOPLAY_BATCH);
+        assert_eq!(tracks[0].uri, "spotify:track:rx2");
+        assert!(tracks.iter().all(|track| track.autoplay));
+    }
+
+    fn is_permutation(order: &[usize], len: usize) -> bool {
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        sorted == (0..len).collect::<Vec<usize>>()
+    }
+
+    #[test]
+    fn sorts_by_multiple_keys() {
+        let mut items = vec![track("b", "y", 1), track("a", "x", 2), track("a", "x", 1)];
+        let play_counts = HashMap::from([("spotify:track:ax1".to_string(), 3)]);
+
+        let order = SortingOrder::new(
+            &[SortKey::Artist, SortKey::TrackNumber],
+            &SortDirection::Ascending,
+        )
+        .unwrap();
+        Playable::sort(&mut items, &order, &play_counts);
+        let uris: Vec<String> = items.iter().map(|t| t.uri()).collect();
+        assert_eq!(
+            uris,
+            [
+                "spotify:track:ax1",
+                "spotify:track:ax2",
+                "spotify:track:by1"
+            ]
+        );
 
-    match n.show() {
-        Ok(handle) => {
-            // only available for XDG
-            #[cfg(all(unix, not(target_os = "macos")))]
-            info!("Created notification: {}", handle.id());
+        let order = SortingOrder::new(&[SortKey::PlayCount], &SortDirection::Descending).unwrap();
+        Playable::sort(&mut items, &order, &play_counts);
+        assert_eq!(items[0].uri(), "spotify:track:ax1");
+    }
+
+    #[test]
+    fn spread_shuffle_alternates_equally_sized_artists() {
+        let items: Vec<Playable> = (0..10)
+            .flat_map(|i| [track("a", "x", i), track("b", "y", i)])
+            .collect();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let order = spread_shuffle(&items, (0..items.len()).collect(), &mut rng);
+            assert!(is_permutation(&order, items.len()));
+            for pair in order.windows(2) {
+                assert_ne!(spread_key(&items[pair[0]]), spread_key(&items[pair[1]]));
+            }
+        }
+    }
+
+    #[test]
+    fn spread_shuffle_separates_minority_artist() {
+        let mut items: Vec<Playable> = (0..12).map(|i| track("a", "x", i)).collect();
+        items.extend((0..4).map(|i| track("b", "y", i)));
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let order = spread_shuffle(&items, (0..items.len()).collect(), &mut rng);
+            let positions: Vec<usize> = order
+                .iter()
+                .enumerate()
+                .filter(|(_, index)| **index >= 12)
+                .map(|(position, _)| position)
+                .collect();
+            for pair in positions.windows(2) {
+                assert!(pair[1] - pair[0] >= 3);
+            }
+        }
+    }
+
+    #[test]
+    fn album_shuffle_keeps_albums_in_order() {
+        let mut items: Vec<Playable> = Vec::new();
+        for album in ["x", "y", "z"] {
+            // add tracks out of order to make sure they get sorted
+            items.extend((1..=4).rev().map(|i| track("a", album, i)));
+        }
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..20 {
+            let order = album_shuffle(&items, None, (0..items.len()).collect(), &mut rng);
+            assert!(is_permutation(&order, items.len()));
+            for album in order.chunks(4) {
+                let key = album_key(&items[album[0]]);
+                let numbers: Vec<(i32, u32)> = album
+                    .iter()
+                    .map(|index| {
+                        assert_eq!(album_key(&items[*index]), key);
+                        album_position(&items[*index])
+                    })
+                    .collect();
+                assert_eq!(numbers, vec![(1, 1), (1, 2), (1, 3), (1, 4)]);
+            }
+        }
+    }
+
+    #[test]
+    fn album_shuffle_continues_current_album() {
+        let mut items: Vec<Playable> = (1..=4).map(|i| track("a", "x", i)).collect();
+        items.extend((1..=4).map(|i| track("a", "y", i)));
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // track 2 of album x is playing
+        let rest: Vec<usize> = (0..items.len()).filter(|i| *i != 1).collect();
+        let order = album_shuffle(&items, Some(1), rest, &mut rng);
+        assert_eq!(&order[..2], &[2, 3]);
+        assert!(order.contains(&0));
+        assert_eq!(order.len(), items.len() - 1);
+    }
+
+    #[test]
+    fn weighted_shuffle_favors_heavy_items() {
+        let mut weights = vec![1.0; 10];
+        weights[3] = 10.0;
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let mut first = 0;
+        for _ in 0..1000 {
+            let order = weighted_shuffle((0..10).collect(), &weights, &mut rng);
+            assert!(is_permutation(&order, 10));
+            if order[0] == 3 {
+                first += 1;
+            }
         }
-        Err(e) => log::error!("Failed to send notification cover: {e}"),
+        // The expected share is 10 / 19, compared to 1 / 10 for an unweighted shuffle.
+        assert!(first > 400, "heavy item was first {first} times");
     }
 }
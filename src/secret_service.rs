@@ -0,0 +1,446 @@
+//! A minimal client of the freedesktop Secret Service API, which is implemented by desktop keyrings
+//! like GNOME Keyring and KWallet, to store credentials outside of plain files.
+//!
+//! <https://specifications.freedesktop.org/secret-service-spec/latest/>
+
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{Connection, proxy};
+
+/// The object path that the API uses for "no object", e.g. when no prompt is necessary.
+const NONE: &str = "/";
+
+const LABEL_PROPERTY: &str = "org.freedesktop.Secret.Item.Label";
+const ATTRIBUTES_PROPERTY: &str = "org.freedesktop.Secret.Item.Attributes";
+
+/// A secret as it's transferred: the session, the encryption parameters, the value and its content
+/// type.
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Service",
+    default_service = "org.freedesktop.secrets",
+    default_path = "/org/freedesktop/secrets",
+    gen_blocking = false
+)]
+trait Service {
+    fn open_session(
+        &self,
+        algorithm: &str,
+        input: &Value<'_>,
+    ) -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+
+    fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+
+    fn unlock(
+        &self,
+        objects: &[ObjectPath<'_>],
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+
+    fn read_alias(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Collection",
+    default_service = "org.freedesktop.secrets",
+    gen_blocking = false
+)]
+trait Collection {
+    fn create_item(
+        &self,
+        properties: HashMap<&str, Value<'_>>,
+        secret: &Secret,
+        replace: bool,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Item",
+    default_service = "org.freedesktop.secrets",
+    gen_blocking = false
+)]
+trait Item {
+    fn get_secret(&self, session: &ObjectPath<'_>) -> zbus::Result<Secret>;
+
+    fn delete(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Prompt",
+    default_service = "org.freedesktop.secrets",
+    gen_blocking = false
+)]
+trait Prompt {
+    fn prompt(&self, window_id: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: OwnedValue) -> zbus::Result<()>;
+}
+
+/// A session with the Secret Service. Secrets are transferred without encryption, which is fine as
+/// the session bus is only accessible by the user.
+pub struct SecretService {
+    connection: Connection,
+    session: OwnedObjectPath,
+}
+
+impl SecretService {
+    /// Open a session with the Secret Service on the bus of `connection`.
+    pub async fn connect(connection: Connection) -> zbus::Result<Self> {
+        let service = ServiceProxy::new(&connection).await?;
+        let (_, session) = service.open_session("plain", &Value::from("")).await?;
+        Ok(Self {
+            connection,
+            session,
+        })
+    }
+
+    /// Let the user confirm the action of the prompt at `path`, e.g. by entering the password of the
+    /// keyring. Returns whether the prompt was confirmed.
+    async fn prompt(&self, path: OwnedObjectPath) -> zbus::Result<bool> {
+        if path.as_str() == NONE {
+            return Ok(true);
+        }
+
+        let prompt = PromptProxy::builder(&self.connection)
+            .path(path)?
+            .build()
+            .await?;
+        let mut completed = prompt.receive_completed().await?;
+        prompt.prompt("").await?;
+        let signal = completed
+            .next()
+            .await
+            .ok_or_else(|| zbus::Error::Failure("The prompt disappeared".into()))?;
+        Ok(!*signal.args()?.dismissed())
+    }
+
+    /// The items whose attributes contain `attributes`, unlocking them if necessary. Fails if
+    /// unlocking them is dismissed.
+    async fn items(&self, attributes: &HashMap<&str, &str>) -> zbus::Result<Vec<OwnedObjectPath>> {
+        let service = ServiceProxy::new(&self.connection).await?;
+        let (unlocked, locked) = service.search_items(attributes.clone()).await?;
+        if locked.is_empty() {
+            return Ok(unlocked);
+        }
+
+        let locked: Vec<ObjectPath<'_>> = locked.into_iter().map(Into::into).collect();
+        let (_, prompt) = service.unlock(&locked).await?;
+        if !self.prompt(prompt).await? {
+            return Err(zbus::Error::Failure(
+                "Unlocking the keyring was dismissed".into(),
+            ));
+        }
+        Ok(service.search_items(attributes.clone()).await?.0)
+    }
+
+    /// The value of the secret with `attributes`, if there is one.
+    pub async fn lookup(&self, attributes: &HashMap<&str, &str>) -> zbus::Result<Option<Vec<u8>>> {
+        let Some(path) = self.items(attributes).await?.into_iter().next() else {
+            return Ok(None);
+        };
+        let item = ItemProxy::builder(&self.connection)
+            .path(path)?
+            .build()
+            .await?;
+        let (_, _, value, _) = item.get_secret(&self.session).await?;
+        Ok(Some(value))
+    }
+
+    /// Store `value` as the secret with `attributes` in the default collection, replacing the
+    /// previous secret with the same attributes.
+    pub async fn store(
+        &self,
+        label: &str,
+        attributes: &HashMap<&str, &str>,
+        value: &[u8],
+    ) -> zbus::Result<()> {
+        let service = ServiceProxy::new(&self.connection).await?;
+        let collection = service.read_alias("default").await?;
+        if collection.as_str() == NONE {
+            return Err(zbus::Error::Failure("There is no default keyring".into()));
+        }
+
+        let (_, prompt) = service.unlock(&[collection.clone().into()]).await?;
+        if !self.prompt(prompt).await? {
+            return Err(zbus::Error::Failure(
+                "Unlocking the keyring was dismissed".into(),
+            ));
+        }
+
+        let collection = CollectionProxy::builder(&self.connection)
+            .path(collection)?
+            .build()
+            .await?;
+        let properties = HashMap::from([
+            (LABEL_PROPERTY, Value::from(label)),
+            (ATTRIBUTES_PROPERTY, Value::from(attributes.clone())),
+        ]);
+        let secret: Secret = (
+            self.session.clone(),
+            Vec::new(),
+            value.to_vec(),
+            "text/plain".into(),
+        );
+        let (_, prompt) = collection.create_item(properties, &secret, true).await?;
+        if !self.prompt(prompt).await? {
+            return Err(zbus::Error::Failure(
+                "Storing the secret was dismissed".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delete the secrets with `attributes`, returning whether there were any. Fails if deleting
+    /// them is dismissed, as they're kept then.
+    pub async fn delete(&self, attributes: &HashMap<&str, &str>) -> zbus::Result<bool> {
+        let items = self.items(attributes).await?;
+        for path in &items {
+            let item = ItemProxy::builder(&self.connection)
+                .path(path.clone())?
+                .build()
+                .await?;
+            let prompt = item.delete().await?;
+            if !self.prompt(prompt).await? {
+                return Err(zbus::Error::Failure(
+                    "Deleting the secret was dismissed".into(),
+                ));
+            }
+        }
+        Ok(!items.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use zbus::object_server::SignalEmitter;
+    use zbus::{ObjectServer, connection, interface};
+
+    use super::*;
+    use crate::private_bus::PrivateBus;
+
+    const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/login";
+    const PROMPT_PATH: &str = "/org/freedesktop/secrets/prompt/dismissed";
+
+    /// The items of the mock, by their object paths.
+    type Items = Arc<Mutex<BTreeMap<String, (HashMap<String, String>, Vec<u8>)>>>;
+
+    fn matching(items: &Items, attributes: &HashMap<String, String>) -> Vec<OwnedObjectPath> {
+        items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (item_attributes, _))| {
+                attributes
+                    .iter()
+                    .all(|(key, value)| item_attributes.get(key) == Some(value))
+            })
+            .map(|(path, _)| OwnedObjectPath::try_from(path.clone()).unwrap())
+            .collect()
+    }
+
+    fn no_object() -> OwnedObjectPath {
+        OwnedObjectPath::try_from(NONE).unwrap()
+    }
+
+    struct MockService {
+        items: Items,
+    }
+
+    #[interface(name = "org.freedesktop.Secret.Service")]
+    impl MockService {
+        fn open_session(
+            &self,
+            _algorithm: &str,
+            _input: Value<'_>,
+        ) -> (OwnedValue, OwnedObjectPath) {
+            let output = OwnedValue::try_from(Value::from("")).unwrap();
+            let session = OwnedObjectPath::try_from("/org/freedesktop/secrets/session/1").unwrap();
+            (output, session)
+        }
+
+        fn search_items(
+            &self,
+            attributes: HashMap<String, String>,
+        ) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+            (matching(&self.items, &attributes), Vec::new())
+        }
+
+        fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+            (objects, no_object())
+        }
+
+        fn read_alias(&self, _name: &str) -> OwnedObjectPath {
+            OwnedObjectPath::try_from(COLLECTION_PATH).unwrap()
+        }
+    }
+
+    struct MockCollection {
+        items: Items,
+        created: usize,
+        /// Whether the user dismisses deleting the items.
+        keep: Arc<AtomicBool>,
+    }
+
+    #[interface(name = "org.freedesktop.Secret.Collection")]
+    impl MockCollection {
+        async fn create_item(
+            &mut self,
+            properties: HashMap<String, OwnedValue>,
+            secret: Secret,
+            replace: bool,
+            #[zbus(object_server)] server: &ObjectServer,
+        ) -> zbus::fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+            let attributes = properties
+                .get(ATTRIBUTES_PROPERTY)
+                .cloned()
+                .and_then(|value| HashMap::<String, String>::try_from(value).ok())
+                .ok_or_else(|| zbus::fdo::Error::InvalidArgs("No attributes".into()))?;
+            if replace {
+                for path in matching(&self.items, &attributes) {
+                    self.items.lock().unwrap().remove(path.as_str());
+                }
+            }
+
+            self.created += 1;
+            let path = format!("{COLLECTION_PATH}/{}", self.created);
+            self.items
+                .lock()
+                .unwrap()
+                .insert(path.clone(), (attributes, secret.2));
+            let item = MockItem {
+                path: path.clone(),
+                items: self.items.clone(),
+                keep: self.keep.clone(),
+            };
+            server.at(path.as_str(), item).await?;
+            Ok((OwnedObjectPath::try_from(path).unwrap(), no_object()))
+        }
+    }
+
+    struct MockItem {
+        path: String,
+        items: Items,
+        keep: Arc<AtomicBool>,
+    }
+
+    #[interface(name = "org.freedesktop.Secret.Item")]
+    impl MockItem {
+        fn get_secret(&self, session: OwnedObjectPath) -> zbus::fdo::Result<Secret> {
+            let items = self.items.lock().unwrap();
+            let (_, value) = items
+                .get(&self.path)
+                .ok_or_else(|| zbus::fdo::Error::UnknownObject(self.path.clone()))?;
+            Ok((session, Vec::new(), value.clone(), "text/plain".into()))
+        }
+
+        fn delete(&self) -> OwnedObjectPath {
+            if self.keep.load(Ordering::Relaxed) {
+                return OwnedObjectPath::try_from(PROMPT_PATH).unwrap();
+            }
+            self.items.lock().unwrap().remove(&self.path);
+            no_object()
+        }
+    }
+
+    /// A prompt that the user dismisses.
+    struct DismissedPrompt;
+
+    #[interface(name = "org.freedesktop.Secret.Prompt")]
+    impl DismissedPrompt {
+        async fn prompt(
+            &self,
+            _window_id: &str,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> zbus::fdo::Result<()> {
+            Self::completed(&emitter, true, Value::from("")).await?;
+            Ok(())
+        }
+
+        #[zbus(signal)]
+        async fn completed(
+            emitter: &SignalEmitter<'_>,
+            dismissed: bool,
+            result: Value<'_>,
+        ) -> zbus::Result<()>;
+    }
+
+    #[test]
+    fn stores_looks_up_and_deletes_secrets() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon isn't available, skipping the Secret Service test");
+            return;
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let items = Items::default();
+            let keep = Arc::new(AtomicBool::new(false));
+            let _server = connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .name("org.freedesktop.secrets")
+                .unwrap()
+                .serve_at(
+                    "/org/freedesktop/secrets",
+                    MockService {
+                        items: items.clone(),
+                    },
+                )
+                .unwrap()
+                .serve_at(
+                    COLLECTION_PATH,
+                    MockCollection {
+                        items,
+                        created: 0,
+                        keep: keep.clone(),
+                    },
+                )
+                .unwrap()
+                .serve_at(PROMPT_PATH, DismissedPrompt)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+
+            let connection = connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let service = SecretService::connect(connection).await.unwrap();
+            let attributes = HashMap::from([("application", "ncspot"), ("secret", "token")]);
+
+            assert_eq!(service.lookup(&attributes).await.unwrap(), None);
+            service.store("token", &attributes, b"first").await.unwrap();
+            service
+                .store("token", &attributes, b"second")
+                .await
+                .unwrap();
+            assert_eq!(
+                service.lookup(&attributes).await.unwrap(),
+                Some(b"second".to_vec())
+            );
+
+            keep.store(true, Ordering::Relaxed);
+            assert!(service.delete(&attributes).await.is_err());
+            assert!(service.lookup(&attributes).await.unwrap().is_some());
+
+            keep.store(false, Ordering::Relaxed);
+            assert!(service.delete(&attributes).await.unwrap());
+            assert_eq!(service.lookup(&attributes).await.unwrap(), None);
+            assert!(!service.delete(&attributes).await.unwrap());
+        });
+    }
+}
@@ -5,6 +5,7 @@ use std::time::{Duration, SystemTime};
 use std::{env, fmt};
 
 use futures::channel::oneshot;
+use librespot_connect::{ConnectConfig, Spirc};
 use librespot_core::authentication::Credentials;
 use librespot_core::cache::Cache;
 use librespot_core::config::SessionConfig;
@@ -62,6 +63,11 @@ pub struct Spotify {
     since: Arc<RwLock<Option<SystemTime>>>,
     /// Channel to send commands to the worker thread.
     channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
+    /// Whether playback is currently controlled by a remote Spotify Connect client.
+    remote: Arc<RwLock<bool>>,
+    /// Whether the last attempt to connect to Spotify failed, so that only the cached library is
+    /// available.
+    offline: Arc<RwLock<bool>>,
 }
 
 impl Spotify {
@@ -77,23 +83,33 @@ impl Spotify {
             credentials,
             cfg: cfg.clone(),
             status: Arc::new(RwLock::new(PlayerEvent::Stopped)),
-            api: WebApi::new(),
+            api: WebApi::new(cfg.profile().clone()),
             elapsed: Arc::new(RwLock::new(None)),
             since: Arc::new(RwLock::new(None)),
             channel: Arc::new(RwLock::new(None)),
+            remote: Arc::new(RwLock::new(false)),
+            offline: Arc::new(RwLock::new(false)),
         };
 
         let (user_tx, user_rx) = oneshot::channel();
         spotify.start_worker(Some(user_tx))?;
-        let user = ASYNC_RUNTIME.get().unwrap().block_on(user_rx).ok();
+        // When offline, the username of the stored credentials is used.
+        let user = ASYNC_RUNTIME
+            .get()
+            .unwrap()
+            .block_on(user_rx)
+            .ok()
+            .or_else(|| spotify.credentials.username.clone());
         let volume = cfg.state().volume;
         spotify.set_volume(volume, true);
 
         spotify.api.set_worker_channel(spotify.channel.clone());
-        spotify
-            .api
-            .update_token()
-            .map(move |h| ASYNC_RUNTIME.get().unwrap().block_on(h).ok());
+        if !spotify.is_offline() {
+            spotify
+                .api
+                .update_token()
+                .map(move |h| ASYNC_RUNTIME.get().unwrap().block_on(h).ok());
+        }
 
         spotify.api.set_user(user);
 
@@ -122,6 +138,9 @@ impl Spotify {
         let (tx, rx) = mpsc::unbounded_channel();
         *self.channel.write().unwrap() = Some(tx);
         let worker_channel = self.channel.clone();
+        let remote = self.remote.clone();
+        *remote.write().unwrap() = false;
+        let offline = self.offline.clone();
         let cfg = self.cfg.clone();
         let events = self.events.clone();
         let volume = self.volume();
@@ -137,6 +156,8 @@ impl Spotify {
             user_tx,
             volume,
             backend,
+            remote,
+            offline,
         ));
         Ok(())
     }
@@ -160,18 +181,31 @@ impl Spotify {
         session_config
     }
 
+    /// Log in with `credentials` to check that they work. On success, reusable credentials are
+    /// stored in the cache of the selected profile, so that no login is needed on the next start.
     pub fn test_credentials(
         cfg: &config::Config,
         credentials: Credentials,
     ) -> Result<Session, librespot_core::Error> {
         let config = Self::session_config(cfg);
+        let cache = Cache::new(
+            Some(cfg.profile().cache_path("librespot")),
+            None,
+            None,
+            None,
+        )
+        .inspect_err(|e| error!("Could not open the credentials cache: {e}"))
+        .ok();
         let _guard = ASYNC_RUNTIME.get().unwrap().enter();
-        let session = Session::new(config, None);
+        let session = Session::new(config, cache);
         ASYNC_RUNTIME
             .get()
             .unwrap()
             .block_on(session.connect(credentials, true))
-            .map(|_| session)
+            .map(|_| {
+                crate::authentication::secure_credentials(cfg.profile());
+                session
+            })
     }
 
     /// Create a [Session] that respects the user configuration in `cfg` and with the given
@@ -180,10 +214,10 @@ impl Spotify {
         cfg: &config::Config,
         credentials: Credentials,
     ) -> Result<Session, librespot_core::Error> {
-        let librespot_cache_path = config::cache_path("librespot");
+        let librespot_cache_path = cfg.profile().cache_path("librespot");
         let audio_cache_path = match cfg.values().audio_cache {
             Some(false) => None,
-            _ => Some(librespot_cache_path.join("files")),
+            _ => Some(crate::cache::audio_cache_path()),
         };
         let cache = Cache::new(
             Some(librespot_cache_path.clone()),
@@ -197,7 +231,8 @@ impl Spotify {
         debug!("opening spotify session");
         let session_config = Self::session_config(cfg);
         let session = Session::new(session_config, Some(cache));
-        session.connect(credentials, true).await.map(|_| session)
+        // The credentials were already stored when they were tested.
+        session.connect(credentials, false).await.map(|_| session)
     }
 
     /// Create and initialize the requested audio backend.
@@ -249,25 +284,48 @@ impl Spotify {
         user_tx: Option<oneshot::Sender<String>>,
         volume: u16,
         backend: SinkBuilder,
+        remote: Arc<RwLock<bool>>,
+        offline: Arc<RwLock<bool>>,
     ) {
-        let bitrate_str = cfg.values().bitrate.unwrap_or(320).to_string();
+        let bitrate_str = cfg
+            .values()
+            .bitrate
+            .unwrap_or(config::DEFAULT_BITRATE)
+            .to_string();
         let bitrate = Bitrate::from_str(&bitrate_str);
         if bitrate.is_err() {
             error!("invalid bitrate, will use 320 instead")
         }
 
         let player_config = PlayerConfig {
-            gapless: cfg.values().gapless.unwrap_or(true),
+            gapless: cfg.values().gapless.unwrap_or(config::DEFAULT_GAPLESS),
             bitrate: bitrate.unwrap_or(Bitrate::Bitrate320),
-            normalisation: cfg.values().volnorm.unwrap_or(false),
-            normalisation_pregain_db: cfg.values().volnorm_pregain.unwrap_or(0.0),
+            normalisation: cfg.values().volnorm.unwrap_or(config::DEFAULT_VOLNORM),
+            normalisation_pregain_db: cfg
+                .values()
+                .volnorm_pregain
+                .unwrap_or(config::DEFAULT_VOLNORM_PREGAIN),
             ..Default::default()
         };
 
-        let session = Self::create_session(&cfg, credentials)
-            .await
-            .expect("Could not create session");
+        let session = match Self::create_session(&cfg, credentials.clone()).await {
+            Ok(session) => session,
+            Err(e) => {
+                error!("Could not connect to Spotify, continuing offline: {e}");
+                *offline.write().unwrap() = true;
+                // Commands are dropped until the worker is started again with a reconnect.
+                *worker_channel.write().unwrap() = None;
+                events.send(Event::CommandResult(Err(format!(
+                    "Could not connect to Spotify, continuing offline: {e}"
+                ))));
+                return;
+            }
+        };
         user_tx.map(|tx| tx.send(session.username()));
+        if std::mem::replace(&mut *offline.write().unwrap(), false) {
+            info!("Connected to Spotify again");
+            events.send(Event::Reconnected);
+        }
 
         let mixer_factory_opt = librespot_playback::mixer::find(Some(SoftMixer::NAME));
         let factory = mixer_factory_opt.expect("could not find softvol mixer factory");
@@ -275,6 +333,21 @@ impl Spotify {
 
         mixer.set_volume(volume);
 
+        // The config is moved into the sink builder below.
+        let connect_config = cfg
+            .values()
+            .connect
+            .unwrap_or(false)
+            .then(|| ConnectConfig {
+                name: cfg
+                    .values()
+                    .connect_name
+                    .clone()
+                    .unwrap_or_else(|| "ncspot".to_string()),
+                initial_volume: volume,
+                ..Default::default()
+            });
+
         let audio_format: librespot_playback::config::AudioFormat = Default::default();
         let player = Player::new(
             player_config,
@@ -284,6 +357,30 @@ impl Spotify {
         );
         let player_events = player.get_player_event_channel();
 
+        let spirc = if let Some(connect_config) = connect_config {
+            match Spirc::new(
+                connect_config,
+                session.clone(),
+                credentials,
+                player.clone(),
+                mixer.clone(),
+            )
+            .await
+            {
+                Ok((spirc, spirc_task)) => {
+                    info!("registered as spotify connect device");
+                    tokio::spawn(spirc_task);
+                    Some(spirc)
+                }
+                Err(e) => {
+                    error!("could not register as spotify connect device: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         let mut worker = Worker::new(
             events.clone(),
             player_events,
@@ -291,6 +388,8 @@ impl Spotify {
             session,
             player,
             mixer,
+            spirc,
+            remote,
         );
         debug!("worker thread ready.");
         worker.run_loop().await;
@@ -399,12 +498,35 @@ impl Spotify {
         self.set_since(None);
     }
 
+    /// Send `event` to the main event loop.
+    pub fn send_event(&self, event: Event) {
+        self.events.send(event);
+    }
+
     /// Start playback of the [Player].
     pub fn play(&self) {
         info!("play()");
         self.send_worker(WorkerCommand::Play);
     }
 
+    /// Whether playback is currently controlled by a remote Spotify Connect client. The [Queue]
+    /// hands track transitions to the remote client while this is the case.
+    ///
+    /// [Queue]: crate::queue::Queue
+    pub fn is_remote(&self) -> bool {
+        *self.remote.read().unwrap()
+    }
+
+    /// Skip to the next item of the context played by a remote Spotify Connect client.
+    pub fn next(&self) {
+        self.send_worker(WorkerCommand::Next);
+    }
+
+    /// Skip to the previous item of the context played by a remote Spotify Connect client.
+    pub fn previous(&self) {
+        self.send_worker(WorkerCommand::Previous);
+    }
+
     /// Toggle playback (play/pause) of the [Player].
     pub fn toggleplayback(&self) {
         match self.get_current_status() {
@@ -486,6 +608,18 @@ impl Spotify {
         self.send_mpris(command);
     }
 
+    /// Send a TrackListReplaced signal on the Mpris interface after the queue changed.
+    #[cfg(feature = "mpris")]
+    pub fn notify_tracklist_changed(&self) {
+        self.send_mpris(MprisCommand::EmitTrackListStatus);
+    }
+
+    /// Send a PlaylistChanged signal on the Mpris interface for the library playlist with `id`.
+    #[cfg(feature = "mpris")]
+    pub fn notify_playlist_changed(&self, id: &str) {
+        self.send_mpris(MprisCommand::EmitPlaylistChanged(id.to_string()));
+    }
+
     /// Set the current volume of the [Player]. If `notify` is true, also notify MPRIS clients about
     /// the update.
     pub fn set_volume(&self, volume: u16, notify: bool) {
@@ -506,9 +640,22 @@ impl Spotify {
         self.send_worker(WorkerCommand::Preload(track.clone()));
     }
 
-    /// Shut down the worker thread.
+    /// Whether Spotify couldn't be reached the last time the worker thread was started, or since.
+    pub fn is_offline(&self) -> bool {
+        *self.offline.read().unwrap()
+    }
+
+    /// Continue offline after Spotify couldn't be reached, until a reconnect succeeds.
+    pub fn set_offline(&self) {
+        *self.offline.write().unwrap() = true;
+        self.events.trigger();
+    }
+
+    /// Shut down the worker thread. As the shutdown is intentional, the worker isn't restarted.
     pub fn shutdown(&self) {
-        self.send_worker(WorkerCommand::Shutdown);
+        if let Some(tx) = self.channel.write().unwrap().take() {
+            let _ = tx.send(WorkerCommand::Shutdown);
+        }
     }
 
     #[cfg(feature = "mpris")]
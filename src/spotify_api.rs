@@ -7,15 +7,17 @@ use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use log::{debug, error, info};
 use rspotify::http::HttpError;
 use rspotify::model::{
-    AlbumId, AlbumType, ArtistId, CursorBasedPage, EpisodeId, FullAlbum, FullArtist, FullEpisode,
-    FullPlaylist, FullShow, FullTrack, ItemPositions, Market, Page, PlayableId, PlaylistId,
-    PlaylistResult, PrivateUser, Recommendations, SavedAlbum, SavedTrack, SearchResult, SearchType,
-    Show, ShowId, SimplifiedTrack, TrackId, UserId,
+    AdditionalType, AlbumId, AlbumType, ArtistId, CurrentPlaybackContext, CursorBasedPage, Device,
+    EpisodeId, FullAlbum, FullArtist, FullEpisode, FullPlaylist, FullShow, FullTrack,
+    ItemPositions, Market, Page, PlayableId, PlaylistId, PlaylistResult, PrivateUser,
+    Recommendations, SavedAlbum, SavedTrack, SearchResult, SearchType, Show, ShowId,
+    SimplifiedTrack, TrackId, UserId,
 };
 use rspotify::{AuthCodeSpotify, ClientError, ClientResult, Config, prelude::*};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
+use crate::config::Profile;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::category::Category;
@@ -37,10 +39,14 @@ pub struct WebApi {
     worker_channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
     /// Time at which the token expires.
     token_expiration: Arc<RwLock<DateTime<Utc>>>,
+    /// The profile whose token is used.
+    profile: Profile,
+    /// Whether the last request failed because Spotify couldn't be reached.
+    unreachable: Arc<RwLock<bool>>,
 }
 
-impl Default for WebApi {
-    fn default() -> Self {
+impl WebApi {
+    pub fn new(profile: Profile) -> Self {
         let config = Config {
             token_refreshing: false,
             ..Default::default()
@@ -55,14 +61,10 @@ impl Default for WebApi {
             user: None,
             worker_channel: Arc::new(RwLock::new(None)),
             token_expiration: Arc::new(RwLock::new(Utc::now())),
+            profile,
+            unreachable: Arc::new(RwLock::new(false)),
         }
     }
-}
-
-impl WebApi {
-    pub fn new() -> Self {
-        Self::default()
-    }
 
     /// Set the username for use with the API.
     pub fn set_user(&mut self, user: Option<String>) {
@@ -95,8 +97,9 @@ impl WebApi {
 
         let api_token = self.api.token.clone();
         let api_token_expiration = self.token_expiration.clone();
+        let profile = self.profile.clone();
         Some(ASYNC_RUNTIME.get().unwrap().spawn_blocking(move || {
-            match crate::authentication::get_rspotify_token() {
+            match crate::authentication::refresh_rspotify_token(&profile) {
                 Ok(token) => {
                     let expires_at = token
                         .expires_at
@@ -117,6 +120,10 @@ impl WebApi {
         F: Fn(&AuthCodeSpotify) -> ClientResult<R>,
     {
         let result = { api_call(&self.api) };
+        *self.unreachable.write().unwrap() = matches!(
+            &result,
+            Err(ClientError::Http(error)) if !matches!(**error, HttpError::StatusCode(_))
+        );
         match result {
             Ok(v) => Some(v),
             Err(ClientError::Http(error)) => {
@@ -151,6 +158,12 @@ impl WebApi {
         }
     }
 
+    /// Whether the last request failed because Spotify couldn't be reached, rather than being
+    /// rejected.
+    pub fn is_unreachable(&self) -> bool {
+        *self.unreachable.read().unwrap()
+    }
+
     /// Append `tracks` at `position` in the playlist with `playlist_id`.
     pub fn append_tracks(
         &self,
@@ -736,4 +749,52 @@ impl WebApi {
     pub fn current_user(&self) -> Result<PrivateUser, ()> {
         self.api_with_retry(|api| api.current_user()).ok_or(())
     }
+
+    /// Get the Spotify Connect devices of the logged in user.
+    pub fn devices(&self) -> Result<Vec<Device>, ()> {
+        self.api_with_retry(|api| api.device()).ok_or(())
+    }
+
+    /// Get the playback state of the currently active Spotify Connect device, if there is one.
+    pub fn current_playback(&self) -> Result<Option<CurrentPlaybackContext>, ()> {
+        self.api_with_retry(|api| {
+            api.current_playback(
+                Some(Market::FromToken),
+                Some(&[AdditionalType::Track, AdditionalType::Episode]),
+            )
+        })
+        .ok_or(())
+    }
+
+    /// Transfer the current playback to the device with the given `device_id`.
+    pub fn transfer_playback(&self, device_id: &str, play: bool) -> Result<(), ()> {
+        self.api_with_retry(|api| api.transfer_playback(device_id, Some(play)))
+            .ok_or(())
+    }
+
+    /// Start playing `playable` at `position_ms` on the device with the given `device_id`.
+    pub fn start_playback(
+        &self,
+        device_id: &str,
+        playable: &Playable,
+        position_ms: u32,
+    ) -> Result<(), ()> {
+        let id: Option<PlayableId> = playable.into();
+        let id = id.ok_or(())?;
+        self.api_with_retry(|api| {
+            api.start_uris_playback(
+                [id.clone()],
+                Some(device_id),
+                None,
+                Some(ChronoDuration::milliseconds(position_ms.into())),
+            )
+        })
+        .ok_or(())
+    }
+
+    /// Pause playback on the device with the given `device_id`, or the active device if `None`.
+    pub fn pause_playback(&self, device_id: Option<&str>) -> Result<(), ()> {
+        self.api_with_retry(|api| api.pause_playback(device_id))
+            .ok_or(())
+    }
 }
@@ -2,12 +2,13 @@ use crate::events::{Event, EventManager};
 use crate::model::playable::Playable;
 use crate::queue::QueueEvent;
 use crate::spotify::PlayerEvent;
+use librespot_connect::Spirc;
 use librespot_core::SpotifyUri;
 use librespot_core::session::Session;
 use librespot_playback::mixer::Mixer;
 use librespot_playback::player::{Player, PlayerEvent as LibrespotPlayerEvent};
 use log::{debug, error, info, warn};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use std::time::SystemTime;
 use tokio::sync::mpsc;
@@ -24,6 +25,8 @@ pub(crate) enum WorkerCommand {
     Seek(u32),
     SetVolume(u16),
     Preload(Playable),
+    Next,
+    Previous,
     Shutdown,
 }
 
@@ -41,9 +44,16 @@ pub struct Worker {
     player: Arc<Player>,
     player_status: PlayerStatus,
     mixer: Arc<dyn Mixer>,
+    /// Spotify Connect handle, if ncspot is registered as a Connect device.
+    spirc: Option<Spirc>,
+    /// Whether the [Player] is currently controlled by a remote Spotify Connect client.
+    remote: Arc<RwLock<bool>>,
+    /// The item that was last loaded into the [Player], either locally or by a remote client.
+    loaded: Option<SpotifyUri>,
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         events: EventManager,
         player_events: mpsc::UnboundedReceiver<LibrespotPlayerEvent>,
@@ -51,6 +61,8 @@ impl Worker {
         session: Session,
         player: Arc<Player>,
         mixer: Arc<dyn Mixer>,
+        spirc: Option<Spirc>,
+        remote: Arc<RwLock<bool>>,
     ) -> Self {
         Self {
             events,
@@ -60,6 +72,35 @@ impl Worker {
             session,
             player_status: PlayerStatus::Stopped,
             mixer,
+            spirc,
+            remote,
+            loaded: None,
+        }
+    }
+
+    /// Whether playback is currently driven by a remote Spotify Connect client.
+    fn is_remote(&self) -> bool {
+        self.spirc.is_some() && *self.remote.read().unwrap()
+    }
+
+    fn set_remote(&self, remote: bool) {
+        *self.remote.write().unwrap() = remote;
+    }
+
+    /// Run `f` on the Spotify Connect handle if a remote client is in control. Returns false if
+    /// the command should be applied to the local [Player] instead.
+    fn with_spirc<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&Spirc) -> Result<(), librespot_core::Error>,
+    {
+        match self.spirc.as_ref() {
+            Some(spirc) if self.is_remote() => {
+                if let Err(e) = f(spirc) {
+                    error!("spotify connect command failed: {e}");
+                }
+                true
+            }
+            _ => false,
         }
     }
 
@@ -83,6 +124,12 @@ impl Worker {
                                     warn!("track is not playable");
                                     self.events.send(Event::Player(PlayerEvent::FinishedTrack));
                                 } else {
+                                    // Take back playback from a remote Connect client.
+                                    if self.is_remote() {
+                                        self.with_spirc(|spirc| spirc.disconnect(false));
+                                        self.set_remote(false);
+                                    }
+                                    self.loaded = Some(uri.clone());
                                     self.player.load(uri, start_playing, position_ms);
                                 }
                             }
@@ -93,19 +140,29 @@ impl Worker {
                         }
                     }
                     Some(WorkerCommand::Play) => {
-                        self.player.play();
+                        if !self.with_spirc(|spirc| spirc.play()) {
+                            self.player.play();
+                        }
                     }
                     Some(WorkerCommand::Pause) => {
-                        self.player.pause();
+                        if !self.with_spirc(|spirc| spirc.pause()) {
+                            self.player.pause();
+                        }
                     }
                     Some(WorkerCommand::Stop) => {
-                        self.player.stop();
+                        if !self.with_spirc(|spirc| spirc.pause()) {
+                            self.player.stop();
+                        }
                     }
                     Some(WorkerCommand::Seek(pos)) => {
-                        self.player.seek(pos);
+                        if !self.with_spirc(|spirc| spirc.set_position_ms(pos)) {
+                            self.player.seek(pos);
+                        }
                     }
                     Some(WorkerCommand::SetVolume(volume)) => {
-                        self.mixer.set_volume(volume);
+                        if !self.with_spirc(|spirc| spirc.set_volume(volume)) {
+                            self.mixer.set_volume(volume);
+                        }
                     }
                     Some(WorkerCommand::Preload(playable)) => {
                         if let Ok(uri) = SpotifyUri::from_uri(&playable.uri()) {
@@ -113,8 +170,19 @@ impl Worker {
                             self.player.preload(uri);
                         }
                     }
+                    Some(WorkerCommand::Next) => {
+                        self.with_spirc(|spirc| spirc.next());
+                    }
+                    Some(WorkerCommand::Previous) => {
+                        self.with_spirc(|spirc| spirc.prev());
+                    }
                     Some(WorkerCommand::Shutdown) => {
                         debug!("Received shutdown command, stopping player and session");
+                        if let Some(spirc) = self.spirc.as_ref()
+                            && let Err(e) = spirc.shutdown()
+                        {
+                            warn!("could not shut down spotify connect: {e}");
+                        }
                         self.player.stop();
                         self.session.shutdown();
                         // Exit the loop to terminate the worker
@@ -128,9 +196,18 @@ impl Worker {
                 event = self.player_events.next() => match event {
                     Some(LibrespotPlayerEvent::Playing {
                         play_request_id: _,
-                        track_id: _,
+                        track_id,
                         position_ms,
                     }) => {
+                        // Anything we didn't load ourselves was started by a Connect client.
+                        if started_remotely(self.spirc.is_some(), self.loaded.as_ref(), &track_id) {
+                            self.set_remote(true);
+                            if let Ok(uri) = track_id.to_uri() {
+                                info!("remote client started playback of {uri}");
+                                self.events.send(Event::Queue(QueueEvent::RemoteTrack(uri)));
+                            }
+                            self.loaded = Some(track_id);
+                        }
                         let position = Duration::from_millis(position_ms as u64);
                         let playback_start = SystemTime::now() - position;
                         self.events
@@ -148,13 +225,23 @@ impl Worker {
                         self.player_status = PlayerStatus::Paused;
                     }
                     Some(LibrespotPlayerEvent::Stopped { .. }) => {
+                        self.set_remote(false);
                         self.events.send(Event::Player(PlayerEvent::Stopped));
                         self.player_status = PlayerStatus::Stopped;
                     }
-                    Some(LibrespotPlayerEvent::EndOfTrack { .. }) => {
+                    // The Connect client that controlled playback went away, so the queue takes
+                    // over again.
+                    Some(LibrespotPlayerEvent::SessionDisconnected { .. }) if self.is_remote() => {
+                        info!("remote client disconnected");
+                        self.set_remote(false);
+                        self.events.trigger();
+                    }
+                    // Track transitions are handled by Spotify Connect while a remote client is
+                    // in control.
+                    Some(LibrespotPlayerEvent::EndOfTrack { .. }) if !self.is_remote() => {
                         self.events.send(Event::Player(PlayerEvent::FinishedTrack));
                     }
-                    Some(LibrespotPlayerEvent::TimeToPreloadNextTrack { .. }) => {
+                    Some(LibrespotPlayerEvent::TimeToPreloadNextTrack { .. }) if !self.is_remote() => {
                         self.events
                             .send(Event::Queue(QueueEvent::PreloadTrackRequest));
                     }
@@ -199,3 +286,30 @@ impl Drop for Worker {
         self.player.stop();
     }
 }
+
+/// Whether `track_id` started playing without ncspot loading it, which means that a Spotify Connect
+/// client started it. `connect` is whether Spotify Connect is enabled.
+fn started_remotely(connect: bool, loaded: Option<&SpotifyUri>, track_id: &SpotifyUri) -> bool {
+    connect && loaded != Some(track_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(id: &str) -> SpotifyUri {
+        SpotifyUri::from_uri(&format!("spotify:track:{id}")).unwrap()
+    }
+
+    #[test]
+    fn detects_remotely_started_tracks() {
+        let loaded = uri("4uLU6hMCjMI75M1A2tKUQC");
+        let other = uri("6rqhFgbbKwnb9MLmUQDhG6");
+
+        assert!(!started_remotely(true, Some(&loaded), &loaded));
+        assert!(started_remotely(true, Some(&loaded), &other));
+        assert!(started_remotely(true, None, &other));
+        // Without Spotify Connect, everything was loaded by ncspot.
+        assert!(!started_remotely(false, Some(&loaded), &other));
+    }
+}
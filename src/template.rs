@@ -0,0 +1,338 @@
+//! The template language used for track, notification and statusbar formats.
+//!
+//! - `%name` is replaced with the value of the placeholder `name`. Some placeholders take an
+//!   argument in brackets, like `%added_at[%d.%m.%Y]`.
+//! - `%20name` pads the value to 20 columns by adding spaces in front of it, `%-20name` adds
+//!   them after it and `%.20name` truncates it to at most 20 columns.
+//! - `{%album| - %album}` shows ` - %album` only if `%album` isn't empty. An optional third part
+//!   is shown otherwise, like in `{%album|%album|No album}`.
+//! - `{%repeat }` is only shown if all placeholders inside of it have a non-empty value.
+//! - `\` escapes the following character and `%%` is a literal `%`.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The placeholders that take an argument in brackets. A `[` after any other placeholder is text.
+const ARGUMENT_PLACEHOLDERS: [&str; 1] = ["added_at"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Placeholder(Placeholder),
+    /// A conditional group. Contains the parts between the `|` separators.
+    Group(Vec<Vec<Node>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    name: String,
+    argument: Option<String>,
+    /// Whether padding is added after the value instead of in front of it.
+    left_align: bool,
+    width: Option<usize>,
+    max_width: Option<usize>,
+    /// The placeholder as written in the template, which is shown if it is unknown.
+    source: String,
+}
+
+impl Placeholder {
+    /// Apply the width options of the placeholder to `value`.
+    fn apply(&self, value: String) -> String {
+        let value = match self.max_width {
+            Some(max_width) => truncate(&value, max_width),
+            None => value,
+        };
+
+        match self.width {
+            Some(width) if value.width() < width => {
+                let padding = " ".repeat(width - value.width());
+                if self.left_align {
+                    value + &padding
+                } else {
+                    padding + &value
+                }
+            }
+            _ => value,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(template: &str) -> Self {
+        Self {
+            chars: template.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    /// Parse until the end of the template, or the end of the current part if `in_group` is set.
+    fn parse_nodes(&mut self, in_group: bool) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '|' | '}' if in_group => break,
+                '\\' => {
+                    self.pos += 1;
+                    text.push(self.advance().unwrap_or('\\'));
+                }
+                '%' => {
+                    self.pos += 1;
+                    match self.parse_placeholder() {
+                        Some(placeholder) => {
+                            if !text.is_empty() {
+                                nodes.push(Node::Text(std::mem::take(&mut text)));
+                            }
+                            nodes.push(Node::Placeholder(placeholder));
+                        }
+                        None => text.push('%'),
+                    }
+                }
+                '{' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    match self.parse_group() {
+                        Some(parts) => {
+                            if !text.is_empty() {
+                                nodes.push(Node::Text(std::mem::take(&mut text)));
+                            }
+                            nodes.push(Node::Group(parts));
+                        }
+                        None => {
+                            // Unterminated groups are shown as they are.
+                            self.pos = start + 1;
+                            text.push('{');
+                        }
+                    }
+                }
+                c => {
+                    self.pos += 1;
+                    text.push(c);
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        nodes
+    }
+
+    /// Parse the parts of a group after the opening brace, or [None] if it is never closed.
+    fn parse_group(&mut self) -> Option<Vec<Vec<Node>>> {
+        let mut parts = vec![self.parse_nodes(true)];
+        loop {
+            match self.advance()? {
+                '|' => parts.push(self.parse_nodes(true)),
+                _ => return Some(parts),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    /// Parse a placeholder after the `%`, or [None] if the `%` doesn't start one.
+    fn parse_placeholder(&mut self) -> Option<Placeholder> {
+        let start = self.pos - 1;
+        if self.peek() == Some('%') {
+            self.pos += 1;
+            return None;
+        }
+
+        let left_align = self.peek() == Some('-');
+        if left_align {
+            self.pos += 1;
+        }
+        let width = self.parse_number();
+        let max_width = if self.peek() == Some('.') {
+            self.pos += 1;
+            self.parse_number()
+        } else {
+            None
+        };
+
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_lowercase() || *c == '_') {
+            name.push(c);
+            self.pos += 1;
+        }
+        if name.is_empty() {
+            self.pos = start + 1;
+            return None;
+        }
+
+        let takes_argument = ARGUMENT_PLACEHOLDERS.contains(&name.as_str());
+        let argument = if takes_argument && self.peek() == Some('[') {
+            self.chars[self.pos..]
+                .iter()
+                .position(|c| *c == ']')
+                .map(|end| {
+                    let argument = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                    self.pos += end + 1;
+                    argument
+                })
+        } else {
+            None
+        };
+
+        Some(Placeholder {
+            name,
+            argument,
+            left_align,
+            width,
+            max_width,
+            source: self.chars[start..self.pos].iter().collect(),
+        })
+    }
+}
+
+/// Render `nodes`. Also returns whether every placeholder had a non-empty value.
+fn render_nodes(
+    nodes: &[Node],
+    lookup: &dyn Fn(&str, Option<&str>) -> Option<String>,
+) -> (String, bool) {
+    let mut output = String::new();
+    let mut complete = true;
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Placeholder(placeholder) => {
+                match lookup(&placeholder.name, placeholder.argument.as_deref()) {
+                    Some(value) => {
+                        complete &= !value.is_empty();
+                        output.push_str(&placeholder.apply(value));
+                    }
+                    None => output.push_str(&placeholder.source),
+                }
+            }
+            Node::Group(parts) => match parts.as_slice() {
+                [body] => {
+                    let (text, complete) = render_nodes(body, lookup);
+                    if complete {
+                        output.push_str(&text);
+                    }
+                }
+                [condition, then, otherwise @ ..] => {
+                    let (condition, _) = render_nodes(condition, lookup);
+                    let part = if condition.trim().is_empty() {
+                        otherwise.first()
+                    } else {
+                        Some(then)
+                    };
+                    if let Some(part) = part {
+                        output.push_str(&render_nodes(part, lookup).0);
+                    }
+                }
+                [] => {}
+            },
+        }
+    }
+
+    (output, complete)
+}
+
+/// Render `template`, asking `lookup` for the value of every placeholder with its name and
+/// optional argument. Placeholders for which `lookup` returns [None] are kept as they are.
+pub fn render(template: &str, lookup: &dyn Fn(&str, Option<&str>) -> Option<String>) -> String {
+    render_nodes(&Parser::new(template).parse_nodes(false), lookup).0
+}
+
+/// Truncate `text` to at most `max_width` columns, ending it with an ellipsis if it was cut off.
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    let mut width = 0;
+    let mut truncated: String = text
+        .chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width < max_width
+        })
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str, argument: Option<&str>) -> Option<String> {
+        match name {
+            "artist" => Some("Foo".to_string()),
+            "artists" => Some("Foo, Bar".to_string()),
+            "title" => Some("Song".to_string()),
+            "album" => Some(String::new()),
+            "added_at" => Some(format!("date({})", argument.unwrap_or_default())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn placeholders_match_whole_names() {
+        assert_eq!(render("%artists - %artist", &lookup), "Foo, Bar - Foo");
+        assert_eq!(render("%unknown 100%% %", &lookup), "%unknown 100% %");
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(render("%title{%album| - %album}", &lookup), "Song");
+        assert_eq!(
+            render("%title{%artist| by %artist}", &lookup),
+            "Song by Foo"
+        );
+        assert_eq!(render("{%album|%album|none}", &lookup), "none");
+        assert_eq!(render("{%title }{%album }end", &lookup), "Song end");
+        assert_eq!(render("{(%title)", &lookup), "{(Song)");
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(render("[%6title]", &lookup), "[  Song]");
+        assert_eq!(render("[%-6title]", &lookup), "[Song  ]");
+        assert_eq!(render("[%.6artists]", &lookup), "[Foo, …]");
+        assert_eq!(render("[%-4.3artists]", &lookup), "[Fo… ]");
+    }
+
+    #[test]
+    fn arguments_and_escaping() {
+        assert_eq!(render("%added_at[%Y]", &lookup), "date(%Y)");
+        // Brackets after placeholders without arguments are text.
+        assert_eq!(render("%title[%artist]", &lookup), "Song[Foo]");
+        assert_eq!(render("%title[%album]", &lookup), "Song[]");
+        assert_eq!(render("\\%title \\{\\} \\\\", &lookup), "%title {} \\");
+    }
+}
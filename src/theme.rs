@@ -1,12 +1,29 @@
 #![allow(clippy::enum_glob_use)]
 
+use std::sync::Mutex;
+#[cfg(not(target_os = "macos"))]
+use std::sync::OnceLock;
+
+use cursive::Cursive;
 use cursive::theme::BaseColor::*;
 use cursive::theme::Color::*;
 use cursive::theme::PaletteColor::*;
 use cursive::theme::*;
 use log::warn;
 
-use crate::config::{ConfigTheme, ConfigThemeConfig};
+use crate::config::{ConfigTheme, ConfigThemeConfig, config_path};
+use crate::ext_traits::CursiveExt;
+
+/// The directory in the configuration directory that contains the theme files of the user.
+const THEMES_DIRECTORY: &str = "themes";
+
+/// The themes that are bundled with ncspot, by name.
+const PRESETS: [(&str, &str); 4] = [
+    ("gruvbox", include_str!("../misc/themes/gruvbox.toml")),
+    ("nord", include_str!("../misc/themes/nord.toml")),
+    ("solarized", include_str!("../misc/themes/solarized.toml")),
+    ("spotify", include_str!("../misc/themes/spotify.toml")),
+];
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub(crate) enum Appearance {
@@ -14,6 +31,31 @@ pub(crate) enum Appearance {
     Dark,
 }
 
+/// The appearance that themes are selected for, detected on first use and updated by
+/// [set_appearance].
+static APPEARANCE: Mutex<Option<Appearance>> = Mutex::new(None);
+
+/// The appearance of the terminal from its background color, which can only be queried before
+/// the user interface takes over the terminal.
+#[cfg(not(target_os = "macos"))]
+static TERMINAL_APPEARANCE: OnceLock<Option<Appearance>> = OnceLock::new();
+
+/// The current appearance of the system.
+pub(crate) fn appearance() -> Appearance {
+    if let Some(appearance) = *APPEARANCE.lock().unwrap() {
+        return appearance;
+    }
+    // Detect the appearance without holding the lock, as asking the desktop portal may take a
+    // while. An appearance that was set in the meantime is newer.
+    let detected = detect_appearance();
+    *APPEARANCE.lock().unwrap().get_or_insert(detected)
+}
+
+/// Update the current appearance of the system. Returns whether it changed.
+pub(crate) fn set_appearance(appearance: Appearance) -> bool {
+    APPEARANCE.lock().unwrap().replace(appearance) != Some(appearance)
+}
+
 #[cfg(target_os = "macos")]
 pub(crate) fn detect_appearance() -> Appearance {
     use std::process::Command;
@@ -28,15 +70,263 @@ pub(crate) fn detect_appearance() -> Appearance {
     }
 }
 
+/// How long to wait for the desktop portal to report its color scheme.
+#[cfg(all(target_os = "linux", feature = "mpris"))]
+const PORTAL_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Detect the appearance from the preferred color scheme of the XDG desktop portal, falling back
+/// to the background color of the terminal.
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn detect_appearance() -> Appearance {
+    #[cfg(all(target_os = "linux", feature = "mpris"))]
+    {
+        let runtime = crate::application::ASYNC_RUNTIME.get().unwrap();
+        let read = tokio::time::timeout(PORTAL_TIMEOUT, read_color_scheme());
+        match runtime.handle().block_on(read) {
+            Ok(Ok(Some(appearance))) => return appearance,
+            Ok(Ok(None)) => log::debug!("The desktop portal has no color scheme preference"),
+            Ok(Err(e)) => {
+                log::debug!("Could not read the color scheme from the desktop portal: {e}")
+            }
+            Err(_) => log::debug!("The desktop portal didn't report its color scheme in time"),
+        }
+    }
+
+    terminal_appearance()
+}
+
+/// The appearance of the terminal, from `$COLORFGBG` or the background color that
+/// [query_terminal_background] found.
 #[cfg(not(target_os = "macos"))]
-fn detect_appearance() -> Appearance {
-    Appearance::Light
+fn terminal_appearance() -> Appearance {
+    std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| colorfgbg_appearance(&value))
+        .or_else(|| TERMINAL_APPEARANCE.get().copied().flatten())
+        .unwrap_or(Appearance::Light)
 }
 
-fn select_theme(theme_cfg: &ConfigThemeConfig) -> Option<ConfigTheme> {
-    let appearance = detect_appearance();
+/// Ask the terminal for its background color with an OSC 11 query, unless `$COLORFGBG` already
+/// tells it. This has to be called before the user interface is created.
+pub(crate) fn query_terminal_background() {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if std::env::var_os("COLORFGBG").is_none() {
+        TERMINAL_APPEARANCE.get_or_init(|| {
+            query_osc11()
+                .inspect_err(|e| log::debug!("Could not query the terminal background: {e}"))
+                .ok()
+                .flatten()
+        });
+    }
+}
+
+/// Send the OSC 11 query for the background color to the terminal, followed by a primary device
+/// attributes query that all terminals answer, so terminals without OSC 11 support don't cause a
+/// delay.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn query_osc11() -> std::io::Result<Option<Appearance>> {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+
+    let mut tty = std::fs::File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+    let fd = tty.as_raw_fd();
+
+    let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+    if unsafe { libc::tcgetattr(fd, original.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let original = unsafe { original.assume_init() };
+
+    // Read the response without waiting for a newline and without echoing it, and give up after
+    // 100ms without input.
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 1;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let result = tty.write_all(b"\x1b]11;?\x1b\\\x1b[c").and_then(|_| {
+        let mut response = Vec::new();
+        let mut buffer = [0; 64];
+        loop {
+            let read = tty.read(&mut buffer)?;
+            response.extend_from_slice(&buffer[..read]);
+            // The device attributes response comes last and ends with `c`.
+            let attributes = response.windows(3).any(|window| window == b"\x1b[?");
+            if read == 0 || (attributes && response.ends_with(b"c")) {
+                break;
+            }
+        }
+        Ok(background_appearance(&String::from_utf8_lossy(&response)))
+    });
+
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    result
+}
+
+/// The appearance for the response to an OSC 11 query like `\x1b]11;rgb:ffff/ffff/ffff\x1b\\`,
+/// which is dark if the background color is.
+#[cfg(not(target_os = "macos"))]
+fn background_appearance(response: &str) -> Option<Appearance> {
+    let start = response.find("]11;rgb:")? + "]11;rgb:".len();
+    let channels = response[start..]
+        .split(['/', '\x1b', '\x07'])
+        .take(3)
+        .map(|channel| {
+            let max = 16_u32.checked_pow(channel.len() as u32)? - 1;
+            let value = u32::from_str_radix(channel, 16).ok()?;
+            Some(f64::from(value) / f64::from(max))
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    let [r, g, b] = channels[..] else {
+        return None;
+    };
+
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    Some(if luminance < 0.5 {
+        Appearance::Dark
+    } else {
+        Appearance::Light
+    })
+}
+
+/// The appearance for `$COLORFGBG`, which some terminals set to the palette indices of the
+/// foreground and background colors like `15;0` or `15;default;0`.
+#[cfg(not(target_os = "macos"))]
+fn colorfgbg_appearance(value: &str) -> Option<Appearance> {
+    match value.rsplit(';').next()?.parse::<u8>().ok()? {
+        0..=6 | 8 => Some(Appearance::Dark),
+        _ => Some(Appearance::Light),
+    }
+}
+
+/// The connection to the session bus, which is shared by reading and watching the color scheme.
+#[cfg(all(target_os = "linux", feature = "mpris"))]
+async fn session() -> zbus::Result<&'static zbus::Connection> {
+    static SESSION: tokio::sync::OnceCell<zbus::Connection> = tokio::sync::OnceCell::const_new();
+    SESSION.get_or_try_init(zbus::Connection::session).await
+}
+
+#[cfg(all(target_os = "linux", feature = "mpris"))]
+async fn read_color_scheme() -> zbus::Result<Option<Appearance>> {
+    async fn read(connection: &zbus::Connection, method: &str) -> zbus::Result<zbus::Message> {
+        connection
+            .call_method(
+                Some("org.freedesktop.portal.Desktop"),
+                "/org/freedesktop/portal/desktop",
+                Some("org.freedesktop.portal.Settings"),
+                method,
+                &("org.freedesktop.appearance", "color-scheme"),
+            )
+            .await
+    }
+
+    let connection = session().await?;
+    // `ReadOne` isn't available in older versions of the portal.
+    let reply = match read(connection, "ReadOne").await {
+        Ok(reply) => reply,
+        Err(_) => read(connection, "Read").await?,
+    };
+    let value: zbus::zvariant::OwnedValue = reply.body().deserialize()?;
+    Ok(color_scheme_appearance(&value))
+}
+
+/// Call `on_change` with the appearance whenever the color scheme of the XDG desktop portal
+/// changes. The terminal appearance is used when the desktop has no preference anymore.
+#[cfg(all(target_os = "linux", feature = "mpris"))]
+pub(crate) async fn watch_color_scheme(mut on_change: impl FnMut(Appearance)) -> zbus::Result<()> {
+    use tokio_stream::StreamExt;
+
+    let connection = session().await?;
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.portal.Settings")?
+        .member("SettingChanged")?
+        .path("/org/freedesktop/portal/desktop")?
+        .arg(0, "org.freedesktop.appearance")?
+        .arg(1, "color-scheme")?
+        .build();
+    let mut stream = zbus::MessageStream::for_match_rule(rule, connection, None).await?;
+
+    while let Some(message) = stream.next().await {
+        let (_, _, value): (String, String, zbus::zvariant::OwnedValue) =
+            message?.body().deserialize()?;
+        on_change(color_scheme_appearance(&value).unwrap_or_else(terminal_appearance));
+    }
+    Ok(())
+}
+
+/// The portal reports 1 if a dark appearance is preferred, 2 for a light one and 0 if there is no
+/// preference. `Read` wraps the value in another variant.
+#[cfg(all(target_os = "linux", feature = "mpris"))]
+fn color_scheme_appearance(value: &zbus::zvariant::Value<'_>) -> Option<Appearance> {
+    use zbus::zvariant::Value;
+
+    match value {
+        Value::U32(1) => Some(Appearance::Dark),
+        Value::U32(2) => Some(Appearance::Light),
+        Value::Value(inner) => color_scheme_appearance(inner),
+        _ => None,
+    }
+}
+
+/// The names of all available themes, both the ones in the themes directory and the bundled ones.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = PRESETS.iter().map(|(name, _)| name.to_string()).collect();
+    if let Ok(entries) = std::fs::read_dir(config_path(THEMES_DIRECTORY)) {
+        names.extend(entries.flatten().filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(String::from)
+        }));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Load the theme called `name`. A theme file `<name>.toml` in the themes directory takes
+/// precedence over a bundled theme of the same name.
+pub fn load_named(name: &str) -> Result<ConfigThemeConfig, String> {
+    let unknown = || {
+        format!(
+            "Unknown theme \"{name}\", available: {}",
+            names().join(", ")
+        )
+    };
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return Err(unknown());
+    }
 
-    match appearance {
+    let path = config_path(THEMES_DIRECTORY).join(format!("{name}.toml"));
+    let contents = if path.is_file() {
+        std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?
+    } else {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, contents)| contents.to_string())
+            .ok_or_else(unknown)?
+    };
+    toml::from_str(&contents).map_err(|e| format!("Invalid theme \"{name}\": {e}"))
+}
+
+/// Use `theme` for the whole user interface, including the views that cache its colors.
+pub fn apply(s: &mut Cursive, theme: Theme) {
+    s.set_theme(theme.clone());
+    s.on_layout(|_, mut layout| layout.set_theme(theme));
+}
+
+fn select_theme(theme_cfg: &ConfigThemeConfig) -> Option<ConfigTheme> {
+    match appearance() {
         Appearance::Dark => theme_cfg
             .dark
             .clone()
@@ -112,9 +402,19 @@ pub fn load(theme_cfg: &Option<ConfigThemeConfig>) -> Theme {
         "statusbar_progress_bg",
         load_color!(&selected_theme, statusbar_progress_bg, Light(Black)),
     );
+    let statusbar = load_color!(&selected_theme, statusbar, Dark(Yellow));
+    palette.set_color("statusbar", statusbar);
     palette.set_color(
-        "statusbar",
-        load_color!(&selected_theme, statusbar, Dark(Yellow)),
+        "statusbar_left",
+        load_color!(&selected_theme, statusbar_left, statusbar),
+    );
+    palette.set_color(
+        "statusbar_center",
+        load_color!(&selected_theme, statusbar_center, statusbar),
+    );
+    palette.set_color(
+        "statusbar_right",
+        load_color!(&selected_theme, statusbar_right, statusbar),
     );
     palette.set_color(
         "statusbar_bg",
@@ -132,6 +432,10 @@ pub fn load(theme_cfg: &Option<ConfigThemeConfig>) -> Theme {
         "search_match",
         load_color!(&selected_theme, search_match, Light(Red)),
     );
+    palette.set_color(
+        "autoplay",
+        load_color!(&selected_theme, autoplay, Dark(Cyan)),
+    );
 
     Theme {
         shadow: false,
@@ -139,3 +443,56 @@ pub fn load(theme_cfg: &Option<ConfigThemeConfig>) -> Theme {
         borders,
     }
 }
+
+#[cfg(all(test, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_appearance_from_background_color() {
+        assert_eq!(
+            background_appearance("\x1b]11;rgb:0000/0000/0000\x1b\\\x1b[?62;22c"),
+            Some(Appearance::Dark)
+        );
+        assert_eq!(
+            background_appearance("\x1b]11;rgb:fdfd/f6f6/e3e3\x07"),
+            Some(Appearance::Light)
+        );
+        assert_eq!(
+            background_appearance("\x1b]11;rgb:28/28/28\x07"),
+            Some(Appearance::Dark)
+        );
+        assert_eq!(background_appearance("\x1b[?62;22c"), None);
+    }
+
+    #[test]
+    fn detects_appearance_from_colorfgbg() {
+        assert_eq!(colorfgbg_appearance("15;0"), Some(Appearance::Dark));
+        assert_eq!(colorfgbg_appearance("15;default;8"), Some(Appearance::Dark));
+        assert_eq!(colorfgbg_appearance("0;15"), Some(Appearance::Light));
+        assert_eq!(colorfgbg_appearance("default;default"), None);
+    }
+
+    #[test]
+    fn loads_every_preset() {
+        for (name, _) in PRESETS {
+            let theme_cfg = load_named(name).unwrap();
+            for appearance in [Appearance::Light, Appearance::Dark] {
+                set_appearance(appearance);
+                let selected = select_theme(&theme_cfg).unwrap();
+                let colors = toml::Value::try_from(&selected).unwrap();
+                for (member, color) in colors.as_table().unwrap() {
+                    assert!(
+                        Color::parse(color.as_str().unwrap()).is_some(),
+                        "Invalid color {color} for \"{member}\" in preset \"{name}\""
+                    );
+                }
+
+                let theme = load(&Some(theme_cfg.clone()));
+                if let Some(primary) = &selected.primary {
+                    assert_eq!(theme.palette[Primary], Color::parse(primary).unwrap());
+                }
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use cursive::Cursive;
 use cursive::view::{View, ViewWrapper};
 use cursive::views::NamedView;
 
-use crate::command::Command;
+use crate::command::{Command, SortKey};
 use crate::commands::CommandResult;
+use crate::config::{Column, SortingOrder};
+use crate::filter::{Field, Value};
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -19,6 +23,15 @@ pub trait ListItem: Sync + Send + 'static {
         "".to_string()
     }
     fn display_right(&self, library: &Library) -> String;
+    /// The content of `column` for this item, or [None] if the item isn't shown in columns.
+    fn column(&self, _column: Column, _library: &Library) -> Option<String> {
+        None
+    }
+    /// The value of `field` for filtering, or [None] if the item doesn't have it. Whether an
+    /// item is saved is looked up separately.
+    fn filter_value(&self, _field: Field) -> Option<Value> {
+        None
+    }
     fn play(&mut self, queue: &Queue);
     fn play_next(&mut self, queue: &Queue);
     fn queue(&mut self, queue: &Queue);
@@ -62,6 +75,18 @@ pub trait ListItem: Sync + Send + 'static {
     fn as_listitem(&self) -> Box<dyn ListItem>;
 }
 
+/// Items that can be sorted by [SortKey]s.
+pub trait Sortable: Sized {
+    /// Compare this item to `other` by `key`. Keys that don't apply to the item compare as
+    /// equal. `play_counts` maps URIs to the number of times they were played.
+    fn compare(&self, other: &Self, key: &SortKey, play_counts: &HashMap<String, u32>) -> Ordering;
+
+    /// Sort `items` by `order`, keeping the relative order of equal items.
+    fn sort(items: &mut [Self], order: &SortingOrder, play_counts: &HashMap<String, u32>) {
+        items.sort_by(|a, b| order.compare(|key| a.compare(b, key, play_counts)));
+    }
+}
+
 pub trait ViewExt: View {
     fn title(&self) -> String {
         "".into()
@@ -73,6 +98,12 @@ pub trait ViewExt: View {
 
     fn on_leave(&self) {}
 
+    /// Whether the view uses `move left` and `move right` itself, instead of the views around it
+    /// like tabs. The view should ignore them if it can't move any further.
+    fn moves_horizontally(&self) -> bool {
+        false
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, _cmd: &Command) -> Result<CommandResult, String> {
         Ok(CommandResult::Ignored)
     }
@@ -91,6 +122,11 @@ impl<V: ViewExt> ViewExt for NamedView<V> {
         self.with_view(|v| v.on_leave());
     }
 
+    fn moves_horizontally(&self) -> bool {
+        self.with_view(|v| v.moves_horizontally())
+            .unwrap_or_default()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.with_view_mut(move |v| v.on_command(s, cmd)).unwrap()
     }
@@ -177,6 +213,10 @@ impl ViewExt for BoxedViewExt {
         self.boxed_view.on_leave();
     }
 
+    fn moves_horizontally(&self) -> bool {
+        self.boxed_view.moves_horizontally()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.boxed_view.on_command(s, cmd)
     }
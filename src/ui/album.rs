@@ -65,13 +65,19 @@ impl ViewExt for AlbumView {
     }
 
     fn title_sub(&self) -> String {
+        let filter = self.tabs.title_sub();
         if let Some(tracks) = &self.album.tracks {
             let duration_secs: u64 = tracks.iter().map(|t| t.duration as u64 / 1000).sum();
             let duration = std::time::Duration::from_secs(duration_secs);
             let duration_str = crate::utils::format_duration(&duration);
-            format!("{} tracks, {}", tracks.len(), duration_str)
+            let title_sub = format!("{} tracks, {}", tracks.len(), duration_str);
+            if filter.is_empty() {
+                title_sub
+            } else {
+                format!("{title_sub} | {filter}")
+            }
         } else {
-            "".to_string()
+            filter
         }
     }
 
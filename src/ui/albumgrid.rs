@@ -0,0 +1,659 @@
+#[cfg(feature = "cover")]
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "cover")]
+use std::sync::{Mutex, OnceLock, mpsc};
+
+use cursive::event::{Callback, Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::{ColorStyle, ColorType};
+use cursive::traits::View;
+use cursive::{Cursive, Printer, Rect, Vec2};
+#[cfg(feature = "cover")]
+use log::error;
+
+use crate::command::{Command, MoveAmount, MoveMode};
+use crate::commands::CommandResult;
+use crate::ext_traits::CursiveExt;
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::queue::Queue;
+use crate::template;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::contextmenu::ContextMenu;
+#[cfg(feature = "cover")]
+use crate::ui::graphics::{self, HalfBlock};
+use crate::ui::listview::ListView;
+use crate::ui::pagination::Pagination;
+
+/// The width of a tile in cells, which is also the width of its cover.
+const TILE_WIDTH: usize = 18;
+/// The space between two tiles.
+const TILE_GAP: usize = 2;
+/// The height of a cover in cells. Every cell shows two pixels, so covers are about square.
+const COVER_HEIGHT: usize = 9;
+/// The lines below the cover, which show the title and the artists of the album.
+const TEXT_HEIGHT: usize = 2;
+
+/// The number of rendered covers that are kept, which is a lot more than fit on the screen.
+#[cfg(feature = "cover")]
+const MAX_THUMBNAILS: usize = 256;
+/// The number of threads that load covers.
+#[cfg(feature = "cover")]
+const COVER_LOADERS: usize = 4;
+
+/// Covers rendered to half blocks, keyed by their URL. Covers that are still loading or couldn't
+/// be loaded are [None]. Only the [MAX_THUMBNAILS] covers that were requested last are kept.
+#[cfg(feature = "cover")]
+#[derive(Default)]
+struct Thumbnails {
+    covers: HashMap<String, Option<Arc<Vec<Vec<HalfBlock>>>>>,
+    /// The URLs of the covers, in the order they were requested.
+    requested: VecDeque<String>,
+}
+
+#[cfg(feature = "cover")]
+impl Thumbnails {
+    fn get(&self, url: &str) -> Option<Arc<Vec<Vec<HalfBlock>>>> {
+        self.covers.get(url)?.clone()
+    }
+
+    fn contains(&self, url: &str) -> bool {
+        self.covers.contains_key(url)
+    }
+
+    /// Add an entry for the cover at `url` that is still loading, dropping the oldest cover if
+    /// there are too many. Returns whether there was no entry for it yet.
+    fn request(&mut self, url: &str) -> bool {
+        if self.contains(url) {
+            return false;
+        }
+        if self.requested.len() >= MAX_THUMBNAILS
+            && let Some(oldest) = self.requested.pop_front()
+        {
+            self.covers.remove(&oldest);
+        }
+        self.covers.insert(url.to_string(), None);
+        self.requested.push_back(url.to_string());
+        true
+    }
+
+    /// Store the rendered cover at `url`, unless it was dropped while it was loading.
+    fn insert(&mut self, url: &str, half_blocks: Vec<Vec<HalfBlock>>) {
+        if let Some(cover) = self.covers.get_mut(url) {
+            *cover = Some(Arc::new(half_blocks));
+        }
+    }
+}
+
+/// Loads covers into [Thumbnails] in [COVER_LOADERS] threads, which are started when the first
+/// cover is requested and stop when the loader is dropped.
+#[cfg(feature = "cover")]
+struct CoverLoader {
+    thumbnails: Arc<RwLock<Thumbnails>>,
+    library: Arc<Library>,
+    tx: OnceLock<mpsc::Sender<String>>,
+}
+
+#[cfg(feature = "cover")]
+impl CoverLoader {
+    fn new(library: Arc<Library>) -> Self {
+        Self {
+            thumbnails: Default::default(),
+            library,
+            tx: OnceLock::new(),
+        }
+    }
+
+    /// The rendered cover at `url`. Starts loading it if it isn't loading already.
+    fn get(&self, url: &str) -> Option<Arc<Vec<Vec<HalfBlock>>>> {
+        if let Some(half_blocks) = self.thumbnails.read().unwrap().get(url) {
+            return Some(half_blocks);
+        }
+        if self.thumbnails.write().unwrap().request(url)
+            && self.sender().send(url.to_string()).is_err()
+        {
+            error!("Cover loaders stopped");
+        }
+        None
+    }
+
+    fn sender(&self) -> &mpsc::Sender<String> {
+        self.tx.get_or_init(|| {
+            let (tx, rx) = mpsc::channel::<String>();
+            let rx = Arc::new(Mutex::new(rx));
+            for _ in 0..COVER_LOADERS {
+                let rx = rx.clone();
+                let thumbnails = self.thumbnails.clone();
+                let library = self.library.clone();
+                std::thread::spawn(move || {
+                    while let Ok(url) = rx.lock().unwrap().recv() {
+                        // Skip covers that were dropped while they were waiting to be loaded.
+                        if thumbnails.read().unwrap().contains(&url)
+                            && let Some(half_blocks) = load_cover(&url)
+                        {
+                            thumbnails.write().unwrap().insert(&url, half_blocks);
+                            library.trigger_redraw();
+                        }
+                    }
+                });
+            }
+            tx
+        })
+    }
+}
+
+/// Download the cover at `url` to the cover cache if it isn't there yet and render it to half
+/// blocks.
+#[cfg(feature = "cover")]
+fn load_cover(url: &str) -> Option<Vec<Vec<HalfBlock>>> {
+    let path = crate::cache::cover(url)
+        .inspect_err(|e| error!("Failed to download cover: {e}"))
+        .ok()?;
+    let image = image::open(&path)
+        .inspect_err(|e| error!("Failed to decode cover: {e}"))
+        .ok()?;
+    Some(graphics::half_blocks(
+        &image.into_rgb8(),
+        Vec2::new(TILE_WIDTH, COVER_HEIGHT),
+    ))
+}
+
+/// The arrangement of the tiles in a view of `size`, which shows `len` albums from the row
+/// `scroll` on.
+#[derive(Clone, Copy, Debug)]
+struct Grid {
+    size: Vec2,
+    tile_height: usize,
+    scroll: usize,
+    len: usize,
+}
+
+impl Grid {
+    fn columns(&self) -> usize {
+        AlbumGridView::columns(self.size.x)
+    }
+
+    /// The number of rows of tiles that are fully visible.
+    fn visible_rows(&self) -> usize {
+        (self.size.y / self.tile_height).max(1)
+    }
+
+    /// The position in the grid of the tile at `position` in the view.
+    fn tile_at(&self, position: Vec2) -> Option<usize> {
+        let column = position.x / (TILE_WIDTH + TILE_GAP);
+        if column >= self.columns() || position.x % (TILE_WIDTH + TILE_GAP) >= TILE_WIDTH {
+            return None;
+        }
+        let row = self.scroll + position.y / self.tile_height;
+        let index = row * self.columns() + column;
+        (index < self.len).then_some(index)
+    }
+
+    /// Translate a movement from the tile at `selected` to a movement in the list of albums.
+    fn grid_move(
+        &self,
+        selected: usize,
+        mode: &MoveMode,
+        amount: &MoveAmount,
+    ) -> (MoveMode, MoveAmount) {
+        let columns = self.columns();
+        let column = selected % columns;
+        let rows = |amount: &MoveAmount| match amount {
+            MoveAmount::Integer(amount) => *amount,
+            MoveAmount::Float(scale) => ((self.visible_rows() as f32) * scale) as i32,
+            MoveAmount::Extreme => 0,
+        };
+
+        match (mode, amount) {
+            (MoveMode::Up | MoveMode::Down, MoveAmount::Extreme) => {
+                (mode.clone(), MoveAmount::Extreme)
+            }
+            (MoveMode::Up | MoveMode::Down, amount) => (
+                mode.clone(),
+                MoveAmount::Integer(rows(amount).max(1) * columns as i32),
+            ),
+            (MoveMode::Left, MoveAmount::Extreme) => {
+                (MoveMode::Up, MoveAmount::Integer(column as i32))
+            }
+            (MoveMode::Right, MoveAmount::Extreme) => (
+                MoveMode::Down,
+                MoveAmount::Integer((columns - 1 - column) as i32),
+            ),
+            (MoveMode::Left, amount) => (MoveMode::Up, MoveAmount::Integer(rows(amount).max(1))),
+            (MoveMode::Right, amount) => (MoveMode::Down, MoveAmount::Integer(rows(amount).max(1))),
+            (mode, amount) => (mode.clone(), amount.clone()),
+        }
+    }
+}
+
+/// Shows albums as a grid of tiles with their cover, title and artists. Covers are only shown if
+/// ncspot is built with the `cover` feature and the terminal supports true colors, otherwise the
+/// tiles only contain text.
+///
+/// The albums are kept in a [ListView], which handles everything but drawing and navigating in two
+/// dimensions, like sorting, filtering and pagination.
+pub struct AlbumGridView {
+    list: ListView<Album>,
+    content: Arc<RwLock<Vec<Album>>>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    /// The first row of tiles that is shown.
+    scroll: usize,
+    last_size: Vec2,
+    #[cfg(feature = "cover")]
+    covers: Option<CoverLoader>,
+}
+
+impl AlbumGridView {
+    pub fn new(content: Arc<RwLock<Vec<Album>>>, queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        Self {
+            list: ListView::new(content.clone(), queue.clone(), library.clone()),
+            content,
+            queue,
+            library,
+            scroll: 0,
+            last_size: Vec2::zero(),
+            #[cfg(feature = "cover")]
+            covers: graphics::supports_true_color().then(|| CoverLoader::new(library.clone())),
+        }
+    }
+
+    /// Remember the order of the albums by the name of the view, see
+    /// [ListView::with_remembered_order].
+    pub fn with_remembered_order(mut self, view: &str) -> Self {
+        self.list = self.list.with_remembered_order(view);
+        self
+    }
+
+    pub fn get_pagination(&self) -> &Pagination<Album> {
+        self.list.get_pagination()
+    }
+
+    #[cfg(feature = "cover")]
+    fn has_covers(&self) -> bool {
+        self.covers.is_some()
+    }
+
+    #[cfg(not(feature = "cover"))]
+    fn has_covers(&self) -> bool {
+        false
+    }
+
+    fn tile_height(&self) -> usize {
+        if self.has_covers() {
+            COVER_HEIGHT + TEXT_HEIGHT + 1
+        } else {
+            TEXT_HEIGHT + 1
+        }
+    }
+
+    /// The number of tiles in a row of a grid that is `width` cells wide.
+    fn columns(width: usize) -> usize {
+        ((width + TILE_GAP) / (TILE_WIDTH + TILE_GAP)).max(1)
+    }
+
+    fn grid(&self) -> Grid {
+        Grid {
+            size: self.last_size,
+            tile_height: self.tile_height(),
+            scroll: self.scroll,
+            len: self.list.content_len(false),
+        }
+    }
+
+    /// The position of the selected album in the grid, which is its row in the list.
+    fn selected(&self) -> usize {
+        self.list
+            .row_of(self.list.get_selected_index())
+            .unwrap_or_default()
+    }
+
+    fn draw_tile(&self, printer: &Printer<'_, '_>, album: &Album, selected: bool) {
+        let text_offset = if self.has_covers() {
+            #[cfg(feature = "cover")]
+            self.draw_cover(printer, album);
+            COVER_HEIGHT
+        } else {
+            0
+        };
+
+        let title_style = if selected {
+            ColorStyle::highlight()
+        } else if album.is_playing(&self.queue) {
+            ColorStyle::new(
+                ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+                ColorType::Color(*printer.theme.palette.custom("playing_bg").unwrap()),
+            )
+        } else {
+            ColorStyle::primary()
+        };
+        printer.with_color(title_style, |printer| {
+            printer.print_hline((0, text_offset), TILE_WIDTH, " ");
+            printer.print(
+                (0, text_offset),
+                &template::truncate(&album.title, TILE_WIDTH),
+            );
+        });
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print(
+                (0, text_offset + 1),
+                &template::truncate(&album.artists.join(", "), TILE_WIDTH),
+            );
+        });
+    }
+
+    /// Draw the cover of `album`, or a placeholder while it's loading.
+    #[cfg(feature = "cover")]
+    fn draw_cover(&self, printer: &Printer<'_, '_>, album: &Album) {
+        let Some(covers) = &self.covers else {
+            return;
+        };
+
+        if let Some(half_blocks) = album.cover_url.as_deref().and_then(|url| covers.get(url)) {
+            print_half_blocks(printer, &half_blocks);
+            return;
+        }
+
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            for y in 0..COVER_HEIGHT {
+                printer.print_hline((0, y), TILE_WIDTH, "░");
+            }
+        });
+    }
+
+    /// Move the selection by `rows` rows of tiles.
+    fn move_rows(&mut self, rows: i32) {
+        let columns = Self::columns(self.last_size.x) as i32;
+        self.list.move_focus(rows * columns);
+    }
+}
+
+/// Print rows of half blocks, centered in `printer`.
+#[cfg(feature = "cover")]
+fn print_half_blocks(printer: &Printer<'_, '_>, half_blocks: &[Vec<HalfBlock>]) {
+    let width = half_blocks.first().map_or(0, Vec::len);
+    let offset = Vec2::new(
+        TILE_WIDTH.saturating_sub(width) / 2,
+        COVER_HEIGHT.saturating_sub(half_blocks.len()) / 2,
+    );
+
+    for (y, row) in half_blocks.iter().enumerate() {
+        for (x, block) in row.iter().enumerate() {
+            let style =
+                ColorStyle::new(ColorType::Color(block.top), ColorType::Color(block.bottom));
+            printer.with_color(style, |printer| {
+                printer.print(offset + (x, y), HalfBlock::CHARACTER);
+            });
+        }
+    }
+}
+
+impl View for AlbumGridView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let content = self.content.read().unwrap();
+        let columns = Self::columns(printer.size.x);
+        let tile_height = self.tile_height();
+        let selected = self.selected();
+        let len = self.list.content_len(false);
+
+        let first = self.scroll * columns;
+        let last = len.min(first + printer.size.y.div_ceil(tile_height) * columns);
+        for index in first..last {
+            let Some(album) = content.get(self.list.item_index(index)) else {
+                continue;
+            };
+            let position = Vec2::new(
+                (index % columns) * (TILE_WIDTH + TILE_GAP),
+                (index / columns - self.scroll) * tile_height,
+            );
+            let printer = printer
+                .offset(position)
+                .cropped((TILE_WIDTH, tile_height - 1));
+            self.draw_tile(&printer, album, index == selected);
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.list.layout(size);
+        self.last_size = size;
+
+        // Scroll the selected album into view.
+        let row = self.selected() / Self::columns(size.x);
+        let visible_rows = self.grid().visible_rows();
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + visible_rows {
+            self.scroll = row + 1 - visible_rows;
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn on_event(&mut self, e: Event) -> EventResult {
+        match e {
+            Event::Mouse {
+                event: MouseEvent::WheelUp,
+                ..
+            } => self.move_rows(-1),
+            Event::Mouse {
+                event: MouseEvent::WheelDown,
+                ..
+            } => self.move_rows(1),
+            Event::Mouse {
+                event: MouseEvent::Press(button @ (MouseButton::Left | MouseButton::Right)),
+                position,
+                offset,
+            } => {
+                let Some(index) = position
+                    .checked_sub(offset)
+                    .and_then(|p| self.grid().tile_at(p))
+                else {
+                    return EventResult::Ignored;
+                };
+                let was_selected = index == self.selected();
+                self.list.move_focus_to(index);
+
+                let target = self
+                    .content
+                    .read()
+                    .unwrap()
+                    .get(self.list.item_index(index))
+                    .map(ListItem::as_listitem);
+                let queue = self.queue.clone();
+                let library = self.library.clone();
+                match (target, button) {
+                    (Some(target), MouseButton::Right) => {
+                        let contextmenu = ContextMenu::new(&*target, queue, library);
+                        return EventResult::Consumed(Some(Callback::from_fn_once(move |s| {
+                            s.add_layer(contextmenu)
+                        })));
+                    }
+                    // The first click selects an album and the second one opens it.
+                    (Some(target), _) if was_selected => {
+                        if let Some(view) = target.open(queue, library) {
+                            return EventResult::Consumed(Some(Callback::from_fn_once(move |s| {
+                                s.on_layout(|_, mut l| l.push_view(view));
+                            })));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => return EventResult::Ignored,
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn important_area(&self, view_size: Vec2) -> Rect {
+        let columns = Self::columns(view_size.x);
+        let selected = self.selected();
+        let row = (selected / columns).saturating_sub(self.scroll);
+        Rect::from_size(
+            (
+                (selected % columns) * (TILE_WIDTH + TILE_GAP),
+                row * self.tile_height(),
+            ),
+            (TILE_WIDTH, self.tile_height()),
+        )
+    }
+}
+
+impl ViewExt for AlbumGridView {
+    fn title(&self) -> String {
+        self.list.title()
+    }
+
+    fn title_sub(&self) -> String {
+        self.list.title_sub()
+    }
+
+    fn moves_horizontally(&self) -> bool {
+        true
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        let selected = self.selected();
+        let last = self.list.content_len(false).saturating_sub(1);
+        match cmd {
+            // Leave moving past the first and last album to the views around the grid.
+            Command::Move(MoveMode::Left, _) if selected == 0 => Ok(CommandResult::Ignored),
+            Command::Move(MoveMode::Right, _) if selected == last => Ok(CommandResult::Ignored),
+            Command::Move(mode, amount) => {
+                let (mode, amount) = self.grid().grid_move(selected, mode, amount);
+                self.list.on_command(s, &Command::Move(mode, amount))
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of 10 albums with three columns and three visible rows of tiles without covers.
+    fn grid(scroll: usize) -> Grid {
+        Grid {
+            size: Vec2::new(58, 9),
+            tile_height: TEXT_HEIGHT + 1,
+            scroll,
+            len: 10,
+        }
+    }
+
+    fn assert_move(
+        (mode, amount): (MoveMode, MoveAmount),
+        expected_mode: MoveMode,
+        expected_amount: MoveAmount,
+    ) {
+        assert_eq!(mode.to_string(), expected_mode.to_string());
+        assert_eq!(format!("{amount:?}"), format!("{expected_amount:?}"));
+    }
+
+    #[test]
+    fn fits_columns_into_width() {
+        assert_eq!(AlbumGridView::columns(0), 1);
+        assert_eq!(AlbumGridView::columns(TILE_WIDTH), 1);
+        assert_eq!(AlbumGridView::columns(2 * TILE_WIDTH + TILE_GAP - 1), 1);
+        assert_eq!(AlbumGridView::columns(2 * TILE_WIDTH + TILE_GAP), 2);
+        assert_eq!(grid(0).columns(), 3);
+    }
+
+    #[test]
+    fn finds_tile_at_position() {
+        assert_eq!(grid(0).tile_at(Vec2::new(0, 0)), Some(0));
+        assert_eq!(grid(0).tile_at(Vec2::new(20, 2)), Some(1));
+        assert_eq!(grid(0).tile_at(Vec2::new(40, 4)), Some(5));
+        // The gap between two tiles and the space after the last column.
+        assert_eq!(grid(0).tile_at(Vec2::new(TILE_WIDTH, 0)), None);
+        assert_eq!(grid(0).tile_at(Vec2::new(60, 0)), None);
+
+        assert_eq!(grid(1).tile_at(Vec2::new(0, 0)), Some(3));
+        assert_eq!(grid(1).tile_at(Vec2::new(0, 6)), Some(9));
+        // There is no album after the last one.
+        assert_eq!(grid(1).tile_at(Vec2::new(20, 6)), None);
+    }
+
+    #[test]
+    fn moves_horizontally_across_row_edges() {
+        assert_move(
+            grid(0).grid_move(4, &MoveMode::Left, &MoveAmount::Integer(1)),
+            MoveMode::Up,
+            MoveAmount::Integer(1),
+        );
+        // Moving left from the first column selects the last album of the previous row.
+        assert_move(
+            grid(0).grid_move(3, &MoveMode::Left, &MoveAmount::Integer(1)),
+            MoveMode::Up,
+            MoveAmount::Integer(1),
+        );
+        assert_move(
+            grid(0).grid_move(5, &MoveMode::Right, &MoveAmount::Integer(1)),
+            MoveMode::Down,
+            MoveAmount::Integer(1),
+        );
+    }
+
+    #[test]
+    fn moves_to_row_edges() {
+        assert_move(
+            grid(0).grid_move(5, &MoveMode::Left, &MoveAmount::Extreme),
+            MoveMode::Up,
+            MoveAmount::Integer(2),
+        );
+        assert_move(
+            grid(0).grid_move(3, &MoveMode::Right, &MoveAmount::Extreme),
+            MoveMode::Down,
+            MoveAmount::Integer(2),
+        );
+        assert_move(
+            grid(0).grid_move(3, &MoveMode::Down, &MoveAmount::Extreme),
+            MoveMode::Down,
+            MoveAmount::Extreme,
+        );
+    }
+
+    #[test]
+    fn moves_vertically_by_rows() {
+        assert_move(
+            grid(0).grid_move(1, &MoveMode::Down, &MoveAmount::Integer(1)),
+            MoveMode::Down,
+            MoveAmount::Integer(3),
+        );
+        // Pages move by the visible rows, but at least by one row.
+        assert_move(
+            grid(0).grid_move(1, &MoveMode::Down, &MoveAmount::Float(1.0)),
+            MoveMode::Down,
+            MoveAmount::Integer(9),
+        );
+        assert_move(
+            grid(0).grid_move(7, &MoveMode::Up, &MoveAmount::Float(0.1)),
+            MoveMode::Up,
+            MoveAmount::Integer(3),
+        );
+    }
+
+    #[cfg(feature = "cover")]
+    #[test]
+    fn drops_oldest_thumbnails() {
+        let mut thumbnails = Thumbnails::default();
+        for i in 0..MAX_THUMBNAILS {
+            assert!(thumbnails.request(&i.to_string()));
+        }
+        assert!(!thumbnails.request("0"));
+        thumbnails.insert("0", Vec::new());
+        assert!(thumbnails.get("0").is_some());
+
+        assert!(thumbnails.request("new"));
+        assert!(!thumbnails.contains("0"));
+        assert!(thumbnails.contains("1"));
+        assert_eq!(thumbnails.covers.len(), MAX_THUMBNAILS);
+
+        // Covers that were dropped while loading aren't added again.
+        thumbnails.insert("0", Vec::new());
+        assert!(!thumbnails.contains("0"));
+    }
+}
@@ -7,12 +7,14 @@ use rspotify::model::AlbumType;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::config::DEFAULT_ALBUM_GRID;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::track::Track;
 use crate::queue::Queue;
-use crate::traits::ViewExt;
+use crate::traits::{BoxedViewExt, ViewExt};
+use crate::ui::albumgrid::AlbumGridView;
 use crate::ui::listview::ListView;
 use crate::ui::tabbedview::TabbedView;
 
@@ -89,22 +91,39 @@ impl ArtistView {
         }
     }
 
+    /// The albums of `artist`, either as a list or as a grid if `album_grid` is enabled.
     fn albums_view(
         artist: &Artist,
         album_type: AlbumType,
         queue: Arc<Queue>,
         library: Arc<Library>,
-    ) -> ListView<Album> {
-        if let Some(artist_id) = &artist.id {
+    ) -> BoxedViewExt {
+        let grid = library
+            .cfg
+            .values()
+            .album_grid
+            .unwrap_or(DEFAULT_ALBUM_GRID);
+        let view: Box<dyn ViewExt> = if let Some(artist_id) = &artist.id {
             let spotify = queue.get_spotify();
             let albums_page = spotify.api.artist_albums(artist_id, Some(album_type));
-            let view = ListView::new(albums_page.items.clone(), queue, library);
-            albums_page.apply_pagination(view.get_pagination());
-
-            view
+            if grid {
+                let view = AlbumGridView::new(albums_page.items.clone(), queue, library);
+                albums_page.apply_pagination(view.get_pagination());
+                Box::new(view)
+            } else {
+                let view = ListView::new(albums_page.items.clone(), queue, library);
+                albums_page.apply_pagination(view.get_pagination());
+                Box::new(view)
+            }
         } else {
-            ListView::new(Arc::new(RwLock::new(Vec::new())), queue, library)
-        }
+            let albums: Arc<RwLock<Vec<Album>>> = Arc::new(RwLock::new(Vec::new()));
+            if grid {
+                Box::new(AlbumGridView::new(albums, queue, library))
+            } else {
+                Box::new(ListView::new(albums, queue, library))
+            }
+        };
+        BoxedViewExt::new(view)
     }
 }
 
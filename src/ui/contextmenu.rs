@@ -4,6 +4,7 @@ use cursive::Cursive;
 use cursive::view::{Margins, ViewWrapper};
 use cursive::views::{Dialog, NamedView, ScrollView, SelectView};
 
+use crate::command::Command;
 use crate::commands::CommandResult;
 use crate::ext_traits::SelectViewExt;
 use crate::library::Library;
@@ -18,7 +19,6 @@ use crate::spotify::PlayerEvent;
 use crate::traits::{ListItem, ViewExt};
 use crate::ui::layout::Layout;
 use crate::ui::modal::Modal;
-use crate::{command::Command, spotify::Spotify};
 use cursive::traits::{Finder, Nameable};
 
 pub struct ContextMenu {
@@ -53,13 +53,10 @@ enum ContextMenuAction {
 }
 
 impl ContextMenu {
-    pub fn add_track_dialog(
-        library: Arc<Library>,
-        spotify: Spotify,
-        track: Track,
-    ) -> NamedView<AddToPlaylistMenu> {
+    pub fn add_track_dialog(library: Arc<Library>, track: Track) -> NamedView<AddToPlaylistMenu> {
         let mut list_select: SelectView<Playlist> = SelectView::new();
-        let current_user_id = library.user_id.as_ref().unwrap();
+        // Offline without cached user info, only collaborative playlists are known to be editable.
+        let current_user_id = library.user_id.as_deref();
 
         // Collect eligible playlists
         let mut eligible_playlists: Vec<Playlist> = library
@@ -67,7 +64,10 @@ impl ContextMenu {
             .read()
             .unwrap()
             .iter()
-            .filter(|list| current_user_id == &list.owner_id || list.collaborative)
+            .filter(|list| {
+                !list.smart
+                    && (current_user_id == Some(list.owner_id.as_str()) || list.collaborative)
+            })
             .cloned()
             .collect();
 
@@ -89,7 +89,6 @@ impl ContextMenu {
         list_select.set_on_submit(move |s, selected| {
             let track = track.clone();
             let mut playlist = selected.clone();
-            let spotify = spotify.clone();
             let library = library.clone();
 
             if playlist.has_track(track.id.as_ref().unwrap_or(&String::new())) {
@@ -98,7 +97,7 @@ impl ContextMenu {
                 already_added_dialog.add_button("Add anyway", move |c| {
                     let mut playlist = playlist.clone();
 
-                    playlist.append_tracks(&[Playable::Track(track.clone())], &spotify, &library);
+                    playlist.append_tracks(&[Playable::Track(track.clone())], &library);
                     c.pop_layer();
 
                     // Close add_track_dialog too
@@ -108,7 +107,7 @@ impl ContextMenu {
                 let modal = Modal::new(already_added_dialog);
                 s.add_layer(modal);
             } else {
-                playlist.append_tracks(&[Playable::Track(track)], &spotify, &library);
+                playlist.append_tracks(&[Playable::Track(track)], &library);
                 s.pop_layer();
             }
         });
@@ -327,8 +326,7 @@ impl ContextMenu {
                         write_share(url.to_string()).ok();
                     }
                     ContextMenuAction::AddToPlaylist(track) => {
-                        let dialog =
-                            Self::add_track_dialog(library, queue.get_spotify(), *track.clone());
+                        let dialog = Self::add_track_dialog(library, *track.clone());
                         s.add_layer(dialog);
                     }
                     ContextMenuAction::ShowRecommendations(item) => {
@@ -1,23 +1,29 @@
 use std::collections::HashSet;
+use std::error::Error;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Stdio};
 
 use std::sync::{Arc, RwLock};
 
 use cursive::theme::{ColorStyle, ColorType, PaletteColor};
-use cursive::{Cursive, Printer, Vec2, View};
+use cursive::{CbSink, Cursive, Printer, Vec2, View};
+use image::RgbImage;
 use ioctl_rs::{TIOCGWINSZ, ioctl};
 use log::{debug, error};
 
 use crate::command::{Command, GotoMode};
 use crate::commands::CommandResult;
-use crate::config::Config;
+use crate::config::{Config, CoverProtocol, DEFAULT_COVER_MAX_SCALE};
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
+use crate::ui::graphics::{self, HalfBlock};
+
+/// The font size that is assumed if the terminal doesn't report its size in pixels.
+const DEFAULT_FONT_SIZE: Vec2 = Vec2::new(8, 16);
 
 pub struct CoverView {
     queue: Arc<Queue>,
@@ -25,12 +31,21 @@ pub struct CoverView {
     loading: Arc<RwLock<HashSet<String>>>,
     last_size: RwLock<Vec2>,
     drawn_url: RwLock<Option<String>>,
+    protocol: CoverProtocol,
+    /// The decoded cover and its URL.
+    image: RwLock<Option<(String, RgbImage)>>,
+    /// The cover rendered to half blocks or braille patterns, which are printed in every frame.
+    cells: RwLock<Vec<Vec<(ColorStyle, String)>>>,
+    /// The position and size of the cells that the cover was drawn to with a graphics protocol.
+    drawn_area: RwLock<Option<(Vec2, Vec2)>>,
+    cb_sink: CbSink,
     ueberzug: RwLock<Option<Child>>,
     font_size: Vec2,
+    scale: f32,
 }
 
 impl CoverView {
-    pub fn new(queue: Arc<Queue>, library: Arc<Library>, config: &Config) -> Self {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, config: &Config, cb_sink: CbSink) -> Self {
         // Determine size of window both in pixels and chars
         let (rows, cols, mut xpixels, mut ypixels) = unsafe {
             let mut query: (u16, u16, u16, u16) = (0, 0, 0, 0);
@@ -41,14 +56,28 @@ impl CoverView {
         debug!("Determined window dimensions: {xpixels}x{ypixels}, {cols}x{rows}");
 
         // Determine font size, considering max scale to prevent tiny covers on HiDPI screens
-        let scale = config.values().cover_max_scale.unwrap_or(1.0);
+        let scale = config
+            .values()
+            .cover_max_scale
+            .unwrap_or(DEFAULT_COVER_MAX_SCALE);
         xpixels = ((xpixels as f32) / scale) as u16;
         ypixels = ((ypixels as f32) / scale) as u16;
 
-        let font_size = Vec2::new((xpixels / cols) as usize, (ypixels / rows) as usize);
+        let font_size = if rows == 0 || cols == 0 || xpixels == 0 || ypixels == 0 {
+            DEFAULT_FONT_SIZE
+        } else {
+            Vec2::new((xpixels / cols) as usize, (ypixels / rows) as usize)
+        };
 
         debug!("Determined font size: {}x{}", font_size.x, font_size.y);
 
+        let protocol = config
+            .values()
+            .cover_protocol
+            .unwrap_or_else(graphics::detect_protocol);
+
+        debug!("Drawing covers with {protocol:?}");
+
         Self {
             queue,
             library,
@@ -56,11 +85,18 @@ impl CoverView {
             loading: Arc::new(RwLock::new(HashSet::new())),
             last_size: RwLock::new(Vec2::new(0, 0)),
             drawn_url: RwLock::new(None),
+            protocol,
+            image: RwLock::new(None),
+            cells: RwLock::new(Vec::new()),
+            drawn_area: RwLock::new(None),
+            cb_sink,
             font_size,
+            scale,
         }
     }
 
-    fn draw_cover(&self, url: String, mut draw_offset: Vec2, draw_size: Vec2) {
+    fn draw_cover(&self, url: String, printer: &Printer<'_, '_>) {
+        let (draw_offset, draw_size) = (printer.offset, printer.size);
         if draw_size.x <= 1 || draw_size.y <= 1 {
             return;
         }
@@ -71,17 +107,44 @@ impl CoverView {
             *last_size != draw_size || drawn_url.as_ref() != Some(&url)
         };
 
-        if !needs_redraw {
-            return;
-        }
+        if needs_redraw {
+            let path = match self.cache_path(url.clone()) {
+                Some(p) => p,
+                None => return,
+            };
+
+            let result = match self.protocol {
+                CoverProtocol::Ueberzug => self.draw_ueberzug(&path, draw_offset, draw_size),
+                _ => self.draw_image(&url, &path, draw_offset, draw_size),
+            };
+            if let Err(e) = result {
+                error!("Failed to draw cover: {e}");
+                return;
+            }
 
-        let path = match self.cache_path(url.clone()) {
-            Some(p) => p,
-            None => return,
-        };
+            let mut last_size = self.last_size.write().unwrap();
+            *last_size = draw_size;
 
-        let mut img_size = Vec2::new(640, 640);
+            let mut drawn_url = self.drawn_url.write().unwrap();
+            *drawn_url = Some(url);
+        }
+
+        if matches!(
+            self.protocol,
+            CoverProtocol::HalfBlocks | CoverProtocol::Braille
+        ) {
+            self.print_cells(printer);
+        }
+    }
 
+    /// Fit a cover of `img_size` pixels into the area at `draw_offset` with `draw_size`. Returns
+    /// the position and size of the cells that the cover covers, and its size in pixels.
+    fn placement(
+        &self,
+        mut img_size: Vec2,
+        mut draw_offset: Vec2,
+        draw_size: Vec2,
+    ) -> (Vec2, Vec2, Vec2) {
         let draw_size_pxls = draw_size * self.font_size;
         let ratio = f32::min(
             f32::min(
@@ -114,34 +177,165 @@ impl CoverView {
         draw_offset.x += (draw_size.x - size.x) / 2;
         draw_offset.y += (draw_size.y - size.y) - (draw_size.y - size.y) / 2;
 
+        (draw_offset, size, img_size)
+    }
+
+    fn draw_ueberzug(
+        &self,
+        path: &Path,
+        draw_offset: Vec2,
+        draw_size: Vec2,
+    ) -> Result<(), Box<dyn Error>> {
+        let (offset, size, _) = self.placement(Vec2::new(640, 640), draw_offset, draw_size);
+
         let cmd = format!(
             "{{\"action\":\"add\",\"scaler\":\"fit_contain\",\"identifier\":\"cover\",\"x\":{},\"y\":{},\"width\":{},\"height\":{},\"path\":\"{}\"}}\n",
-            draw_offset.x,
-            draw_offset.y,
+            offset.x,
+            offset.y,
             size.x,
             size.y,
             path.to_str().unwrap()
         );
 
-        if let Err(e) = self.run_ueberzug_cmd(&cmd) {
-            error!("Failed to run Ueberzug: {e}");
-            return;
+        self.run_ueberzug_cmd(&cmd)
+            .map_err(|e| format!("Failed to run Ueberzug: {e}").into())
+    }
+
+    /// Draw the cover with one of the built-in protocols. Covers drawn with escape sequences are
+    /// written to the terminal after the current frame, which clears their area.
+    fn draw_image(
+        &self,
+        url: &str,
+        path: &Path,
+        draw_offset: Vec2,
+        draw_size: Vec2,
+    ) -> Result<(), Box<dyn Error>> {
+        let image = self.decode(url, path)?;
+        match self.protocol {
+            CoverProtocol::HalfBlocks => {
+                *self.cells.write().unwrap() = graphics::half_blocks(&image, draw_size)
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|block| {
+                                let style = ColorStyle::new(
+                                    ColorType::Color(block.top),
+                                    ColorType::Color(block.bottom),
+                                );
+                                (style, HalfBlock::CHARACTER.to_string())
+                            })
+                            .collect()
+                    })
+                    .collect();
+                return Ok(());
+            }
+            CoverProtocol::Braille => {
+                *self.cells.write().unwrap() = graphics::braille(&image, draw_size)
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|cell| {
+                                let style = ColorStyle::new(
+                                    ColorType::Color(cell.color),
+                                    ColorType::Palette(PaletteColor::Background),
+                                );
+                                (style, cell.character.to_string())
+                            })
+                            .collect()
+                    })
+                    .collect();
+                return Ok(());
+            }
+            _ => {}
         }
 
-        let mut last_size = self.last_size.write().unwrap();
-        *last_size = draw_size;
+        let img_size = Vec2::new(image.width() as usize, image.height() as usize);
+        let (offset, size, img_size) = self.placement(img_size, draw_offset, draw_size);
+        let sequence = match self.protocol {
+            CoverProtocol::Kitty => graphics::kitty(&graphics::fit(&image, img_size), size.x),
+            CoverProtocol::Sixel => {
+                // Sixel graphics are drawn in actual pixels of the terminal.
+                let pixels = Vec2::new(
+                    (img_size.x as f32 * self.scale) as usize,
+                    (img_size.y as f32 * self.scale) as usize,
+                );
+                graphics::sixel(&graphics::fit(&image, pixels))
+            }
+            _ => graphics::iterm2(&std::fs::read(path)?, size),
+        };
 
-        let mut drawn_url = self.drawn_url.write().unwrap();
-        *drawn_url = Some(url);
+        // The previous cover is only erased now, as the frame doesn't redraw the cells of the
+        // view that stay blank.
+        if let Some((previous_offset, previous_size)) =
+            self.drawn_area.write().unwrap().replace((offset, size))
+        {
+            graphics::erase(previous_offset, previous_size);
+        }
+        graphics::write_at(offset, &sequence);
+
+        Ok(())
+    }
+
+    fn print_cells(&self, printer: &Printer<'_, '_>) {
+        let cells = self.cells.read().unwrap();
+        let height = cells.len();
+        let width = cells.first().map_or(0, Vec::len);
+        let offset = Vec2::new(
+            printer.size.x.saturating_sub(width) / 2,
+            printer.size.y.saturating_sub(height) / 2,
+        );
+
+        for (y, row) in cells.iter().enumerate() {
+            for (x, (style, text)) in row.iter().enumerate() {
+                printer.with_color(*style, |printer| {
+                    printer.print(offset + (x, y), text);
+                });
+            }
+        }
+    }
+
+    /// Decode the cover at `path`, which is kept until the cover changes.
+    fn decode(&self, url: &str, path: &Path) -> Result<RgbImage, Box<dyn Error>> {
+        if let Some((decoded_url, image)) = &*self.image.read().unwrap()
+            && decoded_url == url
+        {
+            return Ok(image.clone());
+        }
+
+        let image = image::open(path)?.into_rgb8();
+        *self.image.write().unwrap() = Some((url.to_string(), image.clone()));
+        Ok(image)
     }
 
     fn clear_cover(&self) {
         let mut drawn_url = self.drawn_url.write().unwrap();
-        *drawn_url = None;
+        if drawn_url.take().is_none() {
+            return;
+        }
 
-        let cmd = "{\"action\": \"remove\", \"identifier\": \"cover\"}\n";
-        if let Err(e) = self.run_ueberzug_cmd(cmd) {
-            error!("Failed to run Ueberzug: {e}");
+        match self.protocol {
+            CoverProtocol::Ueberzug => {
+                let cmd = "{\"action\": \"remove\", \"identifier\": \"cover\"}\n";
+                if let Err(e) = self.run_ueberzug_cmd(cmd) {
+                    error!("Failed to run Ueberzug: {e}");
+                }
+            }
+            CoverProtocol::HalfBlocks | CoverProtocol::Braille => {
+                self.cells.write().unwrap().clear()
+            }
+            CoverProtocol::Kitty => {
+                self.drawn_area.write().unwrap().take();
+                graphics::write_at(Vec2::zero(), &graphics::kitty_delete());
+            }
+            CoverProtocol::Sixel | CoverProtocol::Iterm2 => {
+                // The cells of the image might belong to another view already, so cursive redraws
+                // all of them instead of erasing them.
+                if self.drawn_area.write().unwrap().take().is_some()
+                    && self.cb_sink.send(Box::new(Cursive::clear)).is_err()
+                {
+                    error!("Failed to remove cover");
+                }
+            }
         }
     }
 
@@ -165,14 +359,12 @@ impl CoverView {
     }
 
     fn cache_path(&self, url: String) -> Option<PathBuf> {
-        let path = crate::utils::cache_path_for_url(url.clone());
-
         let mut loading = self.loading.write().unwrap();
         if loading.contains(&url) {
             return None;
         }
 
-        if path.exists() {
+        if let Some(path) = crate::cache::cached_cover(&url) {
             return Some(path);
         }
 
@@ -180,7 +372,7 @@ impl CoverView {
 
         let loading_thread = self.loading.clone();
         std::thread::spawn(move || {
-            if let Err(e) = crate::utils::download(url.clone(), path.clone()) {
+            if let Err(e) = crate::cache::cover(&url) {
                 error!("Failed to download cover: {e}");
             }
             let mut loading = loading_thread.write().unwrap();
@@ -207,7 +399,7 @@ impl View for CoverView {
         let cover_url = self.queue.get_current().and_then(|t| t.cover_url());
 
         if let Some(url) = cover_url {
-            self.draw_cover(url, printer.offset, printer.size);
+            self.draw_cover(url, printer);
         } else {
             self.clear_cover();
         }
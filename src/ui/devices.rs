@@ -0,0 +1,148 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::event::{Event, EventResult};
+use cursive::view::ViewWrapper;
+use log::error;
+use rspotify::model::PlayableItem;
+
+use crate::application::ASYNC_RUNTIME;
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::config::{DEFAULT_CONNECT, DEFAULT_CONNECT_NAME};
+use crate::events::Event as AppEvent;
+use crate::library::Library;
+use crate::model::device::Device;
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::{ListView, MouseHandleResult};
+
+/// Lists the Spotify Connect devices of the user and allows transferring playback between them.
+pub struct DevicesView {
+    list: ListView<Device>,
+    devices: Arc<RwLock<Vec<Device>>>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl DevicesView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let devices = Arc::new(RwLock::new(Vec::new()));
+        let list = ListView::new(devices.clone(), queue.clone(), library.clone());
+
+        let view = Self {
+            list,
+            devices,
+            queue,
+            library,
+        };
+        view.refresh();
+        view
+    }
+
+    /// Fetch the current list of devices in the background and redraw once they're there.
+    fn refresh(&self) {
+        let devices = self.devices.clone();
+        let queue = self.queue.clone();
+        let library = self.library.clone();
+        ASYNC_RUNTIME.get().unwrap().spawn_blocking(move || {
+            *devices.write().unwrap() = Self::fetch(&queue, &library);
+            library.trigger_redraw();
+        });
+    }
+
+    /// Fetch the current list of devices from the Web API.
+    fn fetch(queue: &Queue, library: &Library) -> Vec<Device> {
+        let spotify = queue.get_spotify();
+        let receiver_mode = library.cfg.values().connect.unwrap_or(DEFAULT_CONNECT);
+        let local_name = library
+            .cfg
+            .values()
+            .connect_name
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CONNECT_NAME.to_string());
+
+        let mut devices = vec![Device::local(local_name.clone(), queue)];
+        match spotify.api.devices() {
+            Ok(remote) => {
+                let playing = spotify
+                    .api
+                    .current_playback()
+                    .ok()
+                    .flatten()
+                    .and_then(|context| context.item)
+                    .filter(|item| !matches!(item, PlayableItem::Unknown(_)))
+                    .map(|item| Playable::from(&item));
+
+                let local_name = receiver_mode.then_some(local_name.as_str());
+                devices.extend(Device::remote(&remote, local_name, playing.as_ref()));
+            }
+            Err(_) => error!("could not fetch spotify connect devices"),
+        }
+        devices
+    }
+
+    /// Transfer playback to the selected device in the background, then fetch the devices again.
+    /// A failed transfer is shown as the result of the command.
+    fn transfer_selected(&self) {
+        let selected = self.list.get_selected_index();
+        let Some(device) = self.devices.read().unwrap().get(selected).cloned() else {
+            return;
+        };
+        let devices = self.devices.clone();
+        let queue = self.queue.clone();
+        let library = self.library.clone();
+        ASYNC_RUNTIME.get().unwrap().spawn_blocking(move || {
+            if let Err(e) = device.transfer_playback(&queue) {
+                queue
+                    .get_spotify()
+                    .send_event(AppEvent::CommandResult(Err(e)));
+            }
+            *devices.write().unwrap() = Self::fetch(&queue, &library);
+            library.trigger_redraw();
+        });
+    }
+}
+
+impl ViewWrapper for DevicesView {
+    wrap_impl!(self.list: ListView<Device>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match self.list.handle_mouse_event(event) {
+            MouseHandleResult::Handled(result) => result,
+            MouseHandleResult::Unhandled(Command::Play) => {
+                self.transfer_selected();
+                EventResult::consumed()
+            }
+            MouseHandleResult::Unhandled(_) => EventResult::Ignored,
+        }
+    }
+}
+
+impl ViewExt for DevicesView {
+    fn title(&self) -> String {
+        "Devices".to_string()
+    }
+
+    fn title_sub(&self) -> String {
+        format!("{} devices", self.devices.read().unwrap().len())
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Play => {
+                self.transfer_selected();
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::UpdateLibrary | Command::Devices => {
+                self.refresh();
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::PlayNext | Command::Queue | Command::Save | Command::Delete => {
+                Ok(CommandResult::Ignored)
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Drawing of images in the terminal, either with one of the graphics protocols of terminal
+//! emulators or with colored Unicode half blocks or braille patterns.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::Mutex;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use cursive::Vec2;
+use cursive::theme::Color;
+use image::RgbImage;
+use image::imageops::FilterType;
+
+use crate::config::CoverProtocol;
+
+/// The ID of the image in the kitty graphics protocol, which allows replacing and deleting it.
+const KITTY_IMAGE_ID: u32 = 1;
+
+/// The size of the chunks that image data is split into in the kitty graphics protocol.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// The number of levels per channel in the sixel palette.
+const SIXEL_LEVELS: u16 = 6;
+
+/// The dots of braille patterns by their position in the cell, as `[x][y]`.
+const BRAILLE_DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Escape sequences that are written to the terminal after the current frame, see [write_at].
+static PENDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Guess the best protocol to draw images with from the environment of the terminal.
+pub fn detect_protocol() -> CoverProtocol {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    protocol_for(
+        &var("TERM"),
+        &var("TERM_PROGRAM"),
+        std::env::var_os("KITTY_WINDOW_ID").is_some(),
+        matches!(var("COLORTERM").as_str(), "truecolor" | "24bit"),
+    )
+}
+
+fn protocol_for(term: &str, program: &str, kitty: bool, true_color: bool) -> CoverProtocol {
+    let text = if true_color {
+        CoverProtocol::HalfBlocks
+    } else {
+        CoverProtocol::Braille
+    };
+
+    if term.starts_with("screen") || term.starts_with("tmux") {
+        // Terminal multiplexers don't pass the escape sequences of graphics protocols through.
+        text
+    } else if kitty || term == "xterm-kitty" || term == "xterm-ghostty" || program == "ghostty" {
+        CoverProtocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" {
+        CoverProtocol::Iterm2
+    } else if term.contains("sixel")
+        || term.starts_with("foot")
+        || term.starts_with("mlterm")
+        || program == "mintty"
+    {
+        CoverProtocol::Sixel
+    } else {
+        text
+    }
+}
+
+/// Whether the terminal supports true colors, which are needed to draw images with half blocks.
+pub fn supports_true_color() -> bool {
+    detect_protocol() != CoverProtocol::Braille
+}
+
+/// Resize `image` to the largest size that fits into `size` pixels while keeping its aspect ratio.
+pub fn fit(image: &RgbImage, size: Vec2) -> RgbImage {
+    let (width, height) = image.dimensions();
+    let ratio = f32::min(size.x as f32 / width as f32, size.y as f32 / height as f32);
+    let target_width = ((width as f32 * ratio) as u32).max(1);
+    let target_height = ((height as f32 * ratio) as u32).max(1);
+
+    if (target_width, target_height) == (width, height) {
+        image.clone()
+    } else {
+        image::imageops::resize(image, target_width, target_height, FilterType::Triangle)
+    }
+}
+
+/// A terminal cell of an image drawn with the upper half block character, which shows `top` in its
+/// upper half and `bottom` in its lower half.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfBlock {
+    pub top: Color,
+    pub bottom: Color,
+}
+
+impl HalfBlock {
+    pub const CHARACTER: &'static str = "▀";
+}
+
+/// Render `image` to rows of half blocks that fit into `size` cells, keeping its aspect ratio.
+/// Every cell covers two pixels, which are about square in most fonts.
+pub fn half_blocks(image: &RgbImage, size: Vec2) -> Vec<Vec<HalfBlock>> {
+    if size.x == 0 || size.y == 0 {
+        return Vec::new();
+    }
+
+    let image = fit(image, Vec2::new(size.x, size.y * 2));
+    let (width, height) = image.dimensions();
+    let color = |x, y| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        Color::Rgb(r, g, b)
+    };
+
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let top = color(x, y);
+                    HalfBlock {
+                        top,
+                        // The last row of an image with an odd height only fills the upper half.
+                        bottom: if y + 1 < height { color(x, y + 1) } else { top },
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A terminal cell of an image drawn with a braille pattern, whose dots show the brighter pixels of
+/// the cell in their average `color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Braille {
+    pub character: char,
+    pub color: Color,
+}
+
+/// Render `image` to rows of braille patterns that fit into `size` cells, keeping its aspect ratio.
+/// Every cell covers two by four pixels. The colors are reduced to the 216 colors of the
+/// 256-color palette, so that this works in terminals without true color support.
+pub fn braille(image: &RgbImage, size: Vec2) -> Vec<Vec<Braille>> {
+    if size.x == 0 || size.y == 0 {
+        return Vec::new();
+    }
+
+    let image = fit(image, Vec2::new(size.x * 2, size.y * 4));
+    let (width, height) = image.dimensions();
+    let luminance =
+        |[r, g, b]: [u8; 3]| 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b);
+
+    (0..height)
+        .step_by(4)
+        .map(|top| {
+            (0..width)
+                .step_by(2)
+                .map(|left| {
+                    let pixels: Vec<(u8, [u8; 3])> = (0..2)
+                        .flat_map(|x| (0..4).map(move |y| (x, y)))
+                        .filter(|(x, y)| left + x < width && top + y < height)
+                        .map(|(x, y)| {
+                            let dot = BRAILLE_DOTS[x as usize][y as usize];
+                            (dot, image.get_pixel(left + x, top + y).0)
+                        })
+                        .collect();
+
+                    // Pixels that are at least as bright as the average of the cell are drawn, so
+                    // that cells of a single color are filled.
+                    let average = pixels.iter().map(|(_, p)| luminance(*p)).sum::<u32>()
+                        / pixels.len() as u32;
+                    let lit: Vec<&(u8, [u8; 3])> = pixels
+                        .iter()
+                        .filter(|(_, pixel)| luminance(*pixel) >= average)
+                        .collect();
+
+                    let dots = lit.iter().fold(0_u8, |dots, (dot, _)| dots | dot);
+                    let level = |channel: usize| {
+                        let sum: u32 = lit.iter().map(|(_, pixel)| u32::from(pixel[channel])).sum();
+                        ((sum / lit.len() as u32 * 5 + 127) / 255) as u8
+                    };
+                    Braille {
+                        character: char::from_u32(0x2800 + u32::from(dots)).unwrap_or(' '),
+                        color: Color::RgbLowRes(level(0), level(1), level(2)),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Escape sequences that draw `image` with the kitty graphics protocol, scaled to the width of
+/// `columns` cells. This replaces the image that was drawn before.
+pub fn kitty(image: &RgbImage, columns: usize) -> String {
+    let data = STANDARD.encode(image.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+    let mut sequence = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            let _ = write!(
+                sequence,
+                "\x1b_Ga=T,f=24,s={},v={},i={KITTY_IMAGE_ID},q=2,C=1,c={columns},m={more};",
+                image.width(),
+                image.height()
+            );
+        } else {
+            let _ = write!(sequence, "\x1b_Gm={more};");
+        }
+        // Base64 only consists of ASCII characters.
+        sequence.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        sequence.push_str("\x1b\\");
+    }
+    sequence
+}
+
+/// The escape sequence that deletes the image drawn with [kitty].
+pub fn kitty_delete() -> String {
+    format!("\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\")
+}
+
+/// The escape sequence that draws the image file `data` as an inline image of iTerm2, scaled to
+/// `cells`.
+pub fn iterm2(data: &[u8], cells: Vec2) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        data.len(),
+        cells.x,
+        cells.y,
+        STANDARD.encode(data)
+    )
+}
+
+/// Encode `image` as sixel graphics, with its colors reduced to a palette of [SIXEL_LEVELS] levels
+/// per channel.
+pub fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |value: u8| (u16::from(value) * (SIXEL_LEVELS - 1) + 127) / 255;
+    let palette_index: Vec<u16> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0;
+            (level(r) * SIXEL_LEVELS + level(g)) * SIXEL_LEVELS + level(b)
+        })
+        .collect();
+
+    let mut sequence = format!("\x1bPq\"1;1;{width};{height}");
+    let percent = |level: u16| level * 100 / (SIXEL_LEVELS - 1);
+    for index in 0..SIXEL_LEVELS.pow(3) {
+        let (r, g, b) = (
+            index / (SIXEL_LEVELS * SIXEL_LEVELS),
+            index / SIXEL_LEVELS % SIXEL_LEVELS,
+            index % SIXEL_LEVELS,
+        );
+        let _ = write!(
+            sequence,
+            "#{index};2;{};{};{}",
+            percent(r),
+            percent(g),
+            percent(b)
+        );
+    }
+
+    // Every band covers six rows of pixels, which are drawn one color at a time.
+    for top in (0..height).step_by(6) {
+        let mut colors: BTreeMap<u16, Vec<u8>> = BTreeMap::new();
+        for row in 0..6.min(height - top) {
+            for x in 0..width {
+                let color = palette_index[((top + row) * width + x) as usize];
+                colors
+                    .entry(color)
+                    .or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << row;
+            }
+        }
+
+        for (color, bits) in colors {
+            let _ = write!(sequence, "#{color}");
+            for run in bits.chunk_by(|a, b| a == b) {
+                let character = char::from(b'?' + run[0]);
+                if run.len() > 3 {
+                    let _ = write!(sequence, "!{}{character}", run.len());
+                } else {
+                    sequence.extend(std::iter::repeat_n(character, run.len()));
+                }
+            }
+            sequence.push('$');
+        }
+        sequence.push('-');
+    }
+
+    sequence.push_str("\x1b\\");
+    sequence
+}
+
+/// Write the escape `sequence` to the terminal with the cursor at `position`, restoring the cursor
+/// afterwards. It is written by [flush] once the current frame was drawn, so that it isn't mixed
+/// into the output of cursive and drawn over by its frame.
+pub fn write_at(position: Vec2, sequence: &str) {
+    PENDING.lock().unwrap().push(format!(
+        "\x1b7\x1b[{};{}H{sequence}\x1b8",
+        position.y + 1,
+        position.x + 1
+    ));
+}
+
+/// Write the escape sequences of [write_at] to the terminal. This has to be called after every
+/// frame.
+pub fn flush() -> std::io::Result<()> {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for sequence in pending {
+        stdout.write_all(sequence.as_bytes())?;
+    }
+    stdout.flush()
+}
+
+/// Erase the cells of the area at `position` with `size`, which removes images that are drawn into
+/// the cells like sixel graphics.
+pub fn erase(position: Vec2, size: Vec2) {
+    let mut sequence = String::new();
+    for row in 0..size.y {
+        let _ = write!(
+            sequence,
+            "\x1b[{};{}H\x1b[{}X",
+            position.y + row + 1,
+            position.x + 1,
+            size.x
+        );
+    }
+    write_at(position, &sequence);
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn renders_half_blocks() {
+        let image = RgbImage::from_fn(2, 4, |x, y| Rgb([x as u8, y as u8, 0]));
+        let blocks = half_blocks(&image, Vec2::new(2, 2));
+
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().all(|row| row.len() == 2));
+        assert_eq!(
+            blocks[0][0],
+            HalfBlock {
+                top: Color::Rgb(0, 0, 0),
+                bottom: Color::Rgb(0, 1, 0),
+            }
+        );
+        assert_eq!(
+            blocks[1][1],
+            HalfBlock {
+                top: Color::Rgb(1, 2, 0),
+                bottom: Color::Rgb(1, 3, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn fits_half_blocks_into_area() {
+        let image = RgbImage::from_pixel(8, 4, Rgb([255, 0, 0]));
+
+        // Wide images are limited by the width of the area.
+        let blocks = half_blocks(&image, Vec2::new(4, 10));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].len(), 4);
+        assert!(
+            blocks[0]
+                .iter()
+                .all(|block| block.top == Color::Rgb(255, 0, 0) && block.bottom == block.top)
+        );
+
+        // Images are scaled up to fill the area.
+        let blocks = half_blocks(&image, Vec2::new(40, 40));
+        assert_eq!(blocks.len(), 10);
+        assert_eq!(blocks[0].len(), 40);
+
+        assert!(half_blocks(&image, Vec2::new(0, 5)).is_empty());
+    }
+
+    #[test]
+    fn renders_braille() {
+        // The left column is white and the right one black.
+        let image = RgbImage::from_fn(2, 4, |x, _| Rgb([255 * (1 - x as u8); 3]));
+        let cells = braille(&image, Vec2::new(1, 1));
+        assert_eq!(
+            cells,
+            [[Braille {
+                character: '⡇',
+                color: Color::RgbLowRes(5, 5, 5),
+            }]]
+        );
+
+        // Cells of a single color are filled.
+        let image = RgbImage::from_pixel(4, 8, Rgb([255, 0, 0]));
+        let cells = braille(&image, Vec2::new(2, 2));
+        assert_eq!(cells.len(), 2);
+        assert!(cells.iter().flatten().all(|cell| *cell
+            == Braille {
+                character: '⣿',
+                color: Color::RgbLowRes(5, 0, 0),
+            }));
+
+        assert!(braille(&image, Vec2::new(3, 0)).is_empty());
+    }
+
+    #[test]
+    fn detects_protocols() {
+        let protocol = |term, program, kitty| protocol_for(term, program, kitty, true);
+        assert_eq!(protocol("xterm-kitty", "", false), CoverProtocol::Kitty);
+        assert_eq!(protocol("xterm-256color", "", true), CoverProtocol::Kitty);
+        assert_eq!(
+            protocol("xterm-256color", "iTerm.app", false),
+            CoverProtocol::Iterm2
+        );
+        assert_eq!(protocol("foot", "", false), CoverProtocol::Sixel);
+        assert_eq!(
+            protocol("tmux-256color", "", true),
+            CoverProtocol::HalfBlocks
+        );
+        assert_eq!(
+            protocol("xterm-256color", "", false),
+            CoverProtocol::HalfBlocks
+        );
+
+        // Terminals without true color support get braille patterns.
+        assert_eq!(
+            protocol_for("xterm-256color", "", false, false),
+            CoverProtocol::Braille
+        );
+        assert_eq!(
+            protocol_for("screen", "", false, false),
+            CoverProtocol::Braille
+        );
+        assert_eq!(protocol_for("foot", "", false, false), CoverProtocol::Sixel);
+    }
+}
@@ -25,6 +25,7 @@ pub struct Layout {
     screens: HashMap<String, Box<dyn ViewExt>>,
     stack: HashMap<String, Vec<Box<dyn ViewExt>>>,
     statusbar: Box<dyn View>,
+    statusbar_height: usize,
     focus: Option<String>,
     cmdline: EditView,
     cmdline_focus: bool,
@@ -43,11 +44,9 @@ impl Layout {
         theme: Theme,
         configuration: Arc<Config>,
     ) -> Self {
-        let style = ColorStyle::new(
-            ColorType::Color(*theme.palette.custom("cmdline").unwrap()),
-            ColorType::Color(*theme.palette.custom("cmdline_bg").unwrap()),
-        );
-        let mut command_line_input = EditView::new().filler(" ").style(style);
+        let mut command_line_input = EditView::new()
+            .filler(" ")
+            .style(Self::cmdline_style(&theme));
 
         let event_manager = ev.clone();
         // 1. When a search was submitted on the commandline...
@@ -93,6 +92,7 @@ impl Layout {
             screens: HashMap::new(),
             stack: HashMap::new(),
             statusbar: status.into_boxed_view(),
+            statusbar_height: 2,
             focus: None,
             cmdline: command_line_input,
             cmdline_focus: false,
@@ -105,6 +105,19 @@ impl Layout {
         }
     }
 
+    fn cmdline_style(theme: &Theme) -> ColorStyle {
+        ColorStyle::new(
+            ColorType::Color(*theme.palette.custom("cmdline").unwrap()),
+            ColorType::Color(*theme.palette.custom("cmdline_bg").unwrap()),
+        )
+    }
+
+    /// Update the colors of the command line and the error messages after the theme changed.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.cmdline.set_style(Self::cmdline_style(&theme));
+        self.theme = theme;
+    }
+
     pub fn enable_cmdline(&mut self, prefix: char) {
         if !self.cmdline_focus {
             self.cmdline.set_content(prefix);
@@ -135,6 +148,14 @@ impl Layout {
         self
     }
 
+    /// Replace the view of the screen `id`, keeping the views that were opened on top of it.
+    pub fn replace_screen<T: IntoBoxedViewExt>(&mut self, id: &str, view: T) {
+        if let Some(screen) = self.screens.get_mut(id) {
+            screen.on_leave();
+            *screen = view.into_boxed_view_ext();
+        }
+    }
+
     pub fn has_screen(&self, id: &str) -> bool {
         self.screens.contains_key(id)
     }
@@ -278,7 +299,16 @@ impl View for Layout {
             .map(|screen| screen.title())
             .unwrap_or_default();
 
-        if let Some(view) = self.get_top_view() {
+        // The rows left for the title and the screen content, which might be none at all in a
+        // very small terminal.
+        let content_height = printer
+            .size
+            .y
+            .saturating_sub(self.statusbar_height + cmdline_height);
+
+        if let Some(view) = self.get_top_view()
+            && content_height > 0
+        {
             // back button + title
             if !self.is_current_stack_empty() {
                 printer.with_color(ColorStyle::secondary(), |printer| {
@@ -298,19 +328,21 @@ impl View for Layout {
             });
 
             // screen content
-            let printer = &printer
-                .offset((0, 1))
-                .cropped((printer.size.x, printer.size.y - 3 - cmdline_height))
-                .focused(true);
-            view.draw(printer);
+            if content_height > 1 {
+                let printer = &printer
+                    .offset((0, 1))
+                    .cropped((printer.size.x, content_height - 1))
+                    .focused(true);
+                view.draw(printer);
+            }
         }
 
-        self.statusbar
-            .draw(&printer.offset((0, printer.size.y - 2 - cmdline_height)));
+        self.statusbar.draw(&printer.offset((0, content_height)));
 
+        let result_row = printer.size.y.saturating_sub(cmdline_height);
         if let Ok(Some(r)) = result {
-            printer.print_hline((0, printer.size.y - cmdline_height), printer.size.x, " ");
-            printer.print((0, printer.size.y - cmdline_height), &r);
+            printer.print_hline((0, result_row), printer.size.x, " ");
+            printer.print((0, result_row), &r);
         } else if let Err(e) = result {
             let style = ColorStyle::new(
                 ColorType::Color(*self.theme.palette.custom("error").unwrap()),
@@ -318,13 +350,13 @@ impl View for Layout {
             );
 
             printer.with_color(style, |printer| {
-                printer.print_hline((0, printer.size.y - cmdline_height), printer.size.x, " ");
-                printer.print((0, printer.size.y - cmdline_height), &format!("ERROR: {e}"));
+                printer.print_hline((0, result_row), printer.size.x, " ");
+                printer.print((0, result_row), &format!("ERROR: {e}"));
             });
         }
 
         if cmdline_visible {
-            let printer = &printer.offset((0, printer.size.y - 1));
+            let printer = &printer.offset((0, printer.size.y.saturating_sub(1)));
             self.cmdline.draw(printer);
         }
     }
@@ -332,12 +364,17 @@ impl View for Layout {
     fn layout(&mut self, size: Vec2) {
         self.last_size = size;
 
-        self.statusbar.layout(Vec2::new(size.x, 2));
+        self.statusbar_height = self.statusbar.required_size(size).y;
+        self.statusbar
+            .layout(Vec2::new(size.x, self.statusbar_height));
 
         self.cmdline.layout(Vec2::new(size.x, 1));
 
         if let Some(view) = self.get_current_view_mut() {
-            view.layout(Vec2::new(size.x, size.y - 3));
+            view.layout(Vec2::new(
+                size.x,
+                size.y.saturating_sub(1 + self.statusbar_height),
+            ));
         }
     }
 
@@ -417,12 +454,15 @@ impl View for Layout {
                     cmdline_height += 1;
                 }
 
-                if position.y >= self.last_size.y.saturating_sub(2 + cmdline_height)
-                    && position.y < self.last_size.y - cmdline_height
+                let statusbar_top = self
+                    .last_size
+                    .y
+                    .saturating_sub(self.statusbar_height + cmdline_height);
+                if position.y >= statusbar_top
+                    && position.y < self.last_size.y.saturating_sub(cmdline_height)
                 {
-                    self.statusbar.on_event(
-                        event.relativized(Vec2::new(0, self.last_size.y - 2 - cmdline_height)),
-                    );
+                    self.statusbar
+                        .on_event(event.relativized(Vec2::new(0, statusbar_top)));
                     return EventResult::consumed();
                 }
 
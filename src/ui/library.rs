@@ -6,10 +6,11 @@ use strum::IntoEnumIterator;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
-use crate::config::LibraryTab;
-use crate::library::Library;
+use crate::config::{DEFAULT_ALBUM_GRID, DEFAULT_HIDE_DISPLAY_NAMES, LibraryTab};
+use crate::library::{Library, ORDER_ALBUMS, ORDER_ARTISTS, ORDER_SHOWS, ORDER_TRACKS};
 use crate::queue::Queue;
 use crate::traits::ViewExt;
+use crate::ui::albumgrid::AlbumGridView;
 use crate::ui::browse::BrowseView;
 use crate::ui::listview::ListView;
 use crate::ui::playlists::PlaylistsView;
@@ -34,15 +35,31 @@ impl LibraryView {
             match tab {
                 LibraryTab::Tracks => tabview.add_tab(
                     "Tracks",
-                    ListView::new(library.tracks.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.tracks.clone(), queue.clone(), library.clone())
+                        .with_remembered_order(ORDER_TRACKS),
                 ),
+                LibraryTab::Albums
+                    if library
+                        .cfg
+                        .values()
+                        .album_grid
+                        .unwrap_or(DEFAULT_ALBUM_GRID) =>
+                {
+                    tabview.add_tab(
+                        "Albums",
+                        AlbumGridView::new(library.albums.clone(), queue.clone(), library.clone())
+                            .with_remembered_order(ORDER_ALBUMS),
+                    )
+                }
                 LibraryTab::Albums => tabview.add_tab(
                     "Albums",
-                    ListView::new(library.albums.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.albums.clone(), queue.clone(), library.clone())
+                        .with_remembered_order(ORDER_ALBUMS),
                 ),
                 LibraryTab::Artists => tabview.add_tab(
                     "Artists",
-                    ListView::new(library.artists.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.artists.clone(), queue.clone(), library.clone())
+                        .with_remembered_order(ORDER_ARTISTS),
                 ),
                 LibraryTab::Playlists => tabview.add_tab(
                     "Playlists",
@@ -50,7 +67,8 @@ impl LibraryView {
                 ),
                 LibraryTab::Podcasts => tabview.add_tab(
                     "Podcasts",
-                    ListView::new(library.shows.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.shows.clone(), queue.clone(), library.clone())
+                        .with_remembered_order(ORDER_SHOWS),
                 ),
                 LibraryTab::Browse => {
                     tabview.add_tab("Browse", BrowseView::new(queue.clone(), library.clone()))
@@ -61,7 +79,11 @@ impl LibraryView {
         Self {
             tabs: tabview,
             display_name: {
-                let hide_username = library.cfg.values().hide_display_names.unwrap_or(false);
+                let hide_username = library
+                    .cfg
+                    .values()
+                    .hide_display_names
+                    .unwrap_or(DEFAULT_HIDE_DISPLAY_NAMES);
                 if hide_username {
                     None
                 } else {
@@ -85,6 +107,10 @@ impl ViewExt for LibraryView {
         }
     }
 
+    fn title_sub(&self) -> String {
+        self.tabs.title_sub()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.tabs.on_command(s, cmd)
     }
@@ -11,9 +11,14 @@ use cursive::view::scroll;
 use cursive::{Cursive, Printer, Rect, Vec2, XY};
 use unicode_width::UnicodeWidthStr;
 
-use crate::command::{Command, GotoMode, InsertSource, JumpMode, MoveAmount, MoveMode, TargetMode};
+use crate::application::UserData;
+use crate::command::{
+    Command, GotoMode, InsertSource, JumpMode, MoveAmount, MoveMode, SortDirection, TargetMode,
+};
 use crate::commands::CommandResult;
+use crate::config::{Column, ColumnAlign, ColumnConfig, SortingOrder};
 use crate::ext_traits::CursiveExt;
+use crate::filter::{Field, Filter, Value};
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -26,7 +31,8 @@ use crate::queue::Queue;
 #[cfg(feature = "share_clipboard")]
 use crate::sharing::{read_share, write_share};
 use crate::spotify::UriType;
-use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::template;
+use crate::traits::{IntoBoxedViewExt, ListItem, Sortable, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
 use crate::ui::contextmenu::ContextMenu;
@@ -50,6 +56,20 @@ pub struct ListView<I: ListItem> {
     library: Arc<Library>,
     pagination: Pagination<I>,
     title: String,
+    /// The order the rows are sorted in, shown in the column header. The content itself is left
+    /// in its original order, as it may be shared with other views.
+    sort_order: Option<SortingOrder>,
+    /// The name under which the sort order of the list is remembered in the user state.
+    order_view: Option<String>,
+    /// The filter that items have to match to be shown.
+    filter: Option<Filter>,
+    /// The indices in `content` of the items that are shown, in the order they are shown in, if
+    /// the list is filtered or sorted.
+    rows: Option<Vec<usize>>,
+    /// The length of `content` when the rows were last computed.
+    rows_len: usize,
+    /// The [content generation](Self::content_generation) the rows were computed for.
+    rows_generation: (usize, usize, usize),
 }
 
 impl<I: ListItem> Scroller for ListView<I> {
@@ -62,7 +82,7 @@ impl<I: ListItem> Scroller for ListView<I> {
     }
 }
 
-impl<I: ListItem + Clone> ListView<I> {
+impl<I: ListItem + Sortable + Clone> ListView<I> {
     pub fn new(content: Arc<RwLock<Vec<I>>>, queue: Arc<Queue>, library: Arc<Library>) -> Self {
         let result = Self {
             content,
@@ -77,6 +97,12 @@ impl<I: ListItem + Clone> ListView<I> {
             library,
             pagination: Pagination::default(),
             title: "".to_string(),
+            sort_order: None,
+            order_view: None,
+            filter: None,
+            rows: None,
+            rows_len: 0,
+            rows_generation: (0, 0, 0),
         };
         result.try_paginate();
         result
@@ -87,6 +113,104 @@ impl<I: ListItem + Clone> ListView<I> {
         self
     }
 
+    /// Show the items sorted by `order`, which is indicated in the column header and remembered
+    /// if the list has a remembered order.
+    pub fn set_sort_order(&mut self, order: Option<SortingOrder>) {
+        if let (Some(view), Some(order)) = (&self.order_view, &order) {
+            self.library.cfg.with_state_mut(|state| {
+                state.view_orders.insert(view.clone(), order.clone());
+            });
+        }
+        self.sort_order = order;
+        self.refresh_rows();
+    }
+
+    /// Remember the sort order of the list in the user state as `view`, and sort the list by the
+    /// order that was remembered before.
+    pub fn with_remembered_order(mut self, view: &str) -> Self {
+        self.sort_order = self.library.cfg.state().view_orders.get(view).cloned();
+        self.order_view = Some(view.to_string());
+        self.refresh_rows();
+        self
+    }
+
+    /// Sort the list by `order`.
+    pub fn sort(&mut self, order: SortingOrder) {
+        self.set_sort_order(Some(order));
+    }
+
+    /// The columns `content` is shown in, if columns are configured and supported by the items.
+    fn columns(&self, content: &[I]) -> Option<Vec<ColumnConfig>> {
+        let columns = self.library.cfg.values().track_columns.clone()?;
+        content.first()?.column(Column::Title, &self.library)?;
+        Some(columns).filter(|columns| !columns.is_empty())
+    }
+
+    /// The number of rows above the content, which is 1 if a column header is shown.
+    fn header_height(&self) -> usize {
+        usize::from(self.columns(&self.content.read().unwrap()).is_some())
+    }
+
+    /// The width available to the rows, which excludes the scrollbar.
+    fn row_width(&self, width: usize) -> usize {
+        match self.scroller.content_viewport().width() {
+            0 => width,
+            row_width => min(row_width, width),
+        }
+    }
+
+    fn draw_header(&self, printer: &Printer<'_, '_>, columns: &[ColumnConfig]) {
+        let layout = column_layout(columns, self.row_width(printer.size.x));
+
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print_hline((0, 0), printer.size.x, " ");
+            for (column, (x, width)) in columns.iter().zip(layout) {
+                let mut label = column.column.label().to_string();
+                if let Some(order) = self
+                    .sort_order
+                    .as_ref()
+                    .filter(|order| column.column.sort_key().as_ref() == Some(&order.key))
+                {
+                    label.push_str(match order.direction {
+                        SortDirection::Ascending => " ▲",
+                        SortDirection::Descending => " ▼",
+                    });
+                }
+                printer.print((x, 0), &fit_cell(&label, width, column.align()));
+            }
+        });
+    }
+
+    /// Sort by the column at `x` in the header, reversing the direction if the list is already
+    /// sorted by it. The sort command is run through the command manager so that the view
+    /// containing the list can handle it.
+    fn click_header(&self, x: usize) -> EventResult {
+        let Some(columns) = self.columns(&self.content.read().unwrap()) else {
+            return EventResult::Ignored;
+        };
+        let layout = column_layout(&columns, self.row_width(self.last_size.x));
+        let Some(key) = columns
+            .iter()
+            .zip(layout)
+            .find(|(_, (offset, width))| x >= *offset && x < offset + width)
+            .and_then(|(column, _)| column.column.sort_key())
+        else {
+            return EventResult::consumed();
+        };
+
+        let direction = match self.sort_order.as_ref() {
+            Some(order) if order.key == key && order.direction == SortDirection::Ascending => {
+                SortDirection::Descending
+            }
+            _ => SortDirection::Ascending,
+        };
+        EventResult::Consumed(Some(Callback::from_fn_once(move |s| {
+            if let Some(data) = s.user_data::<UserData>().cloned() {
+                data.cmd.handle(s, Command::Sort(vec![key], direction));
+            }
+        })))
+    }
+
     pub fn get_pagination(&self) -> &Pagination<I> {
         &self.pagination
     }
@@ -96,7 +220,7 @@ impl<I: ListItem + Clone> ListView<I> {
     /// If `include_paginator` is `true`, the pagination entry will be included
     /// in the count.
     pub fn content_len(&self, include_paginator: bool) -> usize {
-        let content_len = self.content.read().unwrap().len();
+        let content_len = self.visible_len(&self.content.read().unwrap());
 
         // add 1 more row for paginator if we can paginate
         if self.can_paginate() && include_paginator {
@@ -123,7 +247,7 @@ impl<I: ListItem + Clone> ListView<I> {
         //    OR
         //   The scroller can't scroll further down (mouse scrolling)
         if self.can_paginate()
-            && (self.selected == self.content.read().unwrap().len().saturating_sub(1)
+            && (self.selected == self.content_len(false).saturating_sub(1)
                 || !self.scroller.can_scroll_down())
         {
             self.pagination.call(&self.content, self.library.clone());
@@ -135,24 +259,124 @@ impl<I: ListItem + Clone> ListView<I> {
         self.scroller.get_show_scrollbars()
     }
 
+    /// The index in the content of the selected item.
     pub fn get_selected_index(&self) -> usize {
-        self.selected
+        self.item_index(self.selected)
     }
 
+    /// The rows of the items that contain `query`.
     pub fn get_indexes_of(&self, query: &str) -> Vec<usize> {
         let content = self.content.read().unwrap();
-        content
-            .iter()
-            .enumerate()
-            .filter(|(_, i)| {
-                i.display_left(&self.library)
-                    .to_lowercase()
-                    .contains(&query[..].to_lowercase())
+        (0..self.visible_len(&content))
+            .filter(|row| {
+                content.get(self.item_index(*row)).is_some_and(|i| {
+                    i.display_left(&self.library)
+                        .to_lowercase()
+                        .contains(&query[..].to_lowercase())
+                })
             })
-            .map(|(i, _)| i)
             .collect()
     }
 
+    /// The index in the content of the item that is shown in `row`. Rows past the end of a
+    /// filtered list return an index past the end of the content.
+    pub fn item_index(&self, row: usize) -> usize {
+        match &self.rows {
+            Some(rows) => rows.get(row).copied().unwrap_or(usize::MAX),
+            None => row,
+        }
+    }
+
+    /// The row that shows the item at `index` in the content, unless it is filtered out.
+    pub fn row_of(&self, index: usize) -> Option<usize> {
+        match &self.rows {
+            Some(rows) => rows.iter().position(|i| *i == index),
+            None => Some(index),
+        }
+    }
+
+    /// The number of rows that show items of `content`.
+    fn visible_len(&self, content: &[I]) -> usize {
+        self.rows.as_ref().map_or(content.len(), Vec::len)
+    }
+
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// Only show the items that match `filter`, or all items if it is [None].
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+        self.search_indexes.clear();
+        self.refresh_rows();
+    }
+
+    /// Changes whenever the content might have changed: when the library or the queue changed, or
+    /// when the number of items is different, e.g. after another page was loaded.
+    fn content_generation(&self) -> (usize, usize, usize) {
+        (
+            self.library.generation(),
+            self.queue.generation(),
+            self.content.read().unwrap().len(),
+        )
+    }
+
+    /// Find the items that match the filter and sort them again, e.g. after the content changed.
+    fn refresh_rows(&mut self) {
+        let (rows, len) = {
+            let content = self.content.read().unwrap();
+            let mut rows: Option<Vec<usize>> = self.filter.as_ref().map(|filter| {
+                content
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| filter.matches(&|field| self.filter_value(item, field)))
+                    .map(|(index, _)| index)
+                    .collect()
+            });
+            if let Some(order) = self.sort_order.as_ref() {
+                let state = self.library.cfg.state();
+                rows.get_or_insert_with(|| (0..content.len()).collect())
+                    .sort_by(|a, b| {
+                        order.compare(|key| {
+                            content[*a].compare(&content[*b], key, &state.play_counts)
+                        })
+                    });
+            }
+            (rows, content.len())
+        };
+        self.rows = rows;
+        self.rows_len = len;
+        self.rows_generation = self.content_generation();
+        self.move_focus_to(self.selected);
+    }
+
+    /// The value of `field` of `item` for filtering.
+    fn filter_value(&self, item: &I, field: Field) -> Option<Value> {
+        match field {
+            // Items that aren't tracks or albums can at least be filtered by their name.
+            Field::Title => item
+                .filter_value(field)
+                .or_else(|| Some(Value::Text(item.display_left(&self.library)))),
+            _ => self.library.filter_value(item, field),
+        }
+    }
+
+    /// A description of the active filter and the number of items matching it.
+    fn filter_summary(&self) -> Option<String> {
+        let filter = self.filter.as_ref()?;
+        let matching = self.rows.as_ref().map_or(0, Vec::len);
+        Some(format!("filter: {filter} ({matching}/{})", self.rows_len))
+    }
+
+    /// Add the active filter to `title_sub`, the subtitle of the view that contains the list.
+    pub fn title_sub_with_filter(&self, title_sub: String) -> String {
+        match self.filter_summary() {
+            Some(summary) if !title_sub.is_empty() => format!("{title_sub} | {summary}"),
+            Some(summary) => summary,
+            None => title_sub,
+        }
+    }
+
     pub fn move_focus_to(&mut self, target: usize) {
         let len = self.content_len(false).saturating_sub(1);
         self.selected = min(target, len);
@@ -177,7 +401,13 @@ impl<I: ListItem + Clone> ListView<I> {
                 .collect::<Vec<Playable>>()
         });
         if let Some(tracks) = playables.or(tracks.as_ref()) {
-            let index = self.queue.append_next(tracks);
+            // Only the tracks that match the filter are played, in the order they are shown in.
+            let visible: Option<Vec<Playable>> = self.rows.as_ref().map(|rows| {
+                rows.iter()
+                    .filter_map(|i| tracks.get(*i).cloned())
+                    .collect()
+            });
+            let index = self.queue.append_next(visible.as_ref().unwrap_or(tracks));
             self.queue.play(index + self.selected, true, false);
             true
         } else {
@@ -188,7 +418,7 @@ impl<I: ListItem + Clone> ListView<I> {
     /// Appends the currently focused item after the currently playing item and starts playing it.
     fn play_current_item(&mut self) {
         let mut content = self.content.write().unwrap();
-        if let Some(listitem) = content.get_mut(self.selected) {
+        if let Some(listitem) = content.get_mut(self.item_index(self.selected)) {
             listitem.play(&self.queue);
         }
     }
@@ -199,10 +429,15 @@ impl<I: ListItem + Clone> ListView<I> {
     ///
     /// Panics if `index` is out of bounds.
     pub fn remove(&mut self, index: usize) {
-        let mut c = self.content.write().unwrap();
-        c.remove(index);
-        if self.selected >= c.len() {
-            self.selected = self.selected.saturating_sub(1);
+        {
+            let mut c = self.content.write().unwrap();
+            c.remove(index);
+            if self.selected >= c.len() {
+                self.selected = self.selected.saturating_sub(1);
+            }
+        }
+        if self.rows.is_some() {
+            self.refresh_rows();
         }
     }
 
@@ -226,6 +461,8 @@ impl<I: ListItem + Clone> ListView<I> {
     /// Returns a MouseHandleResult which indicates whether the event has been handled by
     /// the function or if a command needs further processing.
     pub fn handle_mouse_event(&mut self, e: Event) -> MouseHandleResult {
+        // Rows are shifted down by the column header.
+        let header = Vec2::new(0, self.header_height());
         match e {
             Event::Mouse {
                 event: MouseEvent::WheelUp,
@@ -246,6 +483,13 @@ impl<I: ListItem + Clone> ListView<I> {
                 // This is safe as a mouse event is only propagated to a view when it is inside the
                 // view. Therefore underflow shouldn't occur.
                 let view_coordinates_click_position = position - offset;
+                if view_coordinates_click_position.y < header.y {
+                    return MouseHandleResult::Handled(
+                        self.click_header(view_coordinates_click_position.x),
+                    );
+                }
+                let view_coordinates_click_position = view_coordinates_click_position - header;
+                let offset = offset + header;
 
                 let drag_started = if self.has_visible_scrollbars() {
                     self.scroller.start_drag(view_coordinates_click_position)
@@ -260,7 +504,7 @@ impl<I: ListItem + Clone> ListView<I> {
                         .content
                         .read()
                         .unwrap()
-                        .get(clicked_row_index)
+                        .get(self.item_index(clicked_row_index))
                         .map(ListItem::as_listitem);
                     let currently_selected_is_individual = currently_selected_listitem
                         .filter(|item| item.track().is_some())
@@ -272,8 +516,9 @@ impl<I: ListItem + Clone> ListView<I> {
                         // that can be opened.
                         self.move_focus_to(clicked_row_index);
                         let content = self.content.read().unwrap();
-                        let clicked_list_item =
-                            content.get(self.selected).map(ListItem::as_listitem);
+                        let clicked_list_item = content
+                            .get(self.item_index(self.selected))
+                            .map(ListItem::as_listitem);
 
                         if let Some(target) = clicked_list_item
                             && let Some(view) =
@@ -293,14 +538,16 @@ impl<I: ListItem + Clone> ListView<I> {
                 position,
                 offset,
             } => {
-                if let Some(y) = self.get_selected_row(position, offset) {
+                if let Some(y) = self.get_selected_row(position, offset + header) {
                     self.move_focus_to(y);
 
                     let queue = self.queue.clone();
                     let library = self.library.clone();
                     if let Some(target) = {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.item_index(self.selected))
+                            .map(|t| t.as_listitem())
                     } {
                         let contextmenu = ContextMenu::new(&*target, queue, library);
                         return MouseHandleResult::Handled(EventResult::Consumed(Some(
@@ -315,7 +562,7 @@ impl<I: ListItem + Clone> ListView<I> {
                 offset,
             } => {
                 if self.has_visible_scrollbars() {
-                    self.scroller.drag(position.saturating_sub(offset));
+                    self.scroller.drag(position.saturating_sub(offset + header));
                 }
             }
             Event::Mouse {
@@ -334,26 +581,36 @@ impl<I: ListItem + Clone> ListView<I> {
     }
 }
 
-impl<I: ListItem + Clone> View for ListView<I> {
+impl<I: ListItem + Sortable + Clone> View for ListView<I> {
     fn draw(&self, printer: &Printer<'_, '_>) {
         let content = self.content.read().unwrap();
+        let columns = self.columns(&content);
+
+        let printer = match columns.as_ref() {
+            Some(columns) => {
+                self.draw_header(printer, columns);
+                printer.offset((0, 1))
+            }
+            None => printer.clone(),
+        };
 
-        scroll::draw_lines(self, printer, |_, printer, i| {
+        let rows = self.visible_len(&content);
+        scroll::draw_lines(self, &printer, |_, printer, i| {
             // draw paginator after content
-            if i == content.len() && self.can_paginate() {
+            if i == rows && self.can_paginate() {
                 let style = ColorStyle::secondary();
 
                 let max = self.pagination.max_content().unwrap();
-                let buf = format!("{} more items, scroll to load", max - i);
+                let buf = format!("{} more items, scroll to load", max - content.len());
                 printer.with_color(style, |printer| {
                     printer.print((0, 0), &buf);
                 });
-            } else if i < content.len() {
-                let item = &content[i];
-                let currently_playing =
-                    item.is_playing(&self.queue) && self.queue.get_current_index() == Some(i);
+            } else if let Some(item) = content.get(self.item_index(i)).filter(|_| i < rows) {
+                let currently_playing = item.is_playing(&self.queue)
+                    && self.queue.get_current_index() == Some(self.item_index(i));
                 let is_local = item.track().map(|t| t.is_local).unwrap_or_default();
                 let is_playable = item.track().map(|t| t.is_playable).unwrap_or_default();
+                let is_autoplay = item.track().map(|t| t.autoplay).unwrap_or_default();
 
                 let style = if self.selected == i {
                     if currently_playing {
@@ -376,20 +633,38 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     )
                 } else if is_local || is_playable == Some(false) {
                     ColorStyle::secondary()
+                } else if is_autoplay {
+                    ColorStyle::new(
+                        ColorType::Color(*printer.theme.palette.custom("autoplay").unwrap()),
+                        ColorType::Palette(PaletteColor::View),
+                    )
                 } else {
                     ColorStyle::primary()
                 };
 
-                let left = item.display_left(&self.library);
-                let center = item.display_center(&self.library);
-                let right = item.display_right(&self.library);
-                let draw_center = !center.is_empty();
-
                 // Fill the entire row with background color first
                 printer.with_color(style, |printer| {
                     printer.print_hline((0, 0), printer.size.x, " ");
                 });
 
+                if let Some(columns) = columns.as_ref() {
+                    let layout = column_layout(columns, printer.size.x);
+                    printer.with_color(style, |printer| {
+                        for (column, (x, width)) in columns.iter().zip(layout) {
+                            let text = item
+                                .column(column.column, &self.library)
+                                .unwrap_or_default();
+                            printer.print((x, 0), &fit_cell(&text, width, column.align()));
+                        }
+                    });
+                    return;
+                }
+
+                let left = item.display_left(&self.library);
+                let center = item.display_center(&self.library);
+                let right = item.display_right(&self.library);
+                let draw_center = !center.is_empty();
+
                 // draw left string
                 printer.with_color(style, |printer| {
                     printer.print((0, 0), &left);
@@ -458,6 +733,10 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn layout(&mut self, size: Vec2) {
         self.last_size = size;
+        if self.rows.is_some() && self.content_generation() != self.rows_generation {
+            self.refresh_rows();
+        }
+        let size = size.saturating_sub((0, self.header_height()));
 
         let relayout_scroller = self.content_len(false) != self.last_content_len;
         self.last_content_len = self.content_len(true);
@@ -494,18 +773,22 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn important_area(&self, view_size: Vec2) -> Rect {
         if self.content_len(false) > 0 {
-            Rect::from_point((view_size.x, self.selected))
+            Rect::from_point((view_size.x, self.selected + self.header_height()))
         } else {
             Rect::from_point((0, 0))
         }
     }
 }
 
-impl<I: ListItem + Clone> ViewExt for ListView<I> {
+impl<I: ListItem + Sortable + Clone> ViewExt for ListView<I> {
     fn title(&self) -> String {
         self.title.clone()
     }
 
+    fn title_sub(&self) -> String {
+        self.title_sub_with_filter(String::new())
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Play => {
@@ -515,7 +798,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::PlayNext => {
                 info!("played next");
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.item_index(self.selected)) {
                     item.play_next(&self.queue);
                 }
 
@@ -523,7 +806,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             }
             Command::Queue => {
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.item_index(self.selected)) {
                     item.queue(&self.queue);
                 }
 
@@ -532,7 +815,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Save => {
                 let mut item = {
                     let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
+                    content.get(self.item_index(self.selected)).cloned()
                 };
 
                 if let Some(item) = item.as_mut() {
@@ -544,17 +827,13 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Add => {
                 let item = {
                     let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
+                    content.get(self.item_index(self.selected)).cloned()
                 };
 
                 if let Some(track) = item
                     && let Some(track) = track.track()
                 {
-                    let dialog = ContextMenu::add_track_dialog(
-                        self.library.clone(),
-                        self.queue.get_spotify(),
-                        track,
-                    );
+                    let dialog = ContextMenu::add_track_dialog(self.library.clone(), track);
                     return Ok(CommandResult::Modal(Box::new(dialog)));
                 }
 
@@ -563,7 +842,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Delete => {
                 let mut item = {
                     let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
+                    content.get(self.item_index(self.selected)).cloned()
                 };
 
                 if let Some(item) = item.as_mut() {
@@ -576,7 +855,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Share(mode) => {
                 let url = match mode {
                     TargetMode::Selected => self.content.read().ok().and_then(|content| {
-                        content.get(self.selected).and_then(ListItem::share_url)
+                        content
+                            .get(self.item_index(self.selected))
+                            .and_then(ListItem::share_url)
                     }),
                     TargetMode::Current => self
                         .queue
@@ -590,6 +871,29 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
 
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::Filter(expression) => {
+                let filter = match expression {
+                    Some(expression) => {
+                        let views = self
+                            .library
+                            .cfg
+                            .values()
+                            .filters
+                            .clone()
+                            .unwrap_or_default();
+                        Some(Filter::parse(expression, &|name| views.get(name).cloned())?)
+                    }
+                    None => None,
+                };
+                self.set_filter(filter);
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Sort(keys, direction) => {
+                if let Some(order) = SortingOrder::new(keys, direction) {
+                    self.sort(order);
+                }
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Jump(mode) => match mode {
                 JumpMode::Query(query) => {
                     self.search_query = query.to_lowercase();
@@ -633,7 +937,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                 }
             },
             Command::Move(mode, amount) => {
-                let last_idx = self.content.read().unwrap().len().saturating_sub(1);
+                let last_idx = self.content_len(false).saturating_sub(1);
 
                 match mode {
                     MoveMode::Up => {
@@ -673,7 +977,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     TargetMode::Current => self.queue.get_current().map(|t| t.as_listitem()),
                     TargetMode::Selected => {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.item_index(self.selected))
+                            .map(|t| t.as_listitem())
                     }
                 };
 
@@ -691,7 +997,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             }
             Command::Goto(mode) => {
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.item_index(self.selected)) {
                     let queue = self.queue.clone();
                     let library = self.library.clone();
 
@@ -793,7 +1099,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     TargetMode::Current => self.queue.get_current().map(|t| t.as_listitem()),
                     TargetMode::Selected => {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.item_index(self.selected))
+                            .map(|t| t.as_listitem())
                     }
                 };
 
@@ -811,3 +1119,81 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
         Ok(CommandResult::Ignored)
     }
 }
+
+/// Compute the offset and width of every column in a row that is `width` cells wide. Columns are
+/// separated by a single space and columns without a fixed width share the remaining space.
+fn column_layout(columns: &[ColumnConfig], width: usize) -> Vec<(usize, usize)> {
+    let separators = columns.len().saturating_sub(1);
+    let fixed: usize = columns.iter().filter_map(ColumnConfig::width).sum();
+    let mut flexible = columns.iter().filter(|c| c.width().is_none()).count();
+    let mut remaining = width.saturating_sub(fixed + separators);
+    let mut offset = 0;
+
+    columns
+        .iter()
+        .map(|column| {
+            let column_width = column.width().unwrap_or_else(|| {
+                let share = remaining / flexible;
+                remaining -= share;
+                flexible -= 1;
+                share
+            });
+            let column_width = min(column_width, width - offset);
+            let layout = (offset, column_width);
+            offset = min(offset + column_width + 1, width);
+            layout
+        })
+        .collect()
+}
+
+/// Fit `text` into a cell that is `width` cells wide, truncating it if it is too long.
+fn fit_cell(text: &str, width: usize, align: ColumnAlign) -> String {
+    let text = template::truncate(text, width);
+    let padding = width - text.width();
+    match align {
+        ColumnAlign::Left => text + &" ".repeat(padding),
+        ColumnAlign::Right => " ".repeat(padding) + &text,
+        ColumnAlign::Center => " ".repeat(padding / 2) + &text + &" ".repeat(padding - padding / 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(column: Column, width: Option<usize>) -> ColumnConfig {
+        ColumnConfig {
+            column,
+            width,
+            align: None,
+        }
+    }
+
+    #[test]
+    fn flexible_columns_share_remaining_space() {
+        let columns = [
+            column(Column::TrackNumber, None),
+            column(Column::Title, None),
+            column(Column::Album, None),
+            column(Column::Duration, Some(5)),
+        ];
+        assert_eq!(
+            column_layout(&columns, 40),
+            vec![(0, 3), (4, 14), (19, 15), (35, 5)]
+        );
+    }
+
+    #[test]
+    fn columns_are_cut_off_at_the_edge() {
+        let columns = [column(Column::Added, None), column(Column::Duration, None)];
+        assert_eq!(column_layout(&columns, 14), vec![(0, 10), (11, 3)]);
+    }
+
+    #[test]
+    fn cells_are_aligned_and_truncated() {
+        assert_eq!(fit_cell("abc", 5, ColumnAlign::Left), "abc  ");
+        assert_eq!(fit_cell("abc", 5, ColumnAlign::Right), "  abc");
+        assert_eq!(fit_cell("abc", 6, ColumnAlign::Center), " abc  ");
+        assert_eq!(fit_cell("abcdef", 4, ColumnAlign::Left), "abc…");
+    }
+}
@@ -2,9 +2,11 @@ use cursive::{Cursive, CursiveRunner};
 use ncspot::BIN_NAME;
 
 pub mod album;
+pub mod albumgrid;
 pub mod artist;
 pub mod browse;
 pub mod contextmenu;
+pub mod devices;
 pub mod help;
 pub mod layout;
 pub mod library;
@@ -22,6 +24,8 @@ pub mod tabbedview;
 
 #[cfg(feature = "cover")]
 pub mod cover;
+#[cfg(feature = "cover")]
+pub mod graphics;
 
 /// Create a CursiveRunner which implements the drawing logic and event loop.
 pub fn create_cursive() -> Result<CursiveRunner<Cursive>, Box<dyn std::error::Error>> {
@@ -5,10 +5,10 @@ use cursive::view::ViewWrapper;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::config::SortingOrder;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
-use crate::command::SortDirection;
 use crate::queue::Queue;
 use crate::spotify::Spotify;
 
@@ -28,8 +28,14 @@ impl PlaylistView {
         let mut playlist = playlist.clone();
         playlist.load_tracks(&queue.get_spotify());
 
-        if let Some(order) = library.cfg.state().playlist_orders.get(&playlist.id) {
-            playlist.sort(&order.key, &order.direction);
+        let order = library
+            .cfg
+            .state()
+            .playlist_orders
+            .get(&playlist.id)
+            .cloned();
+        if let Some(order) = order {
+            playlist.sort(&order, &library);
         }
 
         let tracks = if let Some(t) = playlist.tracks.as_ref() {
@@ -39,11 +45,19 @@ impl PlaylistView {
         };
 
         let spotify = queue.get_spotify();
-        let list = ListView::new(
+        let mut list = ListView::new(
             Arc::new(RwLock::new(tracks)),
             queue.clone(),
             library.clone(),
         );
+        list.set_sort_order(
+            library
+                .cfg
+                .state()
+                .playlist_orders
+                .get(&playlist.id)
+                .cloned(),
+        );
 
         Self {
             playlist,
@@ -53,6 +67,26 @@ impl PlaylistView {
             queue,
         }
     }
+
+    /// Recreate the list from the tracks of the playlist.
+    fn reload_list(&mut self) {
+        let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
+        let filter = self.list.filter().cloned();
+        self.list = ListView::new(
+            Arc::new(RwLock::new(tracks)),
+            self.queue.clone(),
+            self.library.clone(),
+        );
+        self.list.set_sort_order(
+            self.library
+                .cfg
+                .state()
+                .playlist_orders
+                .get(&self.playlist.id)
+                .cloned(),
+        );
+        self.list.set_filter(filter);
+    }
 }
 
 impl ViewWrapper for PlaylistView {
@@ -65,7 +99,7 @@ impl ViewExt for PlaylistView {
     }
 
     fn title_sub(&self) -> String {
-        if let Some(tracks) = self.playlist.tracks.as_ref() {
+        let title_sub = if let Some(tracks) = self.playlist.tracks.as_ref() {
             let duration_secs = tracks.iter().map(|p| p.duration() as u64 / 1000).sum();
             let duration = std::time::Duration::from_secs(duration_secs);
             format!(
@@ -75,25 +109,42 @@ impl ViewExt for PlaylistView {
             )
         } else {
             "".to_string()
-        }
+        };
+        self.list.title_sub_with_filter(title_sub)
     }
 
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         if let Command::UpdateLibrary = cmd {
-            // Force re-fetch tracks from Spotify for the current playlist view.
-            self.playlist.tracks = None;
-            self.playlist.load_tracks(&self.spotify);
+            if self.playlist.smart {
+                // Smart playlists are put together from the library when it is updated, so the
+                // tracks they have at the moment are shown.
+                let updated = self
+                    .library
+                    .playlists
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .find(|p| p.id == self.playlist.id)
+                    .and_then(|p| p.tracks.clone());
+                self.playlist.tracks = updated.or(self.playlist.tracks.take());
+            } else {
+                // Force re-fetch tracks from Spotify for the current playlist view.
+                self.playlist.tracks = None;
+                self.playlist.load_tracks(&self.spotify);
+            }
 
-            if let Some(order) = self.library.cfg.state().playlist_orders.get(&self.playlist.id) {
-                self.playlist.sort(&order.key, &order.direction);
+            let order = self
+                .library
+                .cfg
+                .state()
+                .playlist_orders
+                .get(&self.playlist.id)
+                .cloned();
+            if let Some(order) = order {
+                self.playlist.sort(&order, &self.library);
             }
 
-            let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+            self.reload_list();
 
             // Return Ignored so the default handler also runs library.update_library()
             return Ok(CommandResult::Ignored);
@@ -101,6 +152,10 @@ impl ViewExt for PlaylistView {
 
         if let Command::Delete = cmd {
             let pos = self.list.get_selected_index();
+            // The filter of the list may hide all items.
+            if pos >= self.playlist.tracks.as_ref().map_or(0, Vec::len) {
+                return Ok(CommandResult::Consumed(None));
+            }
 
             return if self
                 .playlist
@@ -115,30 +170,22 @@ impl ViewExt for PlaylistView {
 
         if let Command::ReverseOrder = cmd {
             // If the playlist has a persisted sort order, flip its direction and re-sort.
-            if let Some(order) = self
+            let order = self
                 .library
                 .cfg
                 .state()
                 .playlist_orders
                 .get(&self.playlist.id)
-                .cloned()
-            {
-                let new_direction = match order.direction {
-                    SortDirection::Ascending => SortDirection::Descending,
-                    SortDirection::Descending => SortDirection::Ascending,
-                };
-
+                .cloned();
+            if let Some(order) = order {
+                let updated = order.reversed();
                 self.library.cfg.with_state_mut(|state| {
-                    let updated = crate::config::SortingOrder {
-                        key: order.key.clone(),
-                        direction: new_direction.clone(),
-                    };
                     state
                         .playlist_orders
-                        .insert(self.playlist.id.clone(), updated);
+                        .insert(self.playlist.id.clone(), updated.clone());
                 });
 
-                self.playlist.sort(&order.key, &new_direction);
+                self.playlist.sort(&updated, &self.library);
             } else {
                 // No persisted sort: just reverse the in-memory track list.
                 if let Some(tracks) = self.playlist.tracks.as_mut() {
@@ -146,33 +193,21 @@ impl ViewExt for PlaylistView {
                 }
             }
 
-            let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+            self.reload_list();
             return Ok(CommandResult::Consumed(None));
         }
 
-        if let Command::Sort(key, direction) = cmd {
+        if let Command::Sort(keys, direction) = cmd
+            && let Some(order) = SortingOrder::new(keys, direction)
+        {
             self.library.cfg.with_state_mut(|state| {
-                let order = crate::config::SortingOrder {
-                    key: key.clone(),
-                    direction: direction.clone(),
-                };
                 state
                     .playlist_orders
-                    .insert(self.playlist.id.clone(), order);
+                    .insert(self.playlist.id.clone(), order.clone());
             });
 
-            self.playlist.sort(key, direction);
-            let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+            self.playlist.sort(&order, &self.library);
+            self.reload_list();
             return Ok(CommandResult::Consumed(None));
         }
 
@@ -30,7 +30,8 @@ impl PlaylistsView {
         let playlists = self.library.playlists.read().unwrap();
         let current = playlists.get(self.list.get_selected_index());
 
-        if let Some(playlist) = current {
+        // Smart playlists are removed from the configuration instead.
+        if let Some(playlist) = current.filter(|playlist| !playlist.smart) {
             let library = self.library.clone();
             let id = playlist.id.clone();
             let dialog = Dialog::text("Are you sure you want to delete this playlist?")
@@ -8,6 +8,7 @@ use std::sync::Arc;
 
 use crate::command::{Command, MoveMode, ShiftMode};
 use crate::commands::CommandResult;
+use crate::config::SortingOrder;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
@@ -69,7 +70,13 @@ impl QueueView {
         let mut list_select: SelectView<Option<String>> = SelectView::new().autojump();
         list_select.add_item("[Create new]", None);
 
-        for list in library.playlists.read().unwrap().iter() {
+        for list in library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|l| !l.smart)
+        {
             list_select.add_item(list.name.clone(), Some(list.id.clone()));
         }
 
@@ -122,7 +129,7 @@ impl ViewExt for QueueView {
             .map(|p| p.duration() as u64 / 1000)
             .sum();
 
-        if duration_secs > 0 {
+        let title_sub = if duration_secs > 0 {
             let duration = std::time::Duration::from_secs(duration_secs);
             format!(
                 "{} tracks, {}",
@@ -131,7 +138,8 @@ impl ViewExt for QueueView {
             )
         } else {
             "".to_string()
-        }
+        };
+        self.list.title_sub_with_filter(title_sub)
     }
 
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
@@ -146,9 +154,21 @@ impl ViewExt for QueueView {
             Command::Queue => {
                 return Ok(CommandResult::Ignored);
             }
+            Command::Sort(keys, direction) => {
+                // Sorting changes the playback order, so it isn't remembered like the order of
+                // other lists.
+                if let Some(order) = SortingOrder::new(keys, direction) {
+                    self.queue.sort(&order);
+                }
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Delete => {
                 let selected = self.list.get_selected_index();
                 let len = self.queue.len();
+                // The filter of the list may hide all items.
+                if selected >= len && len > 0 {
+                    return Ok(CommandResult::Consumed(None));
+                }
 
                 self.queue.remove(selected);
                 if selected == len.saturating_sub(1) {
@@ -168,25 +188,28 @@ impl ViewExt for QueueView {
                     m => m,
                 };
 
-                let amount = amount.abs();
-
-                let selected = self.list.get_selected_index();
-                let len = self.queue.len();
-
-                match mode {
-                    ShiftMode::Up if selected > 0 => {
-                        self.queue
-                            .shift(selected, selected.saturating_sub(amount as usize));
-                        self.list.move_focus(-amount);
-                        return Ok(CommandResult::Consumed(None));
-                    }
-                    ShiftMode::Down if selected < len.saturating_sub(1) => {
-                        self.queue
-                            .shift(selected, min(selected + amount as usize, len - 1));
-                        self.list.move_focus(amount);
-                        return Ok(CommandResult::Consumed(None));
-                    }
-                    _ => {}
+                let amount = amount.unsigned_abs() as usize;
+
+                // The item is moved past `amount` rows, which are not next to each other in the
+                // queue if the list is filtered.
+                let rows = self.list.content_len(false);
+                let target = self
+                    .list
+                    .row_of(self.list.get_selected_index())
+                    .filter(|row| *row < rows)
+                    .and_then(|row| match mode {
+                        ShiftMode::Up if row > 0 => Some((row, row.saturating_sub(amount))),
+                        ShiftMode::Down if row + 1 < rows => {
+                            Some((row, min(row + amount, rows - 1)))
+                        }
+                        _ => None,
+                    });
+
+                if let Some((row, target)) = target {
+                    self.queue
+                        .shift(self.list.item_index(row), self.list.item_index(target));
+                    self.list.move_focus_to(target);
+                    return Ok(CommandResult::Consumed(None));
                 }
             }
             Command::SaveQueue => {
@@ -195,8 +218,12 @@ impl ViewExt for QueueView {
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Move(MoveMode::Playing, _) => {
-                if let Some(playing) = self.queue.get_current_index() {
-                    self.list.move_focus_to(playing);
+                if let Some(row) = self
+                    .queue
+                    .get_current_index()
+                    .and_then(|playing| self.list.row_of(playing))
+                {
+                    self.list.move_focus_to(row);
                 }
                 return Ok(CommandResult::Consumed(None));
             }
@@ -1,17 +1,21 @@
 use std::sync::Arc;
 
 use cursive::Printer;
-use cursive::align::HAlign;
 use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
 use cursive::theme::{ColorStyle, ColorType, PaletteColor};
 use cursive::traits::View;
 use cursive::vec::Vec2;
 use unicode_width::UnicodeWidthStr;
 
+use crate::config::{
+    DEFAULT_CONNECT_NAME, DEFAULT_FLIP_STATUS_INDICATORS, DEFAULT_STATUSBAR_FORMAT,
+    DEFAULT_USE_NERDFONT, StatusbarTemplate,
+};
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::{Queue, RepeatSetting};
 use crate::spotify::{PlayerEvent, Spotify};
+use crate::template;
 use crate::utils::ms_to_hms;
 
 pub struct StatusBar {
@@ -34,7 +38,11 @@ impl StatusBar {
     }
 
     fn use_nerdfont(&self) -> bool {
-        self.library.cfg.values().use_nerdfont.unwrap_or(false)
+        self.library
+            .cfg
+            .values()
+            .use_nerdfont
+            .unwrap_or(DEFAULT_USE_NERDFONT)
     }
 
     fn playback_indicator(&self) -> &str {
@@ -45,7 +53,7 @@ impl StatusBar {
             .cfg
             .values()
             .flip_status_indicators
-            .unwrap_or(false);
+            .unwrap_or(DEFAULT_FLIP_STATUS_INDICATORS);
 
         const NF_PLAY: &str = "\u{f04b} ";
         const NF_PAUSE: &str = "\u{f04c} ";
@@ -64,11 +72,8 @@ impl StatusBar {
         }
     }
 
-    fn volume_display(&self) -> String {
-        format!(
-            " [{}%]",
-            (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
-        )
+    fn volume_percent(&self) -> u16 {
+        (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
     }
 
     fn format_track(&self, t: &Playable) -> String {
@@ -78,9 +83,105 @@ impl StatusBar {
             .values()
             .statusbar_format
             .clone()
-            .unwrap_or_else(|| "%artists - %title".to_string());
+            .unwrap_or_else(|| DEFAULT_STATUSBAR_FORMAT.to_string());
         Playable::format(t, &format, &self.library)
     }
+
+    fn template(&self) -> StatusbarTemplate {
+        self.library
+            .cfg
+            .values()
+            .statusbar
+            .clone()
+            .unwrap_or_else(StatusbarTemplate::default)
+    }
+
+    fn show_progress(&self) -> bool {
+        self.template().progress.unwrap_or(true)
+    }
+
+    /// The value of the statusbar placeholder `name`, or [None] if it isn't a known placeholder.
+    fn placeholder(
+        &self,
+        name: &str,
+        argument: Option<&str>,
+        current: Option<&Playable>,
+    ) -> Option<String> {
+        let nerdfont = self.use_nerdfont();
+        let elapsed_ms = self.spotify.get_current_progress().as_millis() as u32;
+        let value = match name {
+            "indicator" => self.playback_indicator().to_string(),
+            "track" => current.map(|t| self.format_track(t)).unwrap_or_default(),
+            "offline" => match (self.spotify.is_offline(), nerdfont) {
+                (false, _) => String::new(),
+                (true, true) => "\u{f05aa}".to_string(),
+                (true, false) => "[offline]".to_string(),
+            },
+            "updating" => match (*self.library.is_done.read().unwrap(), nerdfont) {
+                (true, _) => String::new(),
+                (false, true) => "\u{f04e6}".to_string(),
+                (false, false) => "[U]".to_string(),
+            },
+            "repeat" => match (self.queue.get_repeat(), nerdfont) {
+                (RepeatSetting::None, _) => String::new(),
+                (RepeatSetting::RepeatPlaylist, true) => "\u{f0456}".to_string(),
+                (RepeatSetting::RepeatTrack, true) => "\u{f0458}".to_string(),
+                (RepeatSetting::RepeatPlaylist, false) => "[R]".to_string(),
+                (RepeatSetting::RepeatTrack, false) => "[R1]".to_string(),
+            },
+            "shuffle" => match (self.queue.get_shuffle(), nerdfont) {
+                (false, _) => String::new(),
+                (true, true) => "\u{f049d}".to_string(),
+                (true, false) => "[Z]".to_string(),
+            },
+            "volume" => format!("{}%", self.volume_percent()),
+            "elapsed" => current.map(|_| ms_to_hms(elapsed_ms)).unwrap_or_default(),
+            "remaining" => current
+                .map(|t| ms_to_hms(t.duration().saturating_sub(elapsed_ms)))
+                .unwrap_or_default(),
+            "queue_pos" => self
+                .queue
+                .get_current_index()
+                .map(|index| format!("{}/{}", index + 1, self.queue.len()))
+                .unwrap_or_default(),
+            "device" => {
+                if self.spotify.is_remote() {
+                    self.library
+                        .cfg
+                        .values()
+                        .connect_name
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_CONNECT_NAME.to_string())
+                } else {
+                    String::new()
+                }
+            }
+            // Everything else is passed on to the track formatting.
+            _ => {
+                return current.map_or(Some(String::new()), |t| {
+                    t.placeholder(name, argument, &self.library)
+                });
+            }
+        };
+        Some(value)
+    }
+
+    /// Render the text lines of the statusbar, split into their left, center and right segments.
+    fn render_lines(&self) -> Vec<[String; 3]> {
+        let current = self.queue.get_current();
+        let lookup =
+            |name: &str, argument: Option<&str>| self.placeholder(name, argument, current.as_ref());
+
+        self.template()
+            .lines()
+            .iter()
+            .map(|line| {
+                [&line.left, &line.center, &line.right].map(|segment| {
+                    template::render(segment.as_deref().unwrap_or_default(), &lookup)
+                })
+            })
+            .collect()
+    }
 }
 
 impl View for StatusBar {
@@ -89,114 +190,53 @@ impl View for StatusBar {
             return;
         }
 
+        let palette = &printer.theme.palette;
         let style_bar = ColorStyle::new(
-            ColorType::Color(*printer.theme.palette.custom("statusbar_progress").unwrap()),
+            ColorType::Color(*palette.custom("statusbar_progress").unwrap()),
             ColorType::Palette(PaletteColor::Background),
         );
         let style_bar_bg = ColorStyle::new(
-            ColorType::Color(
-                *printer
-                    .theme
-                    .palette
-                    .custom("statusbar_progress_bg")
-                    .unwrap(),
-            ),
+            ColorType::Color(*palette.custom("statusbar_progress_bg").unwrap()),
             ColorType::Palette(PaletteColor::Background),
         );
+        let background = ColorType::Color(*palette.custom("statusbar_bg").unwrap());
         let style = ColorStyle::new(
-            ColorType::Color(*printer.theme.palette.custom("statusbar").unwrap()),
-            ColorType::Color(*printer.theme.palette.custom("statusbar_bg").unwrap()),
+            ColorType::Color(*palette.custom("statusbar").unwrap()),
+            background,
         );
+        let segment_styles =
+            ["statusbar_left", "statusbar_center", "statusbar_right"].map(|name| {
+                ColorStyle::new(ColorType::Color(*palette.custom(name).unwrap()), background)
+            });
 
-        printer.print(
-            (0, 0),
-            &vec![' '; printer.size.x].into_iter().collect::<String>(),
-        );
-        printer.with_color(style, |printer| {
-            printer.print(
-                (0, 1),
-                &vec![' '; printer.size.x].into_iter().collect::<String>(),
-            );
-        });
-
-        printer.with_color(style, |printer| {
-            printer.print((1, 1), self.playback_indicator());
-        });
-
-        let updating = if !*self.library.is_done.read().unwrap() {
-            if self.use_nerdfont() {
-                "\u{f04e6} "
-            } else {
-                "[U] "
-            }
-        } else {
-            ""
-        };
-
-        let repeat = if self.use_nerdfont() {
-            match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "\u{f0456} ",
-                RepeatSetting::RepeatTrack => "\u{f0458} ",
-            }
-        } else {
-            match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "[R] ",
-                RepeatSetting::RepeatTrack => "[R1] ",
-            }
-        };
+        let text_offset = if self.show_progress() {
+            printer.print((0, 0), &" ".repeat(printer.size.x));
+            printer.with_color(style_bar_bg, |printer| {
+                printer.print((0, 0), &"┉".repeat(printer.size.x));
+            });
 
-        let shuffle = if self.queue.get_shuffle() {
-            if self.use_nerdfont() {
-                "\u{f049d} "
-            } else {
-                "[Z] "
+            if let Some(t) = self.queue.get_current() {
+                let elapsed_ms = self.spotify.get_current_progress().as_millis() as u32;
+                printer.with_color(style_bar, |printer| {
+                    let duration_width = elapsed_ms
+                        .checked_mul(printer.size.x as u32)
+                        .and_then(|v| v.checked_div(t.duration()))
+                        .unwrap_or(0) as usize;
+                    printer.print((0, 0), &"━".repeat(duration_width + 1));
+                });
             }
+            1
         } else {
-            ""
+            0
         };
 
-        let volume = self.volume_display();
-
-        printer.with_color(style_bar_bg, |printer| {
-            printer.print((0, 0), &"┉".repeat(printer.size.x));
-        });
-
-        let elapsed = self.spotify.get_current_progress();
-        let elapsed_ms = elapsed.as_millis() as u32;
-
-        let formatted_elapsed = ms_to_hms(elapsed.as_millis().try_into().unwrap_or(0));
-
-        let playback_duration_status = match self.queue.get_current() {
-            Some(ref t) => format!("{} / {}", formatted_elapsed, t.duration_str()),
-            None => "".to_string(),
-        };
-
-        let right = updating.to_string()
-            + repeat
-            + shuffle
-            // + saved
-            + &playback_duration_status
-            + &volume;
-        let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
-
-        printer.with_color(style, |printer| {
-            if let Some(ref t) = self.queue.get_current() {
-                printer.print((4, 1), &self.format_track(t));
-            }
-            printer.print((offset, 1), &right);
-        });
-
-        if let Some(t) = self.queue.get_current() {
-            printer.with_color(style_bar, |printer| {
-                let duration_width = elapsed_ms
-                    .checked_mul(printer.size.x as u32)
-                    .and_then(|v| v.checked_div(t.duration()))
-                    .unwrap_or(0) as usize;
-                printer.print((0, 0), &"━".repeat(duration_width + 1));
-            });
-        }
+        draw_lines(
+            printer,
+            text_offset,
+            &self.render_lines(),
+            style,
+            segment_styles,
+        );
     }
 
     fn layout(&mut self, size: Vec2) {
@@ -204,7 +244,9 @@ impl View for StatusBar {
     }
 
     fn required_size(&mut self, constraint: Vec2) -> Vec2 {
-        Vec2::new(constraint.x, 2)
+        let template = self.template();
+        let progress = usize::from(template.progress.unwrap_or(true));
+        Vec2::new(constraint.x, progress + template.lines().len())
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
@@ -215,9 +257,15 @@ impl View for StatusBar {
         } = event
         {
             let position = position - offset;
-            let volume_len = self.volume_display().len();
-
-            if position.y == 0 {
+            // The volume is changed over the right segment of the first line, where the default
+            // template shows it.
+            let text_offset = usize::from(self.show_progress());
+            let volume_len = self
+                .render_lines()
+                .first()
+                .map_or(0, |[_, _, right]| right.width());
+
+            if position.y == 0 && self.show_progress() {
                 if event == MouseEvent::WheelUp {
                     self.spotify.seek_relative(-500);
                 }
@@ -233,7 +281,9 @@ impl View for StatusBar {
                     let new = playable.duration() as f32 * f;
                     self.spotify.seek(new as u32);
                 }
-            } else if self.last_size.x - position.x < volume_len {
+            } else if position.y == text_offset
+                && self.last_size.x.saturating_sub(position.x) <= volume_len
+            {
                 if event == MouseEvent::WheelUp {
                     let volume = self
                         .spotify
@@ -261,3 +311,112 @@ impl View for StatusBar {
         }
     }
 }
+
+/// Draw the text `lines` starting at row `offset`. Every line is filled with `style` first, and its
+/// left, center and right segment are drawn with the respective `segment_styles`.
+fn draw_lines(
+    printer: &Printer<'_, '_>,
+    offset: usize,
+    lines: &[[String; 3]],
+    style: ColorStyle,
+    segment_styles: [ColorStyle; 3],
+) {
+    for (y, line) in lines.iter().enumerate() {
+        let y = y + offset;
+        printer.with_color(style, |printer| {
+            printer.print((0, y), &" ".repeat(printer.size.x));
+        });
+        for ((x, text), style) in place_segments(line, printer.size.x)
+            .into_iter()
+            .zip(segment_styles)
+        {
+            printer.with_color(style, |printer| printer.print((x, y), &text));
+        }
+    }
+}
+
+/// Compute the positions of the left, center and right segment of a line that is `width` columns
+/// wide. The right segment takes precedence: the left segment is truncated in front of it and the
+/// center segment is hidden if it doesn't fit between the two.
+fn place_segments(segments: &[String; 3], width: usize) -> [(usize, String); 3] {
+    let [left, center, right] = segments;
+
+    let right = template::truncate(right, width);
+    let right_offset = width - right.width();
+    let left = template::truncate(left, right_offset);
+    let center_offset = width.saturating_sub(center.width()) / 2;
+    let center = if center_offset >= left.width() && center_offset + center.width() <= right_offset
+    {
+        center.clone()
+    } else {
+        String::new()
+    };
+
+    [(0, left), (center_offset, center), (right_offset, right)]
+}
+
+#[cfg(test)]
+mod tests {
+    use cursive::backends::puppet::Backend;
+    use cursive::backends::puppet::observed::{GraphemePart, ObservedScreen};
+    use cursive::views::Canvas;
+
+    use super::*;
+
+    /// The text in row `y` of `screen`.
+    fn row(screen: &ObservedScreen, y: usize) -> String {
+        (0..screen.size().x)
+            .map(|x| match &screen[Vec2::new(x, y)] {
+                Some(cell) => match &cell.letter {
+                    GraphemePart::Begin(text) => text.clone(),
+                    GraphemePart::Continuation => String::new(),
+                },
+                None => " ".to_string(),
+            })
+            .collect()
+    }
+
+    /// Render a line into a buffer that is `width` columns wide.
+    fn render(segments: [&str; 3], width: usize) -> String {
+        let mut buffer = vec![' '; width];
+        for (x, text) in place_segments(&segments.map(String::from), width) {
+            for (i, c) in text.chars().enumerate() {
+                buffer[x + i] = c;
+            }
+        }
+        buffer.into_iter().collect()
+    }
+
+    #[test]
+    fn places_segments() {
+        assert_eq!(render(["ab", "cd", "ef"], 10), "ab  cd  ef");
+        assert_eq!(render(["ab", "", "ef"], 6), "ab  ef");
+    }
+
+    #[test]
+    fn draws_lines_below_the_progress_bar() {
+        let lines = vec![
+            ["left".to_string(), "mid".to_string(), "right".to_string()],
+            ["a".to_string(), String::new(), "[50%]".to_string()],
+        ];
+        let backend = Backend::init(Some(Vec2::new(20, 3)));
+        let screens = backend.stream();
+        let mut siv = cursive::Cursive::new().into_runner(backend);
+        siv.add_fullscreen_layer(Canvas::new(lines).with_draw(|lines, printer| {
+            let style = ColorStyle::primary();
+            draw_lines(printer, 1, lines, style, [style; 3])
+        }));
+        siv.refresh();
+
+        let screen = screens.try_iter().last().unwrap();
+        assert_eq!(row(&screen, 0).trim(), "");
+        assert_eq!(row(&screen, 1), "left    mid    right");
+        assert_eq!(row(&screen, 2), format!("a{}[50%]", " ".repeat(14)));
+    }
+
+    #[test]
+    fn right_segment_takes_precedence() {
+        assert_eq!(render(["abcdef", "x", "123"], 6), "ab…123");
+        assert_eq!(render(["", "", "1234"], 3), "12…");
+    }
+}
@@ -187,9 +187,25 @@ impl View for TabbedView {
 }
 
 impl ViewExt for TabbedView {
+    fn title_sub(&self) -> String {
+        self.tabs
+            .get(self.selected)
+            .map(|tab| tab.title_sub())
+            .unwrap_or_default()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Move(mode, amount) if matches!(mode, MoveMode::Left | MoveMode::Right) => {
+                if let Some(tab) = self.selected_tab_mut()
+                    && tab.moves_horizontally()
+                {
+                    let result = tab.on_command(s, cmd)?;
+                    if !matches!(result, CommandResult::Ignored) {
+                        return Ok(result);
+                    }
+                }
+
                 if matches!(mode, MoveMode::Left) && !self.on_first_tab() {
                     match amount {
                         MoveAmount::Extreme => self.select_first(),
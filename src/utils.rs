@@ -53,10 +53,13 @@ pub fn download(url: String, path: std::path::PathBuf) -> Result<(), std::io::Er
     let mut resp = reqwest::blocking::get(url).map_err(std::io::Error::other)?;
 
     std::fs::create_dir_all(path.parent().unwrap())?;
-    let mut file = std::fs::File::create(path)?;
+
+    // Download to a temporary file first, so that nobody reads a partially downloaded file.
+    let partial_path = path.with_extension("part");
+    let mut file = std::fs::File::create(&partial_path)?;
 
     std::io::copy(&mut resp, &mut file)?;
-    Ok(())
+    std::fs::rename(partial_path, path)
 }
 
 /// Create the application specific runtime directory and return the path to it.
@@ -0,0 +1,324 @@
+//! Validation of the configuration file, which reports all problems at once with their positions
+//! instead of stopping at the first one.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use cursive::theme::Color;
+use librespot_playback::audio_backend;
+use serde::Deserialize;
+use serde::de::{self, Visitor};
+use toml::Spanned;
+
+use crate::command;
+use crate::commands::CommandManager;
+use crate::config::{ConfigTheme, ConfigValues, LibraryTab};
+
+type SpannedTable = BTreeMap<Spanned<String>, Spanned<toml::Value>>;
+
+/// A problem at a position in the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// The tables of the configuration file whose keys need to be located.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Tables {
+    theme: SpannedTable,
+    keybindings: SpannedTable,
+}
+
+/// The light and dark variants of the theme.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ThemeVariants {
+    theme: Variants,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LibraryTabs {
+    library_tabs: Vec<Spanned<toml::Value>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Variants {
+    light: SpannedTable,
+    dark: SpannedTable,
+}
+
+/// Collects the problems of a configuration file.
+struct Checker<'a> {
+    text: &'a str,
+    problems: Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, span: Range<usize>, message: String) {
+        let before = &self.text[..span.start.min(self.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        self.problems.push(Problem {
+            line,
+            column,
+            message,
+        });
+    }
+
+    fn check_settings(&mut self, settings: &SpannedTable) {
+        let known = field_names::<ConfigValues>();
+        for (key, value) in settings {
+            let name = key.get_ref().as_str();
+            match name {
+                _ if !known.contains(&name) => {
+                    self.report(key.span(), format!("Unknown setting \"{name}\""));
+                }
+                "library_tabs" if !value.get_ref().is_array() => {
+                    self.report(value.span(), "\"library_tabs\" must be an array".into());
+                }
+                // The tabs are checked on their own, see [Checker::check_library_tabs].
+                "library_tabs" => {}
+                "backend" => self.check_backend(value),
+                _ => {
+                    // Every setting is checked on its own, so one invalid value doesn't hide the
+                    // problems of the others.
+                    let table =
+                        toml::Table::from_iter([(name.to_string(), value.get_ref().clone())]);
+                    if let Err(e) = toml::Value::Table(table).try_into::<ConfigValues>() {
+                        self.report(value.span(), format!("Invalid \"{name}\": {}", e.message()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_library_tabs(&mut self, tabs: &[Spanned<toml::Value>]) {
+        for tab in tabs {
+            if tab.get_ref().clone().try_into::<LibraryTab>().is_err() {
+                let tab_name = tab.get_ref();
+                self.report(
+                    tab.span(),
+                    format!(
+                        "Unknown library tab {tab_name}, available: tracks, albums, artists, \
+                         playlists, podcasts, browse"
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_backend(&mut self, value: &Spanned<toml::Value>) {
+        let Some(name) = value.get_ref().as_str() else {
+            self.report(value.span(), "\"backend\" must be a string".into());
+            return;
+        };
+        if !audio_backend::BACKENDS
+            .iter()
+            .any(|backend| backend.0 == name)
+        {
+            let available: Vec<&str> = audio_backend::BACKENDS.iter().map(|b| b.0).collect();
+            self.report(
+                value.span(),
+                format!(
+                    "Unsupported audio backend \"{name}\", available: {}",
+                    available.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// Check the colors of a theme table. `variants` tells whether it may contain the light and
+    /// dark variants.
+    fn check_theme(&mut self, theme: &SpannedTable, variants: bool) {
+        let colors = field_names::<ConfigTheme>();
+        for (key, value) in theme {
+            let name = key.get_ref().as_str();
+            if variants && (name == "light" || name == "dark") {
+                continue;
+            }
+            if !colors.contains(&name) {
+                self.report(key.span(), format!("Unknown theme color \"{name}\""));
+            } else if let Some(color) = value.get_ref().as_str()
+                && Color::parse(color).is_none()
+            {
+                self.report(
+                    value.span(),
+                    format!("Invalid color \"{color}\" for \"{name}\""),
+                );
+            }
+        }
+    }
+
+    fn check_keybindings(&mut self, keybindings: &SpannedTable) {
+        for (key, commands) in keybindings {
+            if CommandManager::parse_keybinding(key.get_ref()).is_none() {
+                self.report(key.span(), format!("Unknown key \"{}\"", key.get_ref()));
+            }
+            // Values that aren't strings were already reported as invalid settings.
+            if let Some(input) = commands.get_ref().as_str()
+                && let Err(e) = command::parse(input)
+            {
+                self.report(commands.span(), format!("Invalid command \"{input}\": {e}"));
+            }
+        }
+    }
+}
+
+/// Find all problems in the configuration `text`.
+pub fn check(text: &str) -> Vec<Problem> {
+    let mut checker = Checker {
+        text,
+        problems: Vec::new(),
+    };
+
+    let settings: SpannedTable = match toml::from_str(text) {
+        Ok(settings) => settings,
+        Err(e) => {
+            checker.report(e.span().unwrap_or_default(), e.message().to_string());
+            return checker.problems;
+        }
+    };
+    checker.check_settings(&settings);
+
+    // Tables with values of the wrong type were already reported above.
+    let tables: Tables = toml::from_str(text).unwrap_or_default();
+    let variants: ThemeVariants = toml::from_str(text).unwrap_or_default();
+    let library_tabs: LibraryTabs = toml::from_str(text).unwrap_or_default();
+    checker.check_library_tabs(&library_tabs.library_tabs);
+    checker.check_theme(&tables.theme, true);
+    checker.check_theme(&variants.theme.light, false);
+    checker.check_theme(&variants.theme.dark, false);
+    checker.check_keybindings(&tables.keybindings);
+
+    checker
+        .problems
+        .sort_by_key(|problem| (problem.line, problem.column));
+    checker.problems
+}
+
+/// Find all problems in the configuration file at `path`. A missing file has no problems.
+pub fn check_file(path: &Path) -> Result<Vec<Problem>, std::io::Error> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(check(&text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The names of the fields of the struct `T`, taken from its derived [Deserialize] implementation.
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only the fields are needed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(text: &str) -> Vec<(usize, String)> {
+        check(text)
+            .into_iter()
+            .map(|problem| (problem.line, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_configuration() {
+        let text = r##"
+use_nerdfont = true
+library_tabs = ["albums"]
+
+[theme]
+primary = "#ff0000"
+
+[theme.dark]
+background = "black"
+
+[keybindings]
+"q" = "quit"
+"##;
+        assert_eq!(messages(text), Vec::new());
+    }
+
+    #[test]
+    fn reports_all_problems() {
+        let text = r#"use_nerdfnt = true
+bitrate = "high"
+library_tabs = ["albums", "songs"]
+
+[theme]
+primary = "reddish"
+
+[theme.light]
+backgrond = "white"
+
+[keybindings]
+"q" = "qiut"
+"#;
+        let lines: Vec<usize> = messages(text).iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 6, 9, 12]);
+    }
+
+    #[test]
+    fn locates_unknown_library_tabs() {
+        let text = "library_tabs = [\n    \"albums\",\n    \"songs\",\n    \"artists\",\n]\n";
+        let problems = check(text);
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (3, 5));
+        assert!(problems[0].message.contains("\"songs\""));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let problems = messages("use_nerdfont = true\nbitrate = \n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, 2);
+    }
+
+    #[test]
+    fn finds_field_names() {
+        let fields = field_names::<ConfigValues>();
+        assert!(fields.contains(&"library_tabs"));
+        assert!(fields.contains(&"keybindings"));
+    }
+}